@@ -0,0 +1,265 @@
+//! `#[derive(CliCommand)]`, re-exported by `cli_parser` behind its `derive` feature.
+//!
+//! See the `CliCommand` trait in `cli_parser` for the supported attributes
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Ident,
+    LitChar, LitStr, PathArguments, Result, Type,
+};
+
+#[proc_macro_derive(CliCommand, attributes(command, flag, positional))]
+pub fn derive_cli_command(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Options of `#[command(..)]` on the struct
+#[derive(Default)]
+struct CommandAttr {
+    id: Option<String>,
+    hidden: bool,
+}
+
+/// Options of `#[flag(..)]` on a field
+#[derive(Default)]
+struct FlagAttr {
+    id: Option<String>,
+    required: bool,
+    count: bool,
+    hidden: bool,
+    default: Option<String>,
+    delimiter: Option<char>,
+}
+
+/// How a field is defined and extracted, decided by its type and attributes
+enum FieldKind {
+    Bool,
+    Count,
+    Option(Type),
+    Vec(Type),
+    Value(Type),
+    Positional(Type),
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(name, "CliCommand needs named fields")),
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                name,
+                "CliCommand can only be derived for structs",
+            ))
+        }
+    };
+
+    let command_attr = command_attr(&input.attrs)?;
+    let id = command_attr
+        .id
+        .unwrap_or_else(|| kebab_case(&name.to_string()));
+    let mut definition = quote! { ::cli_parser::Command::new(#id) };
+    if command_attr.hidden {
+        definition.extend(quote! { .hidden() });
+    }
+
+    let mut extractions = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let (kind, attr) = field_kind(&field.ty, &field.attrs)?;
+        if let FieldKind::Positional(ty) = kind {
+            let id = attr.id.unwrap_or_else(|| ident.to_string().to_uppercase());
+            definition.extend(quote! {
+                .positional_arg(
+                    ::cli_parser::Positional::new(#id)
+                        .value_parser(<#ty as ::std::str::FromStr>::from_str)
+                )
+            });
+            extractions.push(quote! {
+                #ident: ::cli_parser::__derive::positional::<#ty>(command, #id)?
+            });
+            continue;
+        }
+
+        let id = attr
+            .id
+            .clone()
+            .unwrap_or_else(|| format!("--{}", ident.to_string().replace('_', "-")));
+        let (flag, extraction) = match kind {
+            FieldKind::Bool => (
+                quote! { ::cli_parser::Flag::new(#id) },
+                quote! { command.is_present(#id) },
+            ),
+            FieldKind::Count => (
+                quote! { ::cli_parser::Flag::new(#id).counted() },
+                quote! { command.occurrences_of(#id) as _ },
+            ),
+            FieldKind::Option(ty) => (
+                quote! {
+                    ::cli_parser::Flag::new(#id)
+                        .value_parser(<#ty as ::std::str::FromStr>::from_str)
+                },
+                quote! { ::cli_parser::__derive::flag_option::<#ty>(command, #id) },
+            ),
+            FieldKind::Vec(ty) => (
+                quote! { ::cli_parser::Flag::new(#id).values_of_type::<#ty>() },
+                quote! { ::cli_parser::__derive::flag_values::<#ty>(command, #id) },
+            ),
+            FieldKind::Value(ty) => (
+                quote! {
+                    ::cli_parser::Flag::new(#id)
+                        .value_parser(<#ty as ::std::str::FromStr>::from_str)
+                },
+                quote! { ::cli_parser::__derive::flag::<#ty>(command, #id)? },
+            ),
+            FieldKind::Positional(_) => unreachable!(),
+        };
+
+        let mut flag = flag;
+        if attr.required {
+            flag.extend(quote! { .required() });
+        }
+        if attr.hidden {
+            flag.extend(quote! { .hidden() });
+        }
+        if let Some(default) = &attr.default {
+            flag.extend(quote! { .default_value(#default) });
+        }
+        if let Some(delimiter) = attr.delimiter {
+            flag.extend(quote! { .value_delimiter(#delimiter) });
+        }
+        definition.extend(quote! { .flag(#flag) });
+        extractions.push(quote! { #ident: #extraction });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::cli_parser::CliCommand for #name #ty_generics #where_clause {
+            fn command() -> ::cli_parser::Command {
+                #definition
+            }
+
+            fn from_command(
+                command: &::cli_parser::Command,
+            ) -> ::std::result::Result<Self, ::cli_parser::ParseError> {
+                ::std::result::Result::Ok(Self {
+                    #(#extractions,)*
+                })
+            }
+        }
+    })
+}
+
+fn command_attr(attrs: &[Attribute]) -> Result<CommandAttr> {
+    let mut out = CommandAttr::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("command")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                out.id = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.path.is_ident("hidden") {
+                out.hidden = true;
+            } else {
+                return Err(meta.error("expected `id` or `hidden`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
+
+fn field_kind(ty: &Type, attrs: &[Attribute]) -> Result<(FieldKind, FlagAttr)> {
+    let mut out = FlagAttr::default();
+    let mut positional = false;
+    for attr in attrs {
+        if attr.path().is_ident("positional") {
+            positional = true;
+            if !matches!(attr.meta, syn::Meta::Path(_)) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("id") {
+                        out.id = Some(meta.value()?.parse::<LitStr>()?.value());
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `id`"))
+                    }
+                })?;
+            }
+        } else if attr.path().is_ident("flag") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("id") {
+                    out.id = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("required") {
+                    out.required = true;
+                } else if meta.path.is_ident("count") {
+                    out.count = true;
+                } else if meta.path.is_ident("hidden") {
+                    out.hidden = true;
+                } else if meta.path.is_ident("default") {
+                    out.default = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("delimiter") {
+                    out.delimiter = Some(meta.value()?.parse::<LitChar>()?.value());
+                } else {
+                    return Err(meta.error(
+                        "expected `id`, `required`, `count`, `hidden`, `default` or `delimiter`",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+    }
+
+    if positional {
+        return Ok((FieldKind::Positional(ty.clone()), out));
+    }
+    if out.count {
+        return Ok((FieldKind::Count, out));
+    }
+    let kind = match wrapper(ty) {
+        Some(("Option", inner)) => FieldKind::Option(inner),
+        Some(("Vec", inner)) => FieldKind::Vec(inner),
+        _ if is_ident(ty, "bool") => FieldKind::Bool,
+        _ => FieldKind::Value(ty.clone()),
+    };
+    Ok((kind, out))
+}
+
+/// The wrapper name and inner type of `Option<T>` and `Vec<T>`
+fn wrapper(ty: &Type) -> Option<(&'static str, Type)> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    let name = ["Option", "Vec"]
+        .into_iter()
+        .find(|name| segment.ident == Ident::new(name, Span::call_site()))?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some((name, inner.clone())),
+        _ => None,
+    }
+}
+
+fn is_ident(ty: &Type, name: &str) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident(name))
+}
+
+/// `DeployAll` to `deploy-all`
+fn kebab_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('-');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
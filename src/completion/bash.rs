@@ -0,0 +1,193 @@
+use crate::COMPLETE_COMMAND;
+
+use super::{choices, dynamic, ident, key, quote, takes_value, Node, ValueHint};
+
+/// A completion function for bash, walking the complete words to the innermost command,
+/// then completing the value of the previous flag, or the words that may follow the command
+pub(crate) fn script(name: &str, nodes: &[Node]) -> String {
+    let function = format!("_{}", ident(name));
+    let key = |path: &[&str]| key(name, path);
+
+    let mut out = format!(
+        concat!(
+            "{}() {{\n",
+            "    local cur prev cmd i\n",
+            "    COMPREPLY=()\n",
+            "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n",
+            "    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n",
+            "    cmd=\"{}\"\n",
+            "    for ((i = 1; i < COMP_CWORD; i++)); do\n",
+            "        case \"${{cmd}}__${{COMP_WORDS[i]}}\" in\n",
+        ),
+        function,
+        key(&[])
+    );
+    for node in nodes.iter().filter(|n| !n.path.is_empty()) {
+        let key = key(&node.path);
+        out.push_str(&format!(
+            "            {}) cmd={} ;;\n",
+            quote(&key),
+            quote(&key)
+        ));
+    }
+    out.push_str("        esac\n    done\n\n    case \"${cmd}\" in\n");
+
+    for node in nodes {
+        out.push_str(&format!("        {})\n", quote(&key(&node.path))));
+        let values: Vec<String> = node
+            .flags
+            .iter()
+            .filter(|f| takes_value(f))
+            .map(|flag| match choices(flag) {
+                _ if dynamic(flag) => format!(
+                    concat!(
+                        "                {}) COMPREPLY=($(compgen -W \"$(\"${{COMP_WORDS[0]}}\" {} ",
+                        "\"${{COMP_WORDS[@]:1:COMP_CWORD}}\" 2>/dev/null | cut -f1)\" -- \"${{cur}}\")); return 0 ;;\n"
+                    ),
+                    quote(&flag.id),
+                    COMPLETE_COMMAND
+                ),
+                Some(choices) => format!(
+                    "                {}) COMPREPLY=($(compgen -W {} -- \"${{cur}}\")); return 0 ;;\n",
+                    quote(&flag.id),
+                    quote(&choices.join(" "))
+                ),
+                None => match flag.value_hint {
+                    Some(hint) => format!(
+                        "                {}) {}COMPREPLY=($(compgen {} -- \"${{cur}}\")); return 0 ;;\n",
+                        quote(&flag.id),
+                        match hint {
+                            ValueHint::FilePath | ValueHint::DirPath => "compopt -o filenames; ",
+                            _ => "",
+                        },
+                        action(hint)
+                    ),
+                    // Left to the default completion of the shell, like file names
+                    None => format!("                {}) return 0 ;;\n", quote(&flag.id)),
+                },
+            })
+            .collect();
+        if !values.is_empty() {
+            out.push_str("            case \"${prev}\" in\n");
+            out.push_str(&values.concat());
+            out.push_str("            esac\n");
+        }
+
+        let mut words: Vec<&str> = node.extra.iter().map(String::as_str).collect();
+        words.extend(node.commands.iter().map(|c| c.id.as_str()));
+        words.extend(node.flags.iter().map(|f| f.id.as_str()));
+        out.push_str(&format!(
+            "            COMPREPLY=($(compgen -W {} -- \"${{cur}}\"))\n            ;;\n",
+            quote(&words.join(" "))
+        ));
+    }
+
+    out.push_str(&format!(
+        "    esac\n}}\n\ncomplete -F {} -o bashdefault -o default {}\n",
+        function, name
+    ));
+    out
+}
+
+/// The `compgen` option generating the words for `hint`
+fn action(hint: ValueHint) -> &'static str {
+    match hint {
+        ValueHint::FilePath => "-f",
+        ValueHint::DirPath => "-d",
+        ValueHint::Hostname => "-A hostname",
+        ValueHint::Username => "-u",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{validate::one_of, *};
+
+    #[test]
+    fn test_bash() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("git")
+            .command(
+                Command::new("remote")
+                    .flag(Flag::new("--verbose"))
+                    .subcommand(
+                        Command::new("add")
+                            .positional()
+                            .flag(
+                                Flag::new("--mirror")
+                                    .positional()
+                                    .validate_with(one_of(["fetch", "push"])),
+                            )
+                            .complete_extra("origin")
+                            .complete_exclude("--color"),
+                    )
+                    .subcommand(Command::new("prune").hidden()),
+            )
+            .global_flag(Flag::new("--color"))
+            .global_flag(Flag::new("--git-dir").positional())
+            .global_flag(Flag::new("--debug").hidden());
+
+        assert_eq!(
+            generate_completions(Shell::Bash, &parser),
+            concat!(
+                "_git() {\n",
+                "    local cur prev cmd i\n",
+                "    COMPREPLY=()\n",
+                "    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n",
+                "    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n",
+                "    cmd=\"git\"\n",
+                "    for ((i = 1; i < COMP_CWORD; i++)); do\n",
+                "        case \"${cmd}__${COMP_WORDS[i]}\" in\n",
+                "            'git__remote') cmd='git__remote' ;;\n",
+                "            'git__remote__add') cmd='git__remote__add' ;;\n",
+                "        esac\n",
+                "    done\n",
+                "\n",
+                "    case \"${cmd}\" in\n",
+                "        'git')\n",
+                "            case \"${prev}\" in\n",
+                "                '--git-dir') return 0 ;;\n",
+                "            esac\n",
+                "            COMPREPLY=($(compgen -W 'remote --color --git-dir' -- \"${cur}\"))\n",
+                "            ;;\n",
+                "        'git__remote')\n",
+                "            case \"${prev}\" in\n",
+                "                '--git-dir') return 0 ;;\n",
+                "            esac\n",
+                "            COMPREPLY=($(compgen -W 'add --verbose --color --git-dir' -- \"${cur}\"))\n",
+                "            ;;\n",
+                "        'git__remote__add')\n",
+                "            case \"${prev}\" in\n",
+                "                '--mirror') COMPREPLY=($(compgen -W 'fetch push' -- \"${cur}\")); return 0 ;;\n",
+                "                '--git-dir') return 0 ;;\n",
+                "            esac\n",
+                "            COMPREPLY=($(compgen -W 'origin --mirror --verbose --git-dir' -- \"${cur}\"))\n",
+                "            ;;\n",
+                "    esac\n",
+                "}\n",
+                "\n",
+                "complete -F _git -o bashdefault -o default git\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_value_hints() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("ssh")
+            .global_flag(Flag::new("-F").positional().value_hint(ValueHint::FilePath))
+            .global_flag(
+                Flag::new("--host")
+                    .positional()
+                    .value_hint(ValueHint::Hostname),
+            );
+
+        let script = generate_completions(Shell::Bash, &parser);
+        assert!(script.contains(concat!(
+            "'--host') COMPREPLY=($(compgen -A hostname -- \"${cur}\")); return 0 ;;\n",
+            "                '-F') compopt -o filenames; COMPREPLY=($(compgen -f -- \"${cur}\")); return 0 ;;\n",
+        )));
+        let script = generate_completions(Shell::Fish, &parser);
+        assert!(script.contains("-l 'host' -x -a '(__fish_print_hostnames)'"));
+    }
+}
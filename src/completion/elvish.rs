@@ -0,0 +1,180 @@
+use super::{choices, key, takes_value, Node};
+
+/// An argument completer for elvish, walking the complete words to the innermost command,
+/// then completing the value of the previous flag, or the commands and flags that may
+/// follow the command, displayed with their descriptions
+pub(crate) fn script(name: &str, nodes: &[Node]) -> String {
+    let key = |path: &[&str]| key(name, path);
+
+    let mut out = format!(
+        concat!(
+            "set edit:completion:arg-completer[{}] = {{|@words|\n",
+            "    var cmd = {}\n",
+            "    var commands = [",
+        ),
+        quote(name),
+        quote(&key(&[]))
+    );
+    let commands: Vec<String> = nodes
+        .iter()
+        .filter(|n| !n.path.is_empty())
+        .map(|n| format!("&{}=$true", quote(&key(&n.path))))
+        .collect();
+    match commands.is_empty() {
+        true => out.push('&'),
+        false => out.push_str(&commands.join(" ")),
+    }
+    out.push_str(concat!(
+        "]\n",
+        "    for word $words[1..-1] {\n",
+        "        if (has-key $commands $cmd'__'$word) {\n",
+        "            set cmd = $cmd'__'$word\n",
+        "        }\n",
+        "    }\n",
+        "    var previous = $words[-2]\n",
+        "\n",
+        "    var values = [\n",
+    ));
+
+    // Flags with known values complete those, flags taking other values complete file names
+    for node in nodes {
+        let values: Vec<String> = node
+            .flags
+            .iter()
+            .filter(|f| takes_value(f))
+            .map(|flag| {
+                let choices: Vec<String> = choices(flag)
+                    .map(|c| c.iter().map(|c| quote(c)).collect())
+                    .unwrap_or_default();
+                format!("&{}=[{}]", quote(&flag.id), choices.join(" "))
+            })
+            .collect();
+        if !values.is_empty() {
+            out.push_str(&format!(
+                "        &{}=[{}]\n",
+                quote(&key(&node.path)),
+                values.join(" ")
+            ));
+        }
+    }
+    out.push_str("    ]\n    var candidates = [\n");
+
+    for node in nodes {
+        let mut candidates: Vec<String> = node.extra.iter().map(|w| candidate(w, None)).collect();
+        candidates.extend(
+            node.commands
+                .iter()
+                .map(|c| candidate(&c.id, c.about.as_deref())),
+        );
+        candidates.extend(
+            node.flags
+                .iter()
+                .map(|f| candidate(&f.id, f.help.as_deref())),
+        );
+        out.push_str(&format!("        &{}=[\n", quote(&key(&node.path))));
+        for candidate in candidates {
+            out.push_str(&format!("            {}\n", candidate));
+        }
+        out.push_str("        ]\n");
+    }
+
+    out.push_str(concat!(
+        "    ]\n",
+        "\n",
+        "    if (and (has-key $values $cmd) (has-key $values[$cmd] $previous)) {\n",
+        "        var choices = $values[$cmd][$previous]\n",
+        "        if (== (count $choices) 0) {\n",
+        "            edit:complete-filename $words[-1]\n",
+        "        } else {\n",
+        "            all $choices\n",
+        "        }\n",
+        "    } else {\n",
+        "        for candidate $candidates[$cmd] {\n",
+        "            edit:complex-candidate $candidate[0] &display=$candidate[1]\n",
+        "        }\n",
+        "    }\n",
+        "}\n",
+    ));
+    out
+}
+
+/// A `[word display]` pair, displaying the first line of `description` after the word
+fn candidate(word: &str, description: Option<&str>) -> String {
+    let display = match description.and_then(|d| d.lines().next()) {
+        Some(line) => format!("{}  {}", word, line),
+        None => word.to_string(),
+    };
+    format!("[{} {}]", quote(word), quote(&display))
+}
+
+/// `word` as a single quoted elvish string, where `'` is doubled
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{validate::one_of, *};
+
+    #[test]
+    fn test_elvish() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("git")
+            .command(
+                Command::new("remote").about("Manage 'remotes'").flag(
+                    Flag::new("--mirror")
+                        .positional()
+                        .validate_with(one_of(["fetch", "push"])),
+                ),
+            )
+            .global_flag(
+                Flag::new("--git-dir")
+                    .positional()
+                    .help("Path to the repository"),
+            );
+
+        assert_eq!(
+            generate_completions(Shell::Elvish, &parser),
+            concat!(
+                "set edit:completion:arg-completer['git'] = {|@words|\n",
+                "    var cmd = 'git'\n",
+                "    var commands = [&'git__remote'=$true]\n",
+                "    for word $words[1..-1] {\n",
+                "        if (has-key $commands $cmd'__'$word) {\n",
+                "            set cmd = $cmd'__'$word\n",
+                "        }\n",
+                "    }\n",
+                "    var previous = $words[-2]\n",
+                "\n",
+                "    var values = [\n",
+                "        &'git'=[&'--git-dir'=[]]\n",
+                "        &'git__remote'=[&'--mirror'=['fetch' 'push'] &'--git-dir'=[]]\n",
+                "    ]\n",
+                "    var candidates = [\n",
+                "        &'git'=[\n",
+                "            ['remote' 'remote  Manage ''remotes''']\n",
+                "            ['--git-dir' '--git-dir  Path to the repository']\n",
+                "        ]\n",
+                "        &'git__remote'=[\n",
+                "            ['--mirror' '--mirror']\n",
+                "            ['--git-dir' '--git-dir  Path to the repository']\n",
+                "        ]\n",
+                "    ]\n",
+                "\n",
+                "    if (and (has-key $values $cmd) (has-key $values[$cmd] $previous)) {\n",
+                "        var choices = $values[$cmd][$previous]\n",
+                "        if (== (count $choices) 0) {\n",
+                "            edit:complete-filename $words[-1]\n",
+                "        } else {\n",
+                "            all $choices\n",
+                "        }\n",
+                "    } else {\n",
+                "        for candidate $candidates[$cmd] {\n",
+                "            edit:complex-candidate $candidate[0] &display=$candidate[1]\n",
+                "        }\n",
+                "    }\n",
+                "}\n",
+            )
+        );
+    }
+}
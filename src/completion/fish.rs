@@ -0,0 +1,147 @@
+use crate::COMPLETE_COMMAND;
+
+use super::{choices, dynamic, ident, key, takes_value, Node, ValueHint};
+
+/// `complete -c` rules for fish, conditioned on the innermost command of the complete
+/// words, found by a helper function. Flags are described with their
+/// [Flag::help](crate::Flag::help), and flags with known values complete those values
+pub(crate) fn script(name: &str, nodes: &[Node]) -> String {
+    let function = format!("__fish_{}_command", ident(name));
+    let key = |path: &[&str]| key(name, path);
+
+    let mut out = format!(
+        concat!(
+            "function {}\n",
+            "    set -l cmd {}\n",
+            "    for word in (commandline -opc)[2..-1]\n",
+            "        switch \"$cmd\"__\"$word\"\n",
+        ),
+        function,
+        key(&[])
+    );
+    for node in nodes.iter().filter(|n| !n.path.is_empty()) {
+        let key = quote(&key(&node.path));
+        out.push_str(&format!(
+            "            case {}\n                set cmd {}\n",
+            key, key
+        ));
+    }
+    out.push_str("        end\n    end\n    echo $cmd\nend\n");
+
+    for node in nodes {
+        let prefix = format!(
+            "complete -c {} -n {}",
+            name,
+            quote(&format!("test ({}) = {}", function, key(&node.path)))
+        );
+        out.push('\n');
+        for word in node.extra {
+            out.push_str(&format!("{} -f -a {}\n", prefix, quote(word)));
+        }
+        for command in &node.commands {
+            out.push_str(&format!("{} -f -a {}", prefix, quote(&command.id)));
+            out.push_str(&description(command.about.as_deref()));
+            out.push('\n');
+        }
+        for flag in &node.flags {
+            let option = match flag.id.strip_prefix("--") {
+                Some(long) => format!("-l {}", quote(long)),
+                None => format!("-s {}", quote(flag.id.trim_start_matches('-'))),
+            };
+            out.push_str(&format!("{} {}", prefix, option));
+            if takes_value(flag) {
+                match choices(flag) {
+                    _ if dynamic(flag) => {
+                        let complete = format!(
+                            "({} {} (commandline -opc)[2..-1] (commandline -ct) | string split -f1 \\t)",
+                            name, COMPLETE_COMMAND
+                        );
+                        out.push_str(&format!(" -x -a {}", quote(&complete)));
+                    }
+                    Some(choices) => out.push_str(&format!(" -x -a {}", quote(&choices.join(" ")))),
+                    None => match flag.value_hint {
+                        Some(ValueHint::DirPath) => {
+                            out.push_str(" -x -a '(__fish_complete_directories (commandline -ct))'")
+                        }
+                        Some(ValueHint::Hostname) => {
+                            out.push_str(" -x -a '(__fish_print_hostnames)'")
+                        }
+                        Some(ValueHint::Username) => {
+                            out.push_str(" -x -a '(__fish_complete_users)'")
+                        }
+                        // Left to the file completion of the shell
+                        Some(ValueHint::FilePath) | None => out.push_str(" -r -F"),
+                    },
+                }
+            }
+            out.push_str(&description(flag.help.as_deref()));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// The `-d` option with the first line of `text`, if any
+fn description(text: Option<&str>) -> String {
+    match text.and_then(|t| t.lines().next()) {
+        Some(line) => format!(" -d {}", quote(line)),
+        None => String::new(),
+    }
+}
+
+/// `word` as a single quoted fish word, where only `\` and `'` are escaped
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{validate::one_of, *};
+
+    #[test]
+    fn test_fish() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("git")
+            .command(
+                Command::new("remote")
+                    .about("Manage 'remotes'")
+                    .complete_extra("origin")
+                    .flag(
+                        Flag::new("--mirror")
+                            .positional()
+                            .validate_with(one_of(["fetch", "push"])),
+                    ),
+            )
+            .global_flag(
+                Flag::new("--git-dir")
+                    .positional()
+                    .help("Path to the repository"),
+            )
+            .global_flag(Flag::new("-v"));
+
+        assert_eq!(
+            generate_completions(Shell::Fish, &parser),
+            concat!(
+                "function __fish_git_command\n",
+                "    set -l cmd git\n",
+                "    for word in (commandline -opc)[2..-1]\n",
+                "        switch \"$cmd\"__\"$word\"\n",
+                "            case 'git__remote'\n",
+                "                set cmd 'git__remote'\n",
+                "        end\n",
+                "    end\n",
+                "    echo $cmd\n",
+                "end\n",
+                "\n",
+                "complete -c git -n 'test (__fish_git_command) = git' -f -a 'remote' -d 'Manage \\'remotes\\''\n",
+                "complete -c git -n 'test (__fish_git_command) = git' -l 'git-dir' -r -F -d 'Path to the repository'\n",
+                "complete -c git -n 'test (__fish_git_command) = git' -s 'v'\n",
+                "\n",
+                "complete -c git -n 'test (__fish_git_command) = git__remote' -f -a 'origin'\n",
+                "complete -c git -n 'test (__fish_git_command) = git__remote' -l 'mirror' -x -a 'fetch push'\n",
+                "complete -c git -n 'test (__fish_git_command) = git__remote' -l 'git-dir' -r -F -d 'Path to the repository'\n",
+                "complete -c git -n 'test (__fish_git_command) = git__remote' -s 'v'\n",
+            )
+        );
+    }
+}
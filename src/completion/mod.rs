@@ -0,0 +1,275 @@
+//! Completion scripts for shells, generated from the command tree,
+//! see [generate_completions]
+mod bash;
+mod elvish;
+mod fish;
+mod nushell;
+mod powershell;
+mod zsh;
+
+use std::str::FromStr;
+
+use crate::{help::display_key, validate::one_of, CliParser, Command, Flag, FlagKind, Positional};
+
+/// Id of the command printing the completion script of the shell named by its positional,
+/// see [CliParser::completions_command]
+pub const COMPLETIONS_COMMAND: &str = "completions";
+
+/// A shell to generate a completion script for, see [generate_completions].
+/// Parsed from its lowercase name, like `powershell`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    /// With the descriptions of commands and flags
+    Zsh,
+    /// With the descriptions of commands and flags
+    Fish,
+    /// With the descriptions of commands and flags as tooltips
+    PowerShell,
+    /// `extern` signatures, with the descriptions of commands and flags
+    Nushell,
+    /// With the descriptions of commands and flags
+    Elvish,
+}
+
+impl Shell {
+    /// Every supported shell
+    pub const ALL: [Shell; 6] = [
+        Shell::Bash,
+        Shell::Zsh,
+        Shell::Fish,
+        Shell::PowerShell,
+        Shell::Nushell,
+        Shell::Elvish,
+    ];
+
+    /// The lowercase name of the shell, like `bash`
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::PowerShell => "powershell",
+            Shell::Nushell => "nushell",
+            Shell::Elvish => "elvish",
+        }
+    }
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Shell::ALL
+            .into_iter()
+            .find(|shell| shell.name() == name)
+            .ok_or_else(|| format!("unknown shell '{}'", name))
+    }
+}
+
+/// The kind of value a flag takes, completed by the shell's own completion,
+/// see [Flag::value_hint]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueHint {
+    /// A path to a file, or a directory leading to one
+    FilePath,
+    /// A path to a directory
+    DirPath,
+    /// A host name, like the ones in `/etc/hosts`
+    Hostname,
+    /// A user name on the system
+    Username,
+}
+
+impl ValueHint {
+    /// Name of the hint in specs, see [crate::spec]
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ValueHint::FilePath => "file_path",
+            ValueHint::DirPath => "dir_path",
+            ValueHint::Hostname => "hostname",
+            ValueHint::Username => "username",
+        }
+    }
+}
+
+/// Generate the completion script of `parser` for `shell`, completing the visible commands,
+/// subcommands, flags, and the values of flags validated with [crate::validate::one_of].
+///
+/// The script completes the program named by [CliParser::bin_name], and honors
+/// [Command::complete_exclude] and [Command::complete_extra]. Bash, zsh, and fish
+/// scripts complete the values of flags with a [Flag::complete_with] completer by
+/// running the program, see [CliParser::dynamic_completion]. Flags with a [Flag::value_hint]
+/// complete their values with the native completion of the shell, where it has one
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let parser = CliParser::from_args(std::iter::empty())
+///     .bin_name("app")
+///     .command(Command::new("deploy"));
+///
+/// let script = generate_completions(Shell::Bash, &parser);
+/// assert!(script.ends_with("complete -F _app -o bashdefault -o default app\n"));
+/// ```
+pub fn generate_completions<It>(shell: Shell, parser: &CliParser<It>) -> String
+where
+    It: Iterator<Item = String>,
+{
+    let name = parser.program_name();
+    let nodes = nodes(parser);
+    match shell {
+        Shell::Bash => bash::script(&name, &nodes),
+        Shell::Zsh => zsh::script(&name, &nodes),
+        Shell::Fish => fish::script(&name, &nodes),
+        Shell::PowerShell => powershell::script(&name, &nodes),
+        Shell::Nushell => nushell::script(&name, &nodes),
+        Shell::Elvish => elvish::script(&name, &nodes),
+    }
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Register the [COMPLETIONS_COMMAND] command, so `app completions zsh` prints the
+    /// completion script for zsh, see [generate_completions], and [Shell::name] for the names
+    /// of the shells. [CliParser::parse_or_exit] prints the script and exits when the
+    /// command is matched
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["completions", "fish"])
+    ///     .completions_command()
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.id, COMPLETIONS_COMMAND);
+    /// assert_eq!(cmd.positional_as::<Shell>("SHELL"), Some(&Shell::Fish));
+    /// ```
+    pub fn completions_command(mut self) -> Self {
+        let shell = Positional::new("SHELL")
+            .validate_with(one_of(Shell::ALL.map(Shell::name)))
+            .value_parser(Shell::from_str);
+        self.add_command(
+            Command::new(COMPLETIONS_COMMAND)
+                .about("Print the completion script of a shell")
+                .positional_arg(shell),
+        );
+        self.completions_command = true;
+        self
+    }
+}
+
+/// A visible command, or the program itself, with what may follow it
+pub(crate) struct Node<'a> {
+    // Ids of the commands leading here, empty for the program itself
+    pub(crate) path: Vec<&'a str>,
+    // `None` for the program itself
+    pub(crate) command: Option<&'a Command>,
+    // Visible subcommands, in display order
+    pub(crate) commands: Vec<&'a Command>,
+    // Visible flags of this command, the commands before it, and the global flags,
+    // in display order, without the ones it excludes from completion
+    pub(crate) flags: Vec<&'a Flag>,
+    // See [Command::complete_extra]
+    pub(crate) extra: &'a [String],
+}
+
+/// The program, then every visible command, depth first and in display order
+fn nodes<It>(parser: &CliParser<It>) -> Vec<Node<'_>>
+where
+    It: Iterator<Item = String>,
+{
+    let visible_flags = |flags: &mut Vec<&Flag>| {
+        flags.retain(|f| !f.hidden && parser.has_capabilities(&f.capabilities));
+        flags.sort_by(|a, b| {
+            display_key(a.display_order, &a.id).cmp(&display_key(b.display_order, &b.id))
+        });
+    };
+
+    let mut global: Vec<&Flag> = parser.global_flags.values().collect();
+    visible_flags(&mut global);
+    let mut nodes = vec![Node {
+        path: Vec::new(),
+        command: None,
+        commands: parser.visible_commands(&parser.commands),
+        flags: global.clone(),
+        extra: &[],
+    }];
+
+    // Commands to visit, with the path and the flags of the commands before them
+    let mut stack: Vec<(Vec<&str>, Vec<&Flag>, &Command)> = parser
+        .visible_commands(&parser.commands)
+        .into_iter()
+        .rev()
+        .map(|c| (Vec::new(), Vec::new(), c))
+        .collect();
+    while let Some((mut path, mut inherited, command)) = stack.pop() {
+        path.push(&command.id);
+        inherited.extend(command.flags.values());
+        visible_flags(&mut inherited);
+        let subcommands = parser.visible_commands(&command.subcommands);
+        for sub in subcommands.iter().rev() {
+            stack.push((path.clone(), inherited.clone(), sub));
+        }
+
+        let mut flags = inherited.clone();
+        flags.extend(global.iter().copied());
+        flags.retain(|f| !command.complete_exclude.contains(&f.id));
+        nodes.push(Node {
+            path,
+            command: Some(command),
+            commands: subcommands,
+            flags,
+            extra: &command.complete_extra,
+        });
+    }
+    nodes
+}
+
+/// If the flag takes a value
+fn takes_value(flag: &Flag) -> bool {
+    flag.kind == FlagKind::Value
+}
+
+/// If the values of the flag are computed at runtime, see [Flag::complete_with]
+fn dynamic(flag: &Flag) -> bool {
+    flag.completer.is_some()
+}
+
+/// The only valid values of the flag, see [crate::Validator::choices]
+fn choices(flag: &Flag) -> Option<&[String]> {
+    flag.validators.iter().find_map(|v| v.choices())
+}
+
+/// Identifies the command at `path` of the program `name` in scripts, like `git__remote`
+fn key(name: &str, path: &[&str]) -> String {
+    let mut key = ident(name);
+    for id in path {
+        key.push_str("__");
+        key.push_str(id);
+    }
+    key
+}
+
+/// `name` as a shell function name, with other characters than letters,
+/// digits, and `_` replaced by `_`
+fn ident(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+/// `word` as a single quoted shell word
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// If the command takes positionals or trailing args
+fn takes_args(command: &Command) -> bool {
+    command.positional || !command.positionals.is_empty() || command.takes_trailing
+}
@@ -0,0 +1,123 @@
+use super::{choices, takes_args, takes_value, Node, ValueHint};
+
+/// A module of `extern` signatures for nushell, one per command, with the descriptions of
+/// commands and flags as comments, and a completer per flag with known values
+pub(crate) fn script(name: &str, nodes: &[Node]) -> String {
+    let mut out = String::from("module completions {\n");
+
+    for node in nodes {
+        let mut words = vec![name];
+        words.extend(node.path.iter().copied());
+        let command = words.join(" ");
+
+        for flag in node.flags.iter().filter(|f| takes_value(f)) {
+            if let Some(choices) = choices(flag) {
+                let choices: Vec<String> = choices.iter().map(|c| quote(c)).collect();
+                out.push_str(&format!(
+                    "\n  def {} [] {{\n    [ {} ]\n  }}\n",
+                    quote(&completer(&command, &flag.id)),
+                    choices.join(" ")
+                ));
+            }
+        }
+
+        out.push('\n');
+        if let Some(about) = node.command.and_then(|c| c.about.as_deref()) {
+            for line in about.lines() {
+                out.push_str(&format!("  # {}\n", line));
+            }
+        }
+        out.push_str(&format!("  export extern {} [\n", quote(&command)));
+        for flag in &node.flags {
+            let mut param = flag.id.clone();
+            if takes_value(flag) {
+                match choices(flag) {
+                    Some(_) => {
+                        let completer = quote(&completer(&command, &flag.id));
+                        param.push_str(&format!(": string@{}", completer));
+                    }
+                    None => param.push_str(match flag.value_hint {
+                        Some(ValueHint::FilePath) => ": path",
+                        Some(ValueHint::DirPath) => ": directory",
+                        _ => ": string",
+                    }),
+                }
+            }
+            if let Some(help) = flag.help.as_deref().and_then(|h| h.lines().next()) {
+                param.push_str(&format!("  # {}", help));
+            }
+            out.push_str(&format!("    {}\n", param));
+        }
+        if node.command.is_some_and(takes_args) {
+            out.push_str("    ...args: string\n");
+        }
+        out.push_str("  ]\n");
+    }
+
+    out.push_str("}\n\nexport use completions *\n");
+    out
+}
+
+/// Name of the completer of the values of the flag `id` of `command`
+fn completer(command: &str, id: &str) -> String {
+    format!("nu-complete {} {}", command, id.trim_start_matches('-'))
+}
+
+/// `word` as a double quoted nushell string
+fn quote(word: &str) -> String {
+    format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{validate::one_of, *};
+
+    #[test]
+    fn test_nushell() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("git")
+            .command(
+                Command::new("remote")
+                    .about("Manage remotes")
+                    .positional()
+                    .flag(
+                        Flag::new("--mirror")
+                            .positional()
+                            .validate_with(one_of(["fetch", "push"])),
+                    ),
+            )
+            .global_flag(
+                Flag::new("--git-dir")
+                    .positional()
+                    .help("Path to the repository"),
+            )
+            .global_flag(Flag::new("-v"));
+
+        assert_eq!(
+            generate_completions(Shell::Nushell, &parser),
+            concat!(
+                "module completions {\n",
+                "\n",
+                "  export extern \"git\" [\n",
+                "    --git-dir: string  # Path to the repository\n",
+                "    -v\n",
+                "  ]\n",
+                "\n",
+                "  def \"nu-complete git remote mirror\" [] {\n",
+                "    [ \"fetch\" \"push\" ]\n",
+                "  }\n",
+                "\n",
+                "  # Manage remotes\n",
+                "  export extern \"git remote\" [\n",
+                "    --mirror: string@\"nu-complete git remote mirror\"\n",
+                "    --git-dir: string  # Path to the repository\n",
+                "    -v\n",
+                "    ...args: string\n",
+                "  ]\n",
+                "}\n",
+                "\n",
+                "export use completions *\n",
+            )
+        );
+    }
+}
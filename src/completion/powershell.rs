@@ -0,0 +1,190 @@
+use super::{choices, key, takes_value, Node};
+
+/// A native argument completer for PowerShell, walking the complete words to the innermost
+/// command, then completing the value of the previous flag, or the commands and flags
+/// that may follow the command, with their descriptions as tooltips
+pub(crate) fn script(name: &str, nodes: &[Node]) -> String {
+    let key = |path: &[&str]| key(name, path);
+
+    let mut out = format!(
+        concat!(
+            "using namespace System.Management.Automation\n",
+            "\n",
+            "Register-ArgumentCompleter -Native -CommandName {} -ScriptBlock {{\n",
+            "    param($wordToComplete, $commandAst, $cursorPosition)\n",
+            "\n",
+            "    $cmd = {}\n",
+            "    $previous = ''\n",
+            "    foreach ($element in $commandAst.CommandElements | Select-Object -Skip 1) {{\n",
+            "        if ($element.Extent.EndOffset -ge $cursorPosition) {{ break }}\n",
+            "        $word = $element.ToString()\n",
+            "        switch (\"${{cmd}}__$word\") {{\n",
+        ),
+        quote(name),
+        quote(&key(&[]))
+    );
+    for node in nodes.iter().filter(|n| !n.path.is_empty()) {
+        let key = quote(&key(&node.path));
+        out.push_str(&format!("            {} {{ $cmd = {} }}\n", key, key));
+    }
+    out.push_str(concat!(
+        "        }\n",
+        "        $previous = $word\n",
+        "    }\n",
+        "\n",
+        "    $values = $null\n",
+        "    $candidates = @()\n",
+        "    switch ($cmd) {\n",
+    ));
+
+    for node in nodes {
+        out.push_str(&format!("        {} {{\n", quote(&key(&node.path))));
+        let values: Vec<String> = node
+            .flags
+            .iter()
+            .filter(|f| takes_value(f))
+            .map(|flag| match choices(flag) {
+                Some(choices) => {
+                    let choices: Vec<String> = choices.iter().map(|c| quote(c)).collect();
+                    format!(
+                        "                {} {{ $values = @({}) }}\n",
+                        quote(&flag.id),
+                        choices.join(", ")
+                    )
+                }
+                // No results leave it to the path completion of the shell
+                None => format!("                {} {{ return }}\n", quote(&flag.id)),
+            })
+            .collect();
+        if !values.is_empty() {
+            out.push_str("            switch ($previous) {\n");
+            out.push_str(&values.concat());
+            out.push_str("            }\n");
+        }
+
+        out.push_str("            $candidates = @(\n");
+        for word in node.extra {
+            out.push_str(&result(word, "ParameterValue", None));
+        }
+        for command in &node.commands {
+            out.push_str(&result(
+                &command.id,
+                "ParameterValue",
+                command.about.as_deref(),
+            ));
+        }
+        for flag in &node.flags {
+            out.push_str(&result(&flag.id, "ParameterName", flag.help.as_deref()));
+        }
+        out.push_str("            )\n        }\n");
+    }
+
+    out.push_str(concat!(
+        "    }\n",
+        "\n",
+        "    if ($null -ne $values) {\n",
+        "        $candidates = $values | ForEach-Object {\n",
+        "            [CompletionResult]::new($_, $_, [CompletionResultType]::ParameterValue, $_)\n",
+        "        }\n",
+        "    }\n",
+        "    $candidates | Where-Object { $_.CompletionText -like \"$wordToComplete*\" }\n",
+        "}\n",
+    ));
+    out
+}
+
+/// A `CompletionResult` of `word`, with the first line of `description` as tooltip
+fn result(word: &str, kind: &str, description: Option<&str>) -> String {
+    let tooltip = description
+        .and_then(|d| d.lines().next())
+        .filter(|d| !d.is_empty())
+        .unwrap_or(word);
+    format!(
+        "                [CompletionResult]::new({}, {}, [CompletionResultType]::{}, {})\n",
+        quote(word),
+        quote(word),
+        kind,
+        quote(tooltip)
+    )
+}
+
+/// `word` as a single quoted PowerShell string, where `'` is doubled
+fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{validate::one_of, *};
+
+    #[test]
+    fn test_powershell() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("git")
+            .command(
+                Command::new("remote").about("Manage 'remotes'").flag(
+                    Flag::new("--mirror")
+                        .positional()
+                        .validate_with(one_of(["fetch", "push"])),
+                ),
+            )
+            .global_flag(
+                Flag::new("--git-dir")
+                    .positional()
+                    .help("Path to the repository"),
+            );
+
+        assert_eq!(
+            generate_completions(Shell::PowerShell, &parser),
+            concat!(
+                "using namespace System.Management.Automation\n",
+                "\n",
+                "Register-ArgumentCompleter -Native -CommandName 'git' -ScriptBlock {\n",
+                "    param($wordToComplete, $commandAst, $cursorPosition)\n",
+                "\n",
+                "    $cmd = 'git'\n",
+                "    $previous = ''\n",
+                "    foreach ($element in $commandAst.CommandElements | Select-Object -Skip 1) {\n",
+                "        if ($element.Extent.EndOffset -ge $cursorPosition) { break }\n",
+                "        $word = $element.ToString()\n",
+                "        switch (\"${cmd}__$word\") {\n",
+                "            'git__remote' { $cmd = 'git__remote' }\n",
+                "        }\n",
+                "        $previous = $word\n",
+                "    }\n",
+                "\n",
+                "    $values = $null\n",
+                "    $candidates = @()\n",
+                "    switch ($cmd) {\n",
+                "        'git' {\n",
+                "            switch ($previous) {\n",
+                "                '--git-dir' { return }\n",
+                "            }\n",
+                "            $candidates = @(\n",
+                "                [CompletionResult]::new('remote', 'remote', [CompletionResultType]::ParameterValue, 'Manage ''remotes''')\n",
+                "                [CompletionResult]::new('--git-dir', '--git-dir', [CompletionResultType]::ParameterName, 'Path to the repository')\n",
+                "            )\n",
+                "        }\n",
+                "        'git__remote' {\n",
+                "            switch ($previous) {\n",
+                "                '--mirror' { $values = @('fetch', 'push') }\n",
+                "                '--git-dir' { return }\n",
+                "            }\n",
+                "            $candidates = @(\n",
+                "                [CompletionResult]::new('--mirror', '--mirror', [CompletionResultType]::ParameterName, '--mirror')\n",
+                "                [CompletionResult]::new('--git-dir', '--git-dir', [CompletionResultType]::ParameterName, 'Path to the repository')\n",
+                "            )\n",
+                "        }\n",
+                "    }\n",
+                "\n",
+                "    if ($null -ne $values) {\n",
+                "        $candidates = $values | ForEach-Object {\n",
+                "            [CompletionResult]::new($_, $_, [CompletionResultType]::ParameterValue, $_)\n",
+                "        }\n",
+                "    }\n",
+                "    $candidates | Where-Object { $_.CompletionText -like \"$wordToComplete*\" }\n",
+                "}\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::COMPLETE_COMMAND;
+
+use super::{choices, dynamic, ident, key, quote, takes_value, Node, ValueHint};
+
+/// A completion function for zsh, walking the complete words to the innermost command,
+/// then completing the value of the previous flag, or describing the commands and flags
+/// that may follow the command with [Command::about](crate::Command::about)
+/// and [Flag::help](crate::Flag::help)
+pub(crate) fn script(name: &str, nodes: &[Node]) -> String {
+    let function = format!("_{}", ident(name));
+    let key = |path: &[&str]| key(name, path);
+
+    let mut out = format!(
+        concat!(
+            "#compdef {}\n\n",
+            "{}() {{\n",
+            "    local cmd i\n",
+            "    cmd=\"{}\"\n",
+            "    for ((i = 2; i < CURRENT; i++)); do\n",
+            "        case \"${{cmd}}__${{words[i]}}\" in\n",
+        ),
+        name,
+        function,
+        key(&[])
+    );
+    for node in nodes.iter().filter(|n| !n.path.is_empty()) {
+        let key = key(&node.path);
+        out.push_str(&format!(
+            "            {}) cmd={} ;;\n",
+            quote(&key),
+            quote(&key)
+        ));
+    }
+    out.push_str("        esac\n    done\n\n    case \"${cmd}\" in\n");
+
+    for node in nodes {
+        out.push_str(&format!("        {})\n", quote(&key(&node.path))));
+        let values: Vec<String> = node
+            .flags
+            .iter()
+            .filter(|f| takes_value(f))
+            .map(|flag| {
+                let complete = match choices(flag) {
+                    _ if dynamic(flag) => format!(
+                        "compadd -- ${{(f)\"$(${{words[1]}} {} ${{words[2,CURRENT]}} 2>/dev/null | cut -f1)\"}}",
+                        COMPLETE_COMMAND
+                    ),
+                    Some(choices) => {
+                        let choices: Vec<String> = choices.iter().map(|c| quote(c)).collect();
+                        format!("compadd -- {}", choices.join(" "))
+                    }
+                    None => match flag.value_hint {
+                        Some(ValueHint::DirPath) => "_files -/".to_string(),
+                        Some(ValueHint::Hostname) => "_hosts".to_string(),
+                        Some(ValueHint::Username) => "_users".to_string(),
+                        // Left to the file completion of the shell
+                        Some(ValueHint::FilePath) | None => "_files".to_string(),
+                    },
+                };
+                format!(
+                    "                {}) {}; return ;;\n",
+                    quote(&flag.id),
+                    complete
+                )
+            })
+            .collect();
+        if !values.is_empty() {
+            out.push_str("            case \"${words[CURRENT-1]}\" in\n");
+            out.push_str(&values.concat());
+            out.push_str("            esac\n");
+        }
+
+        let mut commands: Vec<String> = node.extra.iter().map(|w| entry(w, None)).collect();
+        commands.extend(
+            node.commands
+                .iter()
+                .map(|c| entry(&c.id, c.about.as_deref())),
+        );
+        let flags: Vec<String> = node
+            .flags
+            .iter()
+            .map(|f| entry(&f.id, f.help.as_deref()))
+            .collect();
+        out.push_str("            local -a commands flags\n");
+        out.push_str(&format!("            commands=({})\n", commands.join(" ")));
+        out.push_str(&format!("            flags=({})\n", flags.join(" ")));
+        out.push_str("            _describe -t commands 'command' commands\n");
+        out.push_str("            _describe -t flags 'flag' flags\n");
+        out.push_str("            ;;\n");
+    }
+
+    out.push_str(&format!(
+        concat!(
+            "    esac\n",
+            "}}\n\n",
+            "if [ \"$funcstack[1]\" = \"{0}\" ]; then\n",
+            "    {0} \"$@\"\n",
+            "else\n",
+            "    compdef {0} {1}\n",
+            "fi\n",
+        ),
+        function, name
+    ));
+    out
+}
+
+/// A quoted `_describe` entry, like `'--out:Output file'`
+fn entry(word: &str, description: Option<&str>) -> String {
+    let word = word.replace(':', "\\:");
+    match description {
+        // Only the first line, to keep the list aligned
+        Some(description) => {
+            let description = description.lines().next().unwrap_or_default();
+            quote(&format!("{}:{}", word, description))
+        }
+        None => quote(&word),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{validate::one_of, *};
+
+    #[test]
+    fn test_zsh() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("git")
+            .command(
+                Command::new("remote")
+                    .about("Manage remotes")
+                    .complete_extra("origin")
+                    .flag(
+                        Flag::new("--mirror")
+                            .positional()
+                            .help("Mirror 'fetch' or 'push'")
+                            .validate_with(one_of(["fetch", "push"])),
+                    ),
+            )
+            .global_flag(
+                Flag::new("--git-dir")
+                    .positional()
+                    .help("Path: the repository"),
+            );
+
+        assert_eq!(
+            generate_completions(Shell::Zsh, &parser),
+            concat!(
+                "#compdef git\n",
+                "\n",
+                "_git() {\n",
+                "    local cmd i\n",
+                "    cmd=\"git\"\n",
+                "    for ((i = 2; i < CURRENT; i++)); do\n",
+                "        case \"${cmd}__${words[i]}\" in\n",
+                "            'git__remote') cmd='git__remote' ;;\n",
+                "        esac\n",
+                "    done\n",
+                "\n",
+                "    case \"${cmd}\" in\n",
+                "        'git')\n",
+                "            case \"${words[CURRENT-1]}\" in\n",
+                "                '--git-dir') _files; return ;;\n",
+                "            esac\n",
+                "            local -a commands flags\n",
+                "            commands=('remote:Manage remotes')\n",
+                "            flags=('--git-dir:Path: the repository')\n",
+                "            _describe -t commands 'command' commands\n",
+                "            _describe -t flags 'flag' flags\n",
+                "            ;;\n",
+                "        'git__remote')\n",
+                "            case \"${words[CURRENT-1]}\" in\n",
+                "                '--mirror') compadd -- 'fetch' 'push'; return ;;\n",
+                "                '--git-dir') _files; return ;;\n",
+                "            esac\n",
+                "            local -a commands flags\n",
+                "            commands=('origin')\n",
+                "            flags=('--mirror:Mirror '\\''fetch'\\'' or '\\''push'\\''' '--git-dir:Path: the repository')\n",
+                "            _describe -t commands 'command' commands\n",
+                "            _describe -t flags 'flag' flags\n",
+                "            ;;\n",
+                "    esac\n",
+                "}\n",
+                "\n",
+                "if [ \"$funcstack[1]\" = \"_git\" ]; then\n",
+                "    _git \"$@\"\n",
+                "else\n",
+                "    compdef _git git\n",
+                "fi\n",
+            )
+        );
+    }
+}
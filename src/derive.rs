@@ -0,0 +1,149 @@
+use std::any::Any;
+
+use crate::{CliParser, Command, ParseError};
+
+/// A struct defining a [Command], and extracted from the parsed command,
+/// usually implemented with `#[derive(CliCommand)]` behind the `derive` feature.
+///
+/// The derive defines the command `id` as the kebab-cased struct name, and a flag
+/// for every field, `--` and the kebab-cased field name, by the field type:
+/// - `bool`: a presence flag
+/// - `Option<T>`: an optional flag taking a `T`
+/// - `Vec<T>`: a flag collecting any number of `T`s, see [crate::Flag::values_of_type]
+/// - any other `T`: a flag taking a `T`, missing values are [ParseError::MissingRequiredFlag]
+///
+/// Values are converted with [std::str::FromStr] while parsing, and must be [Clone].
+/// Attributes customize the definition:
+/// - `#[command(id = "..", hidden)]` on the struct
+/// - `#[flag(id = "..", required, hidden, default = "..", delimiter = ',', count)]`,
+///   `count` makes an integer field a [crate::Flag::counted] flag
+/// - `#[positional]` or `#[positional(id = "..")]` makes the field a required named
+///   [crate::Positional], with the uppercased field name as id
+///
+/// ## Example
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use cli_parser::CliCommand;
+///
+/// #[derive(CliCommand)]
+/// struct Deploy {
+///     #[positional]
+///     target: String,
+///     #[flag(required)]
+///     tag: String,
+///     #[flag(default = "1")]
+///     replicas: u32,
+///     dry_run: bool,
+/// }
+///
+/// let deploy = Deploy::parse_from(["prod", "--tag", "v2", "--dry-run"]).unwrap();
+/// assert_eq!(deploy.target, "prod");
+/// assert_eq!(deploy.tag, "v2");
+/// assert_eq!(deploy.replicas, 1);
+/// assert!(deploy.dry_run);
+/// # }
+/// ```
+pub trait CliCommand: Sized {
+    /// The definition of the command
+    fn command() -> Command;
+
+    /// Extract the struct from the parse result of [CliCommand::command]
+    fn from_command(command: &Command) -> Result<Self, ParseError>;
+
+    /// Parse `args`, following the command id, into the struct
+    fn parse_from<I, S>(args: I) -> Result<Self, ParseError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let command = Self::command();
+        let args = std::iter::once(command.id.clone()).chain(args.into_iter().map(Into::into));
+        let parsed = CliParser::parse_from(args).command(command).parse()?;
+        Self::from_command(&parsed)
+    }
+}
+
+/// Extraction helpers used by the generated [CliCommand] implementations
+#[doc(hidden)]
+pub mod internal {
+    use super::*;
+
+    pub fn flag<T: Any + Clone>(command: &Command, id: &str) -> Result<T, ParseError> {
+        flag_option(command, id).ok_or_else(|| ParseError::MissingRequiredFlag(id.into()))
+    }
+
+    pub fn flag_option<T: Any + Clone>(command: &Command, id: &str) -> Option<T> {
+        command.parsed_flag(id).and_then(|f| f.get::<T>()).cloned()
+    }
+
+    pub fn flag_values<T: Any + Clone>(command: &Command, id: &str) -> Vec<T> {
+        command
+            .parsed_flag(id)
+            .map(|f| f.get_all::<T>().into_iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn positional<T: Any + Clone>(command: &Command, id: &str) -> Result<T, ParseError> {
+        command
+            .parsed_positionals
+            .get(id)
+            .and_then(|p| p.get::<T>())
+            .cloned()
+            .ok_or(ParseError::RequiredPositional)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "derive")]
+mod test {
+    use crate::*;
+
+    #[derive(CliCommand, Debug, PartialEq)]
+    #[command(id = "remote-add")]
+    struct RemoteAdd {
+        #[positional(id = "NAME")]
+        name: String,
+        #[positional]
+        url: String,
+        #[flag(count)]
+        verbose: u8,
+        #[flag(id = "--tag", delimiter = ',')]
+        tags: Vec<String>,
+        depth: Option<u32>,
+        #[flag(default = "origin")]
+        upstream_name: String,
+        force: bool,
+    }
+
+    #[test]
+    fn test_derive() {
+        let add = RemoteAdd::parse_from(
+            "origin git://x --verbose --tag a,b --verbose --upstream-name up --depth 3".split(' '),
+        )
+        .unwrap();
+        assert_eq!(
+            add,
+            RemoteAdd {
+                name: "origin".into(),
+                url: "git://x".into(),
+                verbose: 2,
+                tags: vec!["a".into(), "b".into()],
+                depth: Some(3),
+                upstream_name: "up".into(),
+                force: false,
+            }
+        );
+
+        let add = RemoteAdd::parse_from(["origin", "url", "--force"]).unwrap();
+        assert_eq!((add.depth, add.upstream_name.as_str()), (None, "origin"));
+        assert!(add.force);
+
+        assert!(matches!(
+            RemoteAdd::parse_from(["origin", "url", "--depth", "x"]),
+            Err(ParseError::InvalidValues(id, _)) if id == "--depth"
+        ));
+        assert!(RemoteAdd::command()
+            .to_string()
+            .starts_with("remote-add <NAME> <URL>"));
+    }
+}
@@ -0,0 +1,456 @@
+use std::{any::Any, fmt::Debug, sync::Arc, time::Duration};
+#[cfg(feature = "async")]
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll, Wake, Waker},
+    thread,
+    time::Instant,
+};
+
+use crate::{CliParser, Command, Matches, ParseError};
+
+/// Error returned by a command handler, see [Command::handler]
+pub type HandlerError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Future returned by an async handler, see [Command::async_handler]
+#[cfg(feature = "async")]
+pub type HandlerFuture = Pin<Box<dyn Future<Output = Result<(), HandlerError>> + Send>>;
+
+type HandlerFn = dyn Fn(&Matches) -> Result<(), HandlerError> + Send + Sync;
+
+#[cfg(feature = "async")]
+type AsyncHandlerFn = dyn Fn(Matches) -> HandlerFuture + Send + Sync;
+
+/// Closure registered with [Command::handler] or [Command::async_handler]
+#[derive(Clone)]
+pub(crate) enum Handler {
+    Sync(Arc<HandlerFn>),
+    #[cfg(feature = "async")]
+    Async(Arc<AsyncHandlerFn>),
+}
+
+impl Debug for Handler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Handler")
+    }
+}
+
+/// Why [CliParser::run] failed
+#[derive(Debug)]
+pub enum RunError {
+    /// The args could not be parsed
+    Parse(ParseError),
+    /// No command in the matched path has a handler, (id of the innermost command)
+    NoHandler(String),
+    /// The handler returned an error
+    Handler(HandlerError),
+    /// An async handler ran past the [Command::timeout] of its command, (id of the command, timeout)
+    TimedOut(String, Duration),
+    /// The command has a [Command::timeout], but a blocking [Command::handler],
+    /// which can not be cancelled, (id of the command)
+    TimeoutUnsupported(String),
+    /// Handlers are disabled, see [CliParser::sandboxed]
+    Sandboxed,
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Parse(e) => write!(f, "{}", e),
+            RunError::NoHandler(id) => write!(f, "no handler for command '{}'", id),
+            RunError::Handler(e) => write!(f, "{}", e),
+            RunError::TimedOut(id, timeout) => {
+                write!(f, "command '{}' timed out after {:?}", id, timeout)
+            }
+            RunError::TimeoutUnsupported(id) => write!(
+                f,
+                "command '{}' has a timeout, which blocking handlers do not support",
+                id
+            ),
+            RunError::Sandboxed => write!(f, "handlers are disabled in sandboxed mode"),
+        }
+    }
+}
+
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Parse(e) => Some(e),
+            RunError::NoHandler(_)
+            | RunError::TimedOut(..)
+            | RunError::TimeoutUnsupported(_)
+            | RunError::Sandboxed => None,
+            RunError::Handler(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<ParseError> for RunError {
+    fn from(e: ParseError) -> Self {
+        RunError::Parse(e)
+    }
+}
+
+impl Command {
+    /// Run `handler` when this is the innermost matched command with a handler,
+    /// see [CliParser::run]. The handler gets the [Matches] of the whole invocation,
+    /// from the outermost command
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "greet --name world".split(" ").map(|s| s.to_string());
+    /// CliParser::from_args(args)
+    ///     .command(
+    ///         Command::new("greet")
+    ///             .flag(Flag::new("--name").positional())
+    ///             .handler(|matches| {
+    ///                 let name = matches.flag("--name").and_then(|f| f.value());
+    ///                 println!("hello {}", name.unwrap_or("you"));
+    ///                 Ok(())
+    ///             }),
+    ///     )
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Matches) -> Result<(), HandlerError> + Send + Sync + 'static,
+    {
+        self.handler = Some(Handler::Sync(Arc::new(handler)));
+        self
+    }
+
+    /// Run the async `handler` when this is the innermost matched command with a handler,
+    /// see [CliParser::run_async]. Behind the `async` feature
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// # use std::time::Duration;
+    /// let args = "fetch --url example.com".split(" ").map(|s| s.to_string());
+    /// let mut parser = CliParser::from_args(args).command(
+    ///     Command::new("fetch")
+    ///         .flag(Flag::new("--url").positional())
+    ///         .timeout(Duration::from_secs(30))
+    ///         .async_handler(|matches| async move {
+    ///             let url = matches.flag("--url").and_then(|f| f.value());
+    ///             println!("fetching {}", url.unwrap_or_default());
+    ///             Ok(())
+    ///         }),
+    /// );
+    /// pollster::block_on(parser.run_async()).unwrap();
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn async_handler<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(Matches) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), HandlerError>> + Send + 'static,
+    {
+        self.handler = Some(Handler::Async(Arc::new(move |matches| {
+            Box::pin(handler(matches))
+        })));
+        self
+    }
+
+    /// The innermost command in the parsed path that has a handler,
+    /// or the id of the innermost command
+    fn find_handler(&self) -> Result<&Command, String> {
+        let inner = match &self.subcommand {
+            Some(sub) => sub.find_handler(),
+            None => Err(self.id.clone()),
+        };
+        match (inner, &self.handler) {
+            (Ok(command), _) => Ok(command),
+            (Err(_), Some(_)) => Ok(self),
+            (Err(id), None) => Err(id),
+        }
+    }
+
+    /// The handler to run for the parsed path, with the id and timeout of its command.
+    /// Fails for blocking handlers with a timeout, they can not be cancelled
+    fn dispatch(&self) -> Result<(Handler, String, Option<Duration>), RunError> {
+        let command = self.find_handler().map_err(RunError::NoHandler)?;
+        let handler = command.handler.clone().expect("command has a handler");
+        if matches!(handler, Handler::Sync(_)) && command.timeout.is_some() {
+            return Err(RunError::TimeoutUnsupported(command.id.clone()));
+        }
+        Ok((handler, command.id.clone(), command.timeout))
+    }
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Register application state passed to every handler, read with [Matches::context],
+    /// e.g. for sharing an HTTP client between handlers without global statics
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// struct AppState {
+    ///     endpoint: String,
+    /// }
+    ///
+    /// CliParser::from_args(["status".to_string()].into_iter())
+    ///     .command(Command::new("status").handler(|matches| {
+    ///         let state = matches.context::<AppState>().unwrap();
+    ///         println!("checking {}", state.endpoint);
+    ///         Ok(())
+    ///     }))
+    ///     .context(AppState {
+    ///         endpoint: "https://example.com".into(),
+    ///     })
+    ///     .run()
+    ///     .unwrap();
+    /// ```
+    pub fn context<T: Any + Send + Sync>(mut self, context: T) -> Self {
+        self.context = Some(Arc::new(context));
+        self
+    }
+
+    /// Parse, then call the handler of the matched command, see [Command::handler].
+    ///
+    /// An [Command::async_handler] is run to completion on the current thread,
+    /// use [CliParser::run_async] when already running in an async runtime.
+    ///
+    /// No handler is called when parsing ended on an exclusive flag like `--help`,
+    /// see [crate::Flag::exclusive]
+    #[cfg_attr(not(feature = "async"), allow(unused_variables))]
+    pub fn run(&mut self) -> Result<(), RunError> {
+        let command = self.parse()?;
+        if self.sandboxed {
+            return Err(RunError::Sandboxed);
+        }
+        // Parsing ended early, the exclusive flag is the caller's to handle
+        if command.exclusive_flag.is_some() {
+            return Ok(());
+        }
+        let (handler, id, timeout) = command.dispatch()?;
+        let mut matches = Matches::from(command);
+        matches.context = self.context.clone();
+        match handler {
+            Handler::Sync(handler) => handler(&matches).map_err(RunError::Handler),
+            #[cfg(feature = "async")]
+            Handler::Async(handler) => block_on(with_timeout(handler(matches), id, timeout)),
+        }
+    }
+
+    /// Parse, then call and await the handler of the matched command, see
+    /// [Command::async_handler]. Behind the `async` feature
+    ///
+    /// The [Command::timeout] of the command is enforced on async handlers, with
+    /// [RunError::TimedOut]. Works with any runtime, the timeout runs on its own thread
+    #[cfg(feature = "async")]
+    pub async fn run_async(&mut self) -> Result<(), RunError> {
+        let command = self.parse()?;
+        if self.sandboxed {
+            return Err(RunError::Sandboxed);
+        }
+        // Parsing ended early, the exclusive flag is the caller's to handle
+        if command.exclusive_flag.is_some() {
+            return Ok(());
+        }
+        let (handler, id, timeout) = command.dispatch()?;
+        let mut matches = Matches::from(command);
+        matches.context = self.context.clone();
+        match handler {
+            Handler::Sync(handler) => handler(&matches).map_err(RunError::Handler),
+            Handler::Async(handler) => with_timeout(handler(matches), id, timeout).await,
+        }
+    }
+}
+
+/// Await `handler`, failing if it is not done within `timeout`
+#[cfg(feature = "async")]
+async fn with_timeout(
+    mut handler: HandlerFuture,
+    id: String,
+    timeout: Option<Duration>,
+) -> Result<(), RunError> {
+    let mut deadline = timeout.map(Deadline::new);
+    std::future::poll_fn(|cx| {
+        if let Poll::Ready(result) = handler.as_mut().poll(cx) {
+            return Poll::Ready(result.map_err(RunError::Handler));
+        }
+        match deadline.as_mut().map(|d| Pin::new(d).poll(cx)) {
+            Some(Poll::Ready(())) => {
+                Poll::Ready(Err(RunError::TimedOut(id.clone(), timeout.unwrap())))
+            }
+            _ => Poll::Pending,
+        }
+    })
+    .await
+}
+
+/// Future completing once `duration` has passed, waiting on its own thread
+/// so it does not depend on the timers of any runtime
+#[cfg(feature = "async")]
+struct Deadline {
+    at: Instant,
+    // Waker of the latest poll, the thread is started on the first poll
+    waker: Option<Arc<Mutex<Waker>>>,
+}
+
+#[cfg(feature = "async")]
+impl Deadline {
+    fn new(duration: Duration) -> Self {
+        Self {
+            at: Instant::now() + duration,
+            waker: None,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Future for Deadline {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.at {
+            return Poll::Ready(());
+        }
+        match &self.waker {
+            Some(waker) => waker.lock().unwrap().clone_from(cx.waker()),
+            None => {
+                let waker = Arc::new(Mutex::new(cx.waker().clone()));
+                let (at, shared) = (self.at, waker.clone());
+                thread::spawn(move || {
+                    thread::sleep(at.saturating_duration_since(Instant::now()));
+                    shared.lock().unwrap().wake_by_ref();
+                });
+                self.waker = Some(waker);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// Wakes a thread parked in [block_on]
+#[cfg(feature = "async")]
+struct ThreadWaker(thread::Thread);
+
+#[cfg(feature = "async")]
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run `future` to completion on the current thread
+#[cfg(feature = "async")]
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_run_innermost_handler() {
+        let called = Arc::new(Mutex::new(Vec::new()));
+        let record = |name: &'static str| {
+            let called = called.clone();
+            move |_: &Matches| {
+                called.lock().unwrap().push(name);
+                Ok(())
+            }
+        };
+        let run = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(
+                    Command::new("remote")
+                        .handler(record("remote"))
+                        .subcommand(Command::new("add").handler(record("add")))
+                        .subcommand(Command::new("show")),
+                )
+                .command(Command::new("fail").handler(|_| Err("broken".into())))
+                .command(Command::new("noop"))
+                .command(
+                    Command::new("slow")
+                        .timeout(Duration::from_secs(1))
+                        .handler(record("slow")),
+                )
+                .run()
+        };
+
+        run("remote add").unwrap();
+        run("remote show").unwrap();
+        assert_eq!(*called.lock().unwrap(), ["add", "remote"]);
+
+        assert!(matches!(run("noop"), Err(RunError::NoHandler(id)) if id == "noop"));
+        assert_eq!(run("fail").unwrap_err().to_string(), "broken");
+        assert!(matches!(run("nope"), Err(RunError::Parse(_))));
+        assert!(matches!(run("slow"), Err(RunError::TimeoutUnsupported(id)) if id == "slow"));
+        assert_eq!(called.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_run_skips_exclusive() {
+        let called = Arc::new(Mutex::new(0));
+        let run = |input: &str| {
+            let called = called.clone();
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .version("1.0.0")
+                .with_standard_flags()
+                .command(Command::new("build").handler(move |_| {
+                    *called.lock().unwrap() += 1;
+                    Ok(())
+                }))
+                .run()
+        };
+
+        run("build --help").unwrap();
+        run("build --version").unwrap();
+        assert_eq!(*called.lock().unwrap(), 0);
+        run("build").unwrap();
+        assert_eq!(*called.lock().unwrap(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_run_async_timeout() {
+        let parser = || {
+            CliParser::from_args(std::iter::once("wait".to_string())).command(
+                Command::new("wait")
+                    .timeout(Duration::from_millis(20))
+                    .async_handler(|_| std::future::pending()),
+            )
+        };
+
+        let err = pollster::block_on(parser().run_async()).unwrap_err();
+        assert!(matches!(&err, RunError::TimedOut(id, _) if id == "wait"));
+        assert_eq!(err.to_string(), "command 'wait' timed out after 20ms");
+        assert!(matches!(parser().run(), Err(RunError::TimedOut(..))));
+
+        let args = "sum 1".split(" ").map(|s| s.to_string());
+        CliParser::from_args(args)
+            .command(
+                Command::new("sum")
+                    .positional()
+                    .async_handler(|m| async move {
+                        match m.positional() {
+                            Some("1") => Ok(()),
+                            _ => Err("unexpected".into()),
+                        }
+                    }),
+            )
+            .run()
+            .unwrap();
+    }
+}
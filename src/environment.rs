@@ -0,0 +1,129 @@
+use std::env::var;
+
+use crate::Stream;
+
+/// Width used when the terminal width can not be detected, and on dumb terminals
+const DEFAULT_WIDTH: usize = 80;
+
+/// Presentation settings for generated output, set with [crate::CliParser::environment].
+///
+/// Every setting left as `None` is detected from the process environment,
+/// set it to control the output deterministically, e.g. in tests or a web terminal
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let env = Environment {
+///     width: Some(100),
+///     color: Some(false),
+///     ..Default::default()
+/// };
+/// assert_eq!(env.effective_width(), 100);
+/// ```
+///
+/// With `TERM=dumb`, or [Environment::dumb], output degrades to plain 80 column
+/// ASCII without color, e.g. for serial consoles and CI logs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Environment {
+    /// Terminal width in columns, detected from `COLUMNS`
+    pub width: Option<usize>,
+    /// If output may be colored, detected from `NO_COLOR`, `CLICOLOR_FORCE`, `TERM`
+    /// and the stream written to
+    pub color: Option<bool>,
+    /// Locale like `nb_NO`, detected from `LC_ALL`, `LC_MESSAGES` and `LANG`
+    pub locale: Option<String>,
+    /// If output must be plain ASCII, without box drawing or arrow characters, detected from `TERM`
+    pub ascii: Option<bool>,
+}
+
+impl Environment {
+    /// The profile for minimal terminals: 80 columns, no color and ASCII only
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty()).environment(Environment::dumb());
+    /// ```
+    pub fn dumb() -> Self {
+        Self {
+            width: Some(DEFAULT_WIDTH),
+            color: Some(false),
+            locale: None,
+            ascii: Some(true),
+        }
+    }
+
+    /// The overridden or detected terminal width
+    pub fn effective_width(&self) -> usize {
+        self.width.unwrap_or_else(|| {
+            if is_dumb() {
+                return DEFAULT_WIDTH;
+            }
+            var("COLUMNS")
+                .ok()
+                .and_then(|c| c.parse().ok())
+                .filter(|&c| c > 0)
+                .unwrap_or(DEFAULT_WIDTH)
+        })
+    }
+
+    /// The overridden or detected color support of output written to `stream`
+    pub fn effective_color(&self, stream: &Stream) -> bool {
+        self.color.unwrap_or_else(|| {
+            if var("NO_COLOR").is_ok_and(|v| !v.is_empty()) {
+                return false;
+            }
+            if var("CLICOLOR_FORCE").is_ok_and(|v| !v.is_empty() && v != "0") {
+                return true;
+            }
+            !is_dumb() && stream.is_terminal()
+        })
+    }
+
+    /// The overridden or detected need for plain ASCII output
+    pub fn effective_ascii(&self) -> bool {
+        self.ascii.unwrap_or_else(is_dumb)
+    }
+
+    /// The overridden or detected locale, without encoding, like `nb_NO`
+    pub fn effective_locale(&self) -> String {
+        self.locale.clone().unwrap_or_else(|| {
+            ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|name| var(name).ok())
+                .find(|v| !v.is_empty())
+                .map(|v| v.split(['.', '@']).next().unwrap_or_default().to_string())
+                .unwrap_or_else(|| "C".into())
+        })
+    }
+}
+
+/// If `TERM` names a terminal without any capabilities
+fn is_dumb() -> bool {
+    var("TERM").is_ok_and(|t| t == "dumb")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_overrides() {
+        let env = Environment {
+            width: Some(42),
+            color: Some(true),
+            locale: Some("nb_NO".into()),
+            ascii: Some(false),
+        };
+
+        assert_eq!(env.effective_width(), 42);
+        assert!(env.effective_color(&Stream::writer(Vec::new())));
+        assert_eq!(env.effective_locale(), "nb_NO");
+        assert!(!env.effective_ascii());
+
+        let dumb = Environment::dumb();
+        assert_eq!(dumb.effective_width(), 80);
+        assert!(!dumb.effective_color(&Stream::Stderr));
+        assert!(dumb.effective_ascii());
+    }
+}
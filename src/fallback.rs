@@ -0,0 +1,239 @@
+use std::{cmp::Reverse, collections::HashMap, fmt::Debug, sync::Arc};
+
+use crate::{pick_candidate, CliParser, Command, ParseError};
+
+type ResolverFn = dyn Fn(&str) -> Option<Command> + Send + Sync;
+
+/// A step in resolving unknown (sub)commands, see [CliParser::fallback]
+#[derive(Clone)]
+pub enum Fallback {
+    /// Resolve a unique prefix of a known command, see [CliParser::allow_abbreviations]
+    Abbreviation,
+    /// Return the command and its args as is, see [CliParser::allow_external_subcommands]
+    External,
+    /// Look up the definition of the command, like in a plugin registry,
+    /// returning `None` to continue with the next step
+    Resolver(Arc<ResolverFn>),
+}
+
+impl Fallback {
+    /// Create a [Fallback::Resolver]
+    pub fn resolver<F>(f: F) -> Self
+    where
+        F: Fn(&str) -> Option<Command> + Send + Sync + 'static,
+    {
+        Fallback::Resolver(Arc::new(f))
+    }
+}
+
+impl Debug for Fallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fallback::Abbreviation => f.write_str("Abbreviation"),
+            Fallback::External => f.write_str("External"),
+            Fallback::Resolver(_) => f.write_str("Resolver"),
+        }
+    }
+}
+
+/// How an unknown command was resolved
+pub(crate) enum Resolution {
+    /// Parse the command with this definition
+    Command(Box<Command>),
+    /// Parse the command as an external command
+    External,
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Run the unknown command `name` through the [Fallback]s, in order
+    pub(crate) fn resolve_unknown(
+        &self,
+        commands: &HashMap<String, Command>,
+        name: &str,
+    ) -> Result<Option<Resolution>, ParseError> {
+        for fallback in self.fallbacks.iter() {
+            let resolved = match fallback {
+                Fallback::Abbreviation => self
+                    .abbreviated(commands, name)?
+                    .map(|cmd| Resolution::Command(Box::new(cmd.clone()))),
+                Fallback::External if self.sandboxed => None,
+                Fallback::External => Some(Resolution::External),
+                Fallback::Resolver(resolve) => {
+                    resolve(name).map(|cmd| Resolution::Command(Box::new(cmd)))
+                }
+            };
+            if resolved.is_some() {
+                return Ok(resolved);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The id of `known` closest to the unknown `name`, the last step of the pipeline.
+    /// Ties go to the most used command, see [CliParser::usage_frequency]
+    pub(crate) fn closest<'a>(
+        &self,
+        name: &str,
+        known: impl Iterator<Item = &'a str>,
+    ) -> Option<String> {
+        // Allow about one typo per three characters
+        let max = (name.chars().count() / 3).max(1);
+        let parent = &self.outcome.command_path;
+        known
+            .map(|id| (edit_distance(name, id), id))
+            .filter(|(distance, _)| *distance <= max)
+            .min_by_key(|(distance, id)| (*distance, Reverse(self.usage_of(parent, id)), *id))
+            .map(|(_, id)| id.to_string())
+    }
+
+    /// Find the command `abbrev` is a prefix of, see [CliParser::allow_abbreviations]
+    pub(crate) fn abbreviated<'a>(
+        &self,
+        commands: &'a HashMap<String, Command>,
+        abbrev: &str,
+    ) -> Result<Option<&'a Command>, ParseError> {
+        if abbrev.is_empty() {
+            return Ok(None);
+        }
+
+        let mut candidates: Vec<&Command> = commands
+            .values()
+            .filter(|c| c.id.starts_with(abbrev))
+            .filter(|c| !c.hidden && self.has_capabilities(&c.capabilities))
+            .collect();
+        // Most used first, see [CliParser::usage_frequency]
+        let parent = &self.outcome.command_path;
+        candidates.sort_by_key(|c| (Reverse(self.usage_of(parent, &c.id)), c.id.clone()));
+        if candidates.len() < 2 {
+            return Ok(candidates.pop());
+        }
+
+        let ids: Vec<String> = candidates.iter().map(|c| c.id.clone()).collect();
+        if self.interactive && !self.sandboxed && self.streams.input.is_terminal() {
+            let mut prompts = self.streams.prompts.clone();
+            let picked = pick_candidate(&ids, &self.streams.input, &mut prompts);
+            if let Some(index) = picked {
+                return Ok(Some(candidates[index]));
+            }
+        }
+        Err(ParseError::AmbiguousCommand(abbrev.into(), ids))
+    }
+}
+
+/// Number of single character insertions, deletions, or substitutions turning `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fallbacks_in_order() {
+        let parse = |input: &str, fallbacks: Vec<Fallback>| {
+            let args = input.split(" ").map(|s| s.to_string());
+            let mut parser = CliParser::from_args(args).command(Command::new("status"));
+            for fallback in fallbacks {
+                parser = parser.fallback(fallback);
+            }
+            parser.parse()
+        };
+        let plugin = || Fallback::resolver(|name| Some(Command::new(&format!("plugin-{}", name))));
+
+        let cmd = parse("stat", vec![Fallback::Abbreviation, plugin()]).unwrap();
+        assert_eq!(cmd.id, "status");
+        let cmd = parse("stat", vec![plugin(), Fallback::Abbreviation]).unwrap();
+        assert_eq!(cmd.id, "plugin-stat");
+
+        let cmd = parse("stat x", vec![Fallback::External, plugin()]).unwrap();
+        assert_eq!(cmd.external(), Some(("stat", &["x".to_string()][..])));
+
+        assert!(matches!(
+            parse("stat", vec![]),
+            Err(ParseError::InvalidCommand(c)) if c == "stat"
+        ));
+    }
+
+    #[test]
+    fn test_ambiguous_most_used_first() {
+        let args = "remote s".split(" ").map(|s| s.to_string());
+        let res = CliParser::from_args(args)
+            .command(
+                Command::new("remote")
+                    .subcommand(Command::new("set-url"))
+                    .subcommand(Command::new("show")),
+            )
+            .allow_abbreviations()
+            .usage_frequency([("remote show".to_string(), 3)])
+            .parse();
+
+        assert!(matches!(
+            res,
+            Err(ParseError::AmbiguousCommand(_, ids)) if ids == ["show", "set-url"]
+        ));
+    }
+
+    #[test]
+    fn test_pick_from_input() {
+        let prompts = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let parse = |answer: &'static str| {
+            CliParser::parse_from(["st"])
+                .command(Command::new("stash"))
+                .command(Command::new("status"))
+                .allow_abbreviations()
+                .interactive()
+                .streams(crate::Streams {
+                    prompts: crate::Stream::Writer(prompts.clone()),
+                    input: crate::Input::reader(answer.as_bytes()),
+                    ..Default::default()
+                })
+                .parse()
+        };
+
+        assert_eq!(parse("2\n").unwrap().id, "status");
+        assert_eq!(
+            *prompts.lock().unwrap(),
+            b"Did you mean: 1) stash 2) status\n> "
+        );
+        assert!(matches!(parse("\n"), Err(ParseError::AmbiguousCommand(..))));
+    }
+
+    #[test]
+    fn test_suggest_closest() {
+        let mut parser = CliParser::from_args(std::iter::empty())
+            .command(Command::new("status"))
+            .command(Command::new("stash"))
+            .command(Command::new("secret").hidden())
+            .global_flag(crate::Flag::new("--verbose"))
+            .environment(crate::Environment::dumb());
+        let suggestion = |parser: &CliParser<_>| parser.error_context().unwrap().suggestion.clone();
+
+        let err = parser.parse_args(["stauts"]).unwrap_err();
+        assert_eq!(suggestion(&parser).as_deref(), Some("status"));
+        assert_eq!(
+            parser.render_error(&err),
+            "error: unknown command 'stauts' at position 0, did you mean 'status'?"
+        );
+        parser.parse_args(["status", "--verbsoe"]).unwrap_err();
+        assert_eq!(suggestion(&parser).as_deref(), Some("--verbose"));
+        parser.parse_args(["secre"]).unwrap_err();
+        assert_eq!(suggestion(&parser), None);
+        parser.parse_args(["deploy"]).unwrap_err();
+        assert_eq!(suggestion(&parser), None);
+    }
+}
@@ -0,0 +1,825 @@
+use std::collections::HashMap;
+
+use crate::{
+    standard::{HELP, HELP_COMMAND, SHORT_HELP},
+    style::Style,
+    CliParser, Command, Fallback, Flag, FlagKind, ParseError,
+};
+
+/// The layout of generated help, see [CliParser::help_template]
+pub const DEFAULT_HELP_TEMPLATE: &str =
+    "{usage}\n\n{about}\n\n{before_help}\n\n{arguments}\n\n{flags}\n\n{commands}\n\n{global_flags}\n\n{after_help}\n";
+
+/// Narrowest description column before descriptions move below their labels
+const MIN_DESCRIPTION_WIDTH: usize = 20;
+
+/// Every placeholder of [CliParser::help_template]
+const PLACEHOLDERS: &[&str] = &[
+    "usage",
+    "about",
+    "before_help",
+    "arguments",
+    "flags",
+    "commands",
+    "global_flags",
+    "after_help",
+];
+
+/// The parts of a help screen, before layout
+pub(crate) struct HelpPage {
+    // Localized `usage` label
+    pub(crate) usage_label: String,
+    // Like `git remote <COMMAND> [FLAGS]`
+    pub(crate) usage: String,
+    pub(crate) description: Option<String>,
+    // Free-form text around the sections, see [Command::before_help]
+    pub(crate) before: Option<String>,
+    pub(crate) after: Option<String>,
+    // In order, including empty ones
+    pub(crate) sections: Vec<Section>,
+}
+
+/// A titled list of (label, description) rows in a [HelpPage]
+pub(crate) struct Section {
+    // Message key of the title, like `flags`
+    pub(crate) key: &'static str,
+    pub(crate) title: String,
+    pub(crate) rows: Vec<(String, String)>,
+}
+
+/// Sort key listing items with a display order first, by that order, then the rest by id,
+/// see [Flag::display_order]
+pub(crate) fn display_key(order: Option<usize>, id: &str) -> (usize, &str) {
+    (order.unwrap_or(usize::MAX), id)
+}
+
+/// The flag with the value it takes, like `--out <VALUE>`
+pub(crate) fn flag_usage(flag: &Flag) -> String {
+    match flag.kind {
+        FlagKind::Value if !flag.tuple.is_empty() => {
+            let names: Vec<String> = flag.tuple.iter().map(|(n, _)| format!("<{}>", n)).collect();
+            format!("{} {}", flag.id, names.join(" "))
+        }
+        FlagKind::Value => format!("{} <VALUE>", flag.id),
+        _ => flag.id.clone(),
+    }
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Register the exclusive global [HELP] and [SHORT_HELP] flags. [CliParser::parse_or_exit]
+    /// prints the detailed or the summarized help of the matched command and exits
+    /// when one is passed
+    pub fn help_flag(self) -> Self {
+        self.global_flag(Flag::new(HELP).exclusive())
+            .global_flag(Flag::new(SHORT_HELP).exclusive())
+    }
+
+    /// Register the [HELP_COMMAND] command, so `app help remote add` prints the help of
+    /// `remote add` like `app remote add --help` does, and `app help` the help of the
+    /// whole program. Command names resolve through abbreviations when they are allowed,
+    /// see [CliParser::allow_abbreviations]. [CliParser::parse_or_exit] prints the help
+    /// and exits when the command is matched
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["help", "deploy"])
+    ///     .command(Command::new("deploy").positional())
+    ///     .help_command()
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.id, HELP_COMMAND);
+    /// assert_eq!(cmd.trailing, ["deploy"]);
+    /// ```
+    pub fn help_command(mut self) -> Self {
+        self.add_command(
+            Command::new(HELP_COMMAND)
+                .about("Print the help of a command")
+                .trailing_args(),
+        );
+        self.help_command = true;
+        self
+    }
+
+    /// Print the help of the program instead of an error when it is run without any args,
+    /// see [CliParser::parse_or_exit]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let code = CliParser::parse_from(std::iter::empty::<String>())
+    ///     .command(Command::new("build"))
+    ///     .streams(Streams {
+    ///         errors: Stream::writer(std::io::sink()),
+    ///         ..Default::default()
+    ///     })
+    ///     .help_on_empty()
+    ///     .parse_or_print();
+    /// assert_eq!(code.unwrap_err(), USAGE_EXIT_CODE);
+    /// ```
+    pub fn help_on_empty(mut self) -> Self {
+        self.help_on_empty = true;
+        self
+    }
+
+    /// Set the program name shown in help, the file name of the running
+    /// executable by default
+    pub fn bin_name(mut self, name: &str) -> Self {
+        self.bin_name = Some(name.into());
+        self
+    }
+
+    /// Render the help of the whole program, listing the commands and global flags
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("git")
+    ///     .command(Command::new("status"))
+    ///     .help_flag();
+    ///
+    /// assert_eq!(
+    ///     parser.help(),
+    ///     "usage: git <COMMAND> [FLAGS]\n\ncommands:\n  status\n\nglobal flags:\n  --help\n  -h\n"
+    /// );
+    /// ```
+    pub fn help(&self) -> String {
+        self.render_help::<&str>(&[], true)
+            .expect("the program help is always found")
+    }
+
+    /// Render a summary of the help of the whole program, see [CliParser::command_short_help]
+    pub fn short_help(&self) -> String {
+        self.render_help::<&str>(&[], false)
+            .expect("the program help is always found")
+    }
+
+    /// Render the help of the command at `path`, like `["remote", "add"]`, listing its
+    /// positionals, flags with their requirements and defaults, subcommands, and the
+    /// global flags. `None` if there is no such command
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("app")
+    ///     .command(
+    ///         Command::new("deploy")
+    ///             .positional_arg(Positional::new("TARGET"))
+    ///             .flag(Flag::new("--env").positional().default_value("staging")),
+    ///     );
+    ///
+    /// assert_eq!(
+    ///     parser.command_help(&["deploy"]).unwrap(),
+    ///     "usage: app deploy <TARGET> [FLAGS]\n\n\
+    ///      arguments:\n  <TARGET>\n\n\
+    ///      flags:\n  --env <VALUE>  (default: staging)\n"
+    /// );
+    /// ```
+    pub fn command_help<S: AsRef<str>>(&self, path: &[S]) -> Option<String> {
+        self.render_help(path, true)
+    }
+
+    /// Render a summary of the help of the command at `path`, as printed for [SHORT_HELP].
+    /// Unlike [CliParser::command_help], it shows the [Command::about] instead of the
+    /// [Command::long_about], leaves out the before and after help, and keeps each flag
+    /// on one line with the first line of its [Flag::help].
+    /// `None` if there is no such command
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("app")
+    ///     .command(
+    ///         Command::new("deploy")
+    ///             .about("Deploy the app")
+    ///             .long_about("Deploy the app to every configured region")
+    ///             .flag(
+    ///                 Flag::new("--force")
+    ///                     .help("Skip checks")
+    ///                     .long_help("Skip checks.\n\nExample: app deploy --force"),
+    ///             ),
+    ///     );
+    ///
+    /// assert_eq!(
+    ///     parser.command_short_help(&["deploy"]).unwrap(),
+    ///     "usage: app deploy [FLAGS]\n\nDeploy the app\n\nflags:\n  --force  Skip checks\n"
+    /// );
+    /// ```
+    pub fn command_short_help<S: AsRef<str>>(&self, path: &[S]) -> Option<String> {
+        self.render_help(path, false)
+    }
+
+    /// Lay out the help with placeholders replaced by the parts of the help, instead of
+    /// the default [DEFAULT_HELP_TEMPLATE], e.g. for branded help screens.
+    ///
+    /// The placeholders are `{usage}`, `{about}`, `{before_help}`, `{after_help}`, and the
+    /// sections `{arguments}`, `{flags}`, `{commands}`, and `{global_flags}`, each with its title. Parts that are empty
+    /// leave no blank lines behind. `{flags}` and `{global_flags}` include the sections
+    /// of their [Flag::help_heading]s
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("app")
+    ///     .command(Command::new("run").about("Run the app"))
+    ///     .help_template("== app ==\n{commands}\n\n{flags}\n\n{usage}");
+    ///
+    /// assert_eq!(
+    ///     parser.help(),
+    ///     "== app ==\ncommands:\n  run  Run the app\n\nusage: app <COMMAND> [FLAGS]\n"
+    /// );
+    /// ```
+    pub fn help_template(mut self, template: &str) -> Self {
+        self.help_template = Some(template.into());
+        self
+    }
+
+    /// The usage of the command the last parse failed in, and a hint to pass [HELP] when
+    /// it is registered, appended to errors by [CliParser::parse_or_exit]
+    pub(crate) fn error_hint(&self) -> String {
+        let path = match &self.error_context {
+            Some(context) => &context.path[..],
+            None => &[],
+        };
+        let mut hint = String::new();
+        if let Some(page) = self.help_page(path, false) {
+            hint.push_str(&format!("\n\n{}: {}", page.usage_label, page.usage));
+        }
+        if self.global_flags.contains_key(HELP) {
+            let locale = self.environment.effective_locale();
+            let flag = Style::new(&self.environment, &self.streams.errors).bold(HELP);
+            let text = self.messages.format(&locale, "hint.help", &[&flag]);
+            hint.push_str(&format!("\n\n{}", text));
+        }
+        hint
+    }
+
+    /// The help of the command named by the args of the matched [HELP_COMMAND] `command`
+    pub(crate) fn help_command_output(&self, command: &Command) -> Result<String, ParseError> {
+        let abbreviations = self
+            .fallbacks
+            .iter()
+            .any(|f| matches!(f, Fallback::Abbreviation));
+        let mut commands = &self.commands;
+        let mut path: Vec<String> = Vec::new();
+        for name in command.trailing.iter().map(|a| a.to_string_lossy()) {
+            let found = match commands.get(name.as_ref()) {
+                Some(found) => Some(found),
+                None if abbreviations => self.abbreviated(commands, &name)?,
+                None => None,
+            };
+            let found = found.ok_or_else(|| ParseError::InvalidCommand(name.to_string()))?;
+            path.push(found.id.clone());
+            commands = &found.subcommands;
+        }
+        Ok(self.command_help(&path).expect("the command was found"))
+    }
+
+    /// The detailed help when `long`, otherwise the summary
+    pub(crate) fn render_help<S: AsRef<str>>(&self, path: &[S], long: bool) -> Option<String> {
+        let page = self.help_page(path, long)?;
+        let columns = self.environment.effective_width();
+        // The summary keeps every row on one line
+        let row_columns = match long {
+            true => columns,
+            false => usize::MAX,
+        };
+        let paragraphs = |text: &Option<String>| {
+            let text = text.as_deref().unwrap_or_default();
+            let lines: Vec<String> = text
+                .split('\n')
+                .map(|l| wrap(l, columns).join("\n"))
+                .collect();
+            lines.join("\n")
+        };
+        let mut parts = vec![
+            ("usage", format!("{}: {}", page.usage_label, page.usage)),
+            ("about", paragraphs(&page.description)),
+            ("before_help", paragraphs(&page.before)),
+            ("after_help", paragraphs(&page.after)),
+        ];
+        for section in &page.sections {
+            let mut block = String::new();
+            if !section.rows.is_empty() {
+                block.push_str(&format!("{}:", section.title));
+            }
+            let width = section
+                .rows
+                .iter()
+                .map(|(label, _)| label.chars().count())
+                .max()
+                .unwrap_or(0);
+            // Descriptions wrap with a hanging indent under the description column,
+            // or start on the next line when the labels leave too little room
+            let indent = match row_columns.saturating_sub(width + 4) < MIN_DESCRIPTION_WIDTH {
+                true => 8,
+                false => width + 4,
+            };
+            for (label, notes) in &section.rows {
+                let lines: Vec<String> = notes
+                    .split('\n')
+                    .flat_map(|l| wrap(l, row_columns.saturating_sub(indent)))
+                    .collect();
+                block.push_str(&format!("\n  {}", label));
+                for (i, line) in lines.iter().enumerate() {
+                    match (i, indent == width + 4) {
+                        (0, true) => {
+                            let pad = width - label.chars().count() + 2;
+                            block.push_str(&format!("{:pad$}{}", "", line, pad = pad));
+                        }
+                        _ => block.push_str(&format!("\n{:indent$}{}", "", line, indent = indent)),
+                    }
+                }
+            }
+            // Flags under a help heading follow the other flags in the same part
+            match parts.iter_mut().find(|(key, _)| *key == section.key) {
+                Some((_, part)) if part.is_empty() => *part = block,
+                Some((_, part)) if !block.is_empty() => part.push_str(&format!("\n\n{}", block)),
+                Some(_) => {}
+                None => parts.push((section.key, block)),
+            }
+        }
+
+        let mut out = self
+            .help_template
+            .as_deref()
+            .unwrap_or(DEFAULT_HELP_TEMPLATE)
+            .to_string();
+        for (key, part) in parts {
+            out = out.replace(&format!("{{{}}}", key), &part);
+        }
+        // Sections the page does not have, like the flags of the program itself
+        for key in PLACEHOLDERS {
+            out = out.replace(&format!("{{{}}}", key), "");
+        }
+        Some(collapse_blank_lines(&out))
+    }
+
+    /// The parts of the help of the command at `path`, detailed when `long`, otherwise
+    /// summarized. `None` if there is no such command
+    pub(crate) fn help_page<S: AsRef<str>>(&self, path: &[S], long: bool) -> Option<HelpPage> {
+        let mut commands = &self.commands;
+        let mut matched: Vec<&Command> = Vec::new();
+        for id in path {
+            let command = commands.get(id.as_ref())?;
+            commands = &command.subcommands;
+            matched.push(command);
+        }
+
+        let locale = self.environment.effective_locale();
+        let text = |key: &str| self.messages.template(&locale, key).to_string();
+        let section = |key: &'static str, rows| Section {
+            key,
+            title: text(key),
+            rows,
+        };
+
+        let mut usage = vec![self.program_name()];
+        usage.extend(matched.iter().map(|c| c.id.clone()));
+        let mut sections = Vec::new();
+        if let Some(command) = matched.last() {
+            let mut arguments = Vec::new();
+            if command.positional {
+                arguments.push("<POSITIONAL>".to_string());
+            }
+            arguments.extend(command.positionals.iter().map(|p| format!("<{}>", p.id)));
+            usage.extend(arguments.iter().cloned());
+            let rows = arguments.into_iter().map(|a| (a, String::new())).collect();
+            sections.push(section("arguments", rows));
+            sections.extend(self.flag_sections("flags", &command.flags, &locale, long));
+        }
+
+        let commands: Vec<(String, String)> = self
+            .visible_commands(commands)
+            .into_iter()
+            .map(|c| (c.id.clone(), c.about.clone().unwrap_or_default()))
+            .collect();
+        if !commands.is_empty() {
+            usage.push("<COMMAND>".into());
+        }
+        usage.push("[FLAGS]".into());
+        sections.push(section("commands", commands));
+        sections.extend(self.flag_sections("global_flags", &self.global_flags, &locale, long));
+
+        let command = matched.last();
+        let description = match long {
+            true => command.and_then(|c| c.description()),
+            false => command.and_then(|c| c.summary()),
+        };
+        Some(HelpPage {
+            usage_label: text("usage"),
+            usage: usage.join(" "),
+            description: description.map(String::from),
+            before: command.and_then(|c| c.before_help.clone()).filter(|_| long),
+            after: command.and_then(|c| c.after_help.clone()).filter(|_| long),
+            sections,
+        })
+    }
+
+    /// The commands shown in generated output, in display order, see [Command::display_order]
+    pub(crate) fn visible_commands<'a>(
+        &self,
+        commands: &'a HashMap<String, Command>,
+    ) -> Vec<&'a Command> {
+        let mut commands: Vec<&Command> = commands
+            .values()
+            .filter(|c| !c.hidden && self.has_capabilities(&c.capabilities))
+            .collect();
+        commands.sort_by(|a, b| {
+            display_key(a.display_order, &a.id).cmp(&display_key(b.display_order, &b.id))
+        });
+        commands
+    }
+
+    /// The sections listing the visible `flags` in display order, first the ones without
+    /// a [Flag::help_heading] titled by the message `key`, then a section per heading,
+    /// in the order the headings first appear
+    fn flag_sections(
+        &self,
+        key: &'static str,
+        flags: &HashMap<String, Flag>,
+        locale: &str,
+        long: bool,
+    ) -> Vec<Section> {
+        let mut flags: Vec<&Flag> = flags
+            .values()
+            .filter(|f| !f.hidden && self.has_capabilities(&f.capabilities))
+            .collect();
+        flags.sort_by(|a, b| {
+            display_key(a.display_order, &a.id).cmp(&display_key(b.display_order, &b.id))
+        });
+        let mut sections = vec![Section {
+            key,
+            title: self.messages.template(locale, key).to_string(),
+            rows: Vec::new(),
+        }];
+        for flag in flags {
+            let row = self.flag_row(flag, locale, long);
+            let heading = match &flag.help_heading {
+                Some(heading) => heading,
+                None => {
+                    sections[0].rows.push(row);
+                    continue;
+                }
+            };
+            match sections[1..].iter_mut().find(|s| &s.title == heading) {
+                Some(section) => section.rows.push(row),
+                None => sections.push(Section {
+                    key,
+                    title: heading.clone(),
+                    rows: vec![row],
+                }),
+            }
+        }
+        sections
+    }
+
+    /// The flag with its requirements and default, and its detailed description
+    /// when `long`, otherwise the first line of its description
+    fn flag_row(&self, flag: &Flag, locale: &str, long: bool) -> (String, String) {
+        let mut notes = Vec::new();
+        if flag.required {
+            notes.push(self.messages.template(locale, "required").to_string());
+        }
+        if let Some(default) = &flag.default_val {
+            let label = self.messages.template(locale, "default");
+            notes.push(format!("{}: {}", label, default));
+        }
+        let help = match long {
+            true => flag.long_description(),
+            false => flag.description().and_then(|h| h.lines().next()),
+        };
+        let mut description: Vec<String> = help.into_iter().map(String::from).collect();
+        if !notes.is_empty() {
+            description.push(format!("({})", notes.join(", ")));
+        }
+        (flag_usage(flag), description.join(" "))
+    }
+
+    pub(crate) fn program_name(&self) -> String {
+        self.bin_name.clone().unwrap_or_else(|| {
+            std::env::args_os()
+                .next()
+                .as_ref()
+                .and_then(|arg| std::path::Path::new(arg).file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Split `text` into lines of at most `width` characters at spaces,
+/// words longer than `width` get a line of their own
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Collapse runs of blank lines left by empty parts into one, without any
+/// leading blank lines, ending with a single newline
+fn collapse_blank_lines(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            blank = !out.is_empty();
+            continue;
+        }
+        if blank {
+            out.push('\n');
+            blank = false;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_command_help() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("git")
+            .command(
+                Command::new("remote")
+                    .about("Manage remotes")
+                    .flag(Flag::new("--verbose").help("Show urls"))
+                    .subcommand(
+                        Command::new("add")
+                            .about("Add a remote")
+                            .long_about("Add a remote named <NAME>")
+                            .positional_arg(Positional::new("NAME"))
+                            .flag(Flag::new("--fetch").required())
+                            .flag(
+                                Flag::new("--tags")
+                                    .positional()
+                                    .required()
+                                    .default_value("all"),
+                            )
+                            .flag(Flag::new("--debug").hidden()),
+                    )
+                    .subcommand(Command::new("prune").hidden()),
+            )
+            .with_standard_flags();
+
+        assert_eq!(
+            parser.command_help(&["remote", "add"]).unwrap(),
+            concat!(
+                "usage: git remote add <NAME> [FLAGS]\n",
+                "\n",
+                "Add a remote named <NAME>\n",
+                "\n",
+                "arguments:\n",
+                "  <NAME>\n",
+                "\n",
+                "flags:\n",
+                "  --fetch         (required)\n",
+                "  --tags <VALUE>  (required, default: all)\n",
+                "\n",
+                "global flags:\n",
+                "  --help\n",
+                "  --quiet\n",
+                "  --verbose\n",
+                "  --version\n",
+                "  -h\n",
+            )
+        );
+        assert!(parser
+            .command_help(&["remote"])
+            .unwrap()
+            .starts_with(concat!(
+                "usage: git remote <COMMAND> [FLAGS]\n\nManage remotes\n\n",
+                "flags:\n  --verbose  Show urls\n\ncommands:\n  add  Add a remote\n"
+            )));
+        assert!(parser.command_help(&["remote", "nope"]).is_none());
+    }
+
+    #[test]
+    fn test_short_help() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("app")
+            .environment(Environment {
+                width: Some(40),
+                ..Default::default()
+            })
+            .command(
+                Command::new("deploy")
+                    .about("Deploy the app")
+                    .long_about("Deploy the app to every region")
+                    .after_help("See the deploy docs")
+                    .flag(
+                        Flag::new("--region")
+                            .positional()
+                            .help("Region to deploy to, all configured regions by default")
+                            .long_help("Region to deploy to\nExample: app deploy --region eu"),
+                    ),
+            )
+            .help_flag();
+
+        assert_eq!(
+            parser.command_short_help(&["deploy"]).unwrap(),
+            concat!(
+                "usage: app deploy [FLAGS]\n\nDeploy the app\n\n",
+                "flags:\n",
+                "  --region <VALUE>  Region to deploy to, all configured regions by default\n\n",
+                "global flags:\n  --help\n  -h\n",
+            )
+        );
+        assert_eq!(
+            parser.command_help(&["deploy"]).unwrap(),
+            concat!(
+                "usage: app deploy [FLAGS]\n\nDeploy the app to every region\n\n",
+                "flags:\n",
+                "  --region <VALUE>  Region to deploy to\n",
+                "                    Example: app deploy\n",
+                "                    --region eu\n\n",
+                "global flags:\n  --help\n  -h\n\n",
+                "See the deploy docs\n",
+            )
+        );
+        let cmd = CliParser::parse_from(["deploy", "-h"])
+            .command(Command::new("deploy"))
+            .help_flag()
+            .parse()
+            .unwrap();
+        assert_eq!(cmd.exclusive_flag.as_deref(), Some(SHORT_HELP));
+    }
+
+    #[test]
+    fn test_help_command() {
+        let help = |args: &[&str]| {
+            let mut parser = CliParser::parse_from(args.iter().copied())
+                .bin_name("git")
+                .command(Command::new("remote").subcommand(Command::new("add").positional()))
+                .allow_abbreviations()
+                .with_standard_flags();
+            let cmd = parser.parse().unwrap();
+            assert_eq!(cmd.id, HELP_COMMAND);
+            (parser.help_command_output(&cmd), parser)
+        };
+
+        let (output, parser) = help(&["help", "rem", "add"]);
+        assert_eq!(
+            output.unwrap(),
+            parser.command_help(&["remote", "add"]).unwrap()
+        );
+        let (output, parser) = help(&["help"]);
+        assert_eq!(output.unwrap(), parser.help());
+        assert!(parser
+            .help()
+            .contains("  help    Print the help of a command\n"));
+        assert!(matches!(
+            help(&["help", "remote", "nope"]).0,
+            Err(ParseError::InvalidCommand(c)) if c == "nope"
+        ));
+    }
+
+    #[test]
+    fn test_display_order() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("app")
+            .command(Command::new("build"))
+            .command(Command::new("test").display_order(1))
+            .command(Command::new("run").display_order(0))
+            .global_flag(Flag::new("--color"))
+            .global_flag(Flag::new("--verbose").display_order(0));
+
+        assert_eq!(
+            parser.help(),
+            concat!(
+                "usage: app <COMMAND> [FLAGS]\n\n",
+                "commands:\n  run\n  test\n  build\n\n",
+                "global flags:\n  --verbose\n  --color\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_help_headings() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("cargo")
+            .command(
+                Command::new("build")
+                    .flag(Flag::new("--target").help_heading("Compilation"))
+                    .flag(Flag::new("--jobs").help_heading("Compilation"))
+                    .flag(Flag::new("--offline").help_heading("Manifest"))
+                    .flag(Flag::new("--all").display_order(0).help_heading("Manifest")),
+            )
+            .global_flag(Flag::new("--color").help_heading("Display"))
+            .global_flag(Flag::new("--verbose"));
+
+        assert_eq!(
+            parser.command_help(&["build"]).unwrap(),
+            concat!(
+                "usage: cargo build [FLAGS]\n\n",
+                "Manifest:\n  --all\n  --offline\n\n",
+                "Compilation:\n  --jobs\n  --target\n\n",
+                "global flags:\n  --verbose\n\n",
+                "Display:\n  --color\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_help_wrapping() {
+        let parser = |width: usize| {
+            CliParser::from_args(std::iter::empty())
+                .bin_name("app")
+                .environment(Environment {
+                    width: Some(width),
+                    ..Default::default()
+                })
+                .command(
+                    Command::new("run")
+                        .about("Run the app in the current directory")
+                        .flag(
+                            Flag::new("--jobs")
+                                .positional()
+                                .default_value("4")
+                                .help("Number of parallel jobs, defaults to the number of cores"),
+                        ),
+                )
+        };
+
+        assert_eq!(
+            parser(40).command_help(&["run"]).unwrap(),
+            concat!(
+                "usage: app run [FLAGS]\n\n",
+                "Run the app in the current directory\n\n",
+                "flags:\n",
+                "  --jobs <VALUE>  Number of parallel\n",
+                "                  jobs, defaults to the\n",
+                "                  number of cores\n",
+                "                  (default: 4)\n",
+            )
+        );
+        assert_eq!(
+            parser(30).command_help(&["run"]).unwrap(),
+            concat!(
+                "usage: app run [FLAGS]\n\n",
+                "Run the app in the current\n",
+                "directory\n\n",
+                "flags:\n",
+                "  --jobs <VALUE>\n",
+                "        Number of parallel\n",
+                "        jobs, defaults to the\n",
+                "        number of cores\n",
+                "        (default: 4)\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_help_template() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .bin_name("app")
+            .command(
+                Command::new("run")
+                    .long_about("Run the app\n\nfor real")
+                    .before_help("Careful")
+                    .after_help("Docs: example.com")
+                    .flag(Flag::new("--fast")),
+            )
+            .help_template(
+                "{about}\n\n\n{arguments}\n{flags}\n---\n{usage}\n{commands}{after_help}",
+            );
+
+        assert_eq!(
+            parser.command_help(&["run"]).unwrap(),
+            "Run the app\n\nfor real\n\nflags:\n  --fast\n---\nusage: app run [FLAGS]\nDocs: example.com\n"
+        );
+        assert_eq!(
+            parser
+                .help_template(DEFAULT_HELP_TEMPLATE)
+                .command_help(&["run"])
+                .unwrap(),
+            concat!(
+                "usage: app run [FLAGS]\n\nRun the app\n\nfor real\n\nCareful\n\n",
+                "flags:\n  --fast\n\nDocs: example.com\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,632 @@
+use crate::{Command, FlagKind, Limit, Limits, ParseError, ValueSource};
+
+impl Command {
+    /// Reconstruct the args of a parsed command, e.g. to spawn it again.
+    ///
+    /// Flags are emitted in id order, flags filled in from a default are left out.
+    ///
+    /// ## Round-tripping
+    /// Parsing the args again with the same definition gives an equivalent result,
+    /// with the same args from `to_args`. The original args are not always reproduced:
+    /// - Presence flags passed more than once are emitted once
+    /// - Values of a [crate::Flag::value_delimiter] flag are joined into a single value
+    /// - Abbreviated commands and `/flag` or `-flag` style flags are emitted in full
+    /// - Args skipped by [crate::ParseMode::Lenient] and after a [crate::Flag::exclusive]
+    ///   flag are lost
+    /// - Args that were not valid unicode are lossily converted,
+    ///   see [crate::CliParser::from_args_os]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "copy src --out dest".split(" ").map(|s| s.to_string());
+    /// let cmd = CliParser::from_args(args)
+    ///     .command(Command::new("copy").positional().flag(Flag::new("--out").positional()))
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.to_args(), ["copy", "src", "--out", "dest"]);
+    /// ```
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![self.id.clone()];
+        args.extend(self.positional_val.clone());
+        for recipe in self.positionals.iter() {
+            if let Some(value) = self
+                .parsed_positionals
+                .get(&recipe.id)
+                .and_then(|p| p.value.clone())
+            {
+                args.push(value);
+            }
+        }
+
+        let mut flags: Vec<_> = self
+            .parsed_flags
+            .values()
+            .filter(|f| f.source == ValueSource::Cli)
+            .collect();
+        flags.sort_by(|a, b| a.id.cmp(&b.id));
+        for flag in flags {
+            match flag.kind {
+                FlagKind::Bool => args.push(flag.id.clone()),
+                FlagKind::Count => args.extend((0..flag.occurrences).map(|_| flag.id.clone())),
+                FlagKind::Value => match flag.value_delimiter {
+                    Some(delimiter) => {
+                        args.push(flag.id.clone());
+                        args.push(flag.values.join(&delimiter.to_string()));
+                    }
+                    None => {
+                        for values in flag.values.chunks(flag.tuple.len().max(1)) {
+                            args.push(flag.id.clone());
+                            args.extend(values.iter().cloned());
+                        }
+                    }
+                },
+            }
+        }
+
+        args.extend(self.external_args.iter().flatten().cloned());
+        if !self.trailing.is_empty() {
+            args.push("--".into());
+            args.extend(
+                self.trailing
+                    .iter()
+                    .map(|arg| arg.to_string_lossy().into_owned()),
+            );
+        }
+        if let Some(sub) = &self.subcommand {
+            args.extend(sub.to_args());
+        }
+        args
+    }
+}
+
+/// Join args into a single Windows command line, quoting them by the
+/// MSVC CRT rules so `CreateProcess` splits them back into the same args.
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let line = windows_command_line(&["copy", "my file", r#"say "hi""#]);
+/// assert_eq!(line, r#"copy "my file" "say \"hi\"""#);
+/// ```
+pub fn windows_command_line<S: AsRef<str>>(args: &[S]) -> String {
+    args.iter()
+        .map(|arg| windows_quote(arg.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote a single arg by the MSVC CRT rules
+pub fn windows_quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // Escape all preceding backslashes, and the quote itself
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            c => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // Backslashes before the closing quote must be escaped
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Split `line` into args like a POSIX shell, without any expansion.
+///
+/// Args are separated by whitespace. Single quotes keep everything up to the
+/// closing quote as is, double quotes allow escaping `"`, `\`, `$` and `` ` ``
+/// with a backslash, and a backslash outside quotes keeps the next character as is.
+/// Quoted and unquoted parts next to each other form a single arg
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let args = shell_split(r#"commit -m "fix: \"quoted\" bug" --author='A B' a\ b"#).unwrap();
+/// assert_eq!(args, ["commit", "-m", r#"fix: "quoted" bug"#, "--author=A B", "a b"]);
+///
+/// assert!(matches!(shell_split("echo 'oops"), Err(ParseError::UnterminatedQuote(_))));
+/// ```
+pub fn shell_split(line: &str) -> Result<Vec<String>, ParseError> {
+    shell_split_limited(line, Limits::default())
+}
+
+/// [shell_split] for untrusted lines, failing with [ParseError::LimitExceeded]
+/// as soon as an arg breaks one of the `limits`
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let limits = Limits {
+///     max_args: Some(2),
+///     ..Limits::default()
+/// };
+/// assert!(shell_split_limited("a b", limits).is_ok());
+/// assert!(matches!(
+///     shell_split_limited("a b c d", limits),
+///     Err(ParseError::LimitExceeded(Limit::Args(3)))
+/// ));
+/// ```
+pub fn shell_split_limited(line: &str, limits: Limits) -> Result<Vec<String>, ParseError> {
+    let mut args = Vec::new();
+    // The arg being built, `None` between args so `""` still makes an empty arg
+    let mut arg: Option<String> = None;
+    let mut chars = line.chars();
+    // Add the finished arg to `args`, enforcing the limits
+    let finish = |args: &mut Vec<String>, arg: Option<String>| {
+        let arg = match arg {
+            Some(arg) => arg,
+            None => return Ok(()),
+        };
+        if limits.max_args.is_some_and(|max| args.len() >= max) {
+            return Err(ParseError::LimitExceeded(Limit::Args(args.len() + 1)));
+        }
+        if limits.max_arg_len.is_some_and(|max| arg.len() > max) {
+            return Err(ParseError::LimitExceeded(Limit::ArgLength(arg.len())));
+        }
+        args.push(arg);
+        Ok(())
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => finish(&mut args, arg.take())?,
+            '\\' => {
+                let arg = arg.get_or_insert_with(String::new);
+                arg.extend(chars.next());
+            }
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(ParseError::UnterminatedQuote(arg.clone())),
+                    }
+                }
+            }
+            '"' => {
+                let arg = arg.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => arg.push(c),
+                            Some(c) => arg.extend(['\\', c]),
+                            None => return Err(ParseError::UnterminatedQuote(arg.clone())),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(ParseError::UnterminatedQuote(arg.clone())),
+                    }
+                }
+            }
+            c => arg.get_or_insert_with(String::new).push(c),
+        }
+    }
+    finish(&mut args, arg)?;
+    Ok(args)
+}
+
+/// Collect the args in a URL query string, one per `arg` parameter in order,
+/// e.g. for browser based demos on WASM where there are no process args.
+///
+/// A leading `?` is skipped, other parameters are ignored, and values are
+/// decoded like a form: `+` is a space and `%XX` escapes are decoded,
+/// leaving invalid escapes as is
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let args = query_args("?arg=deploy&arg=--tag&arg=my+app%21&theme=dark");
+/// assert_eq!(args, ["deploy", "--tag", "my app!"]);
+/// ```
+pub fn query_args(query: &str) -> Vec<String> {
+    query
+        .strip_prefix('?')
+        .unwrap_or(query)
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("arg="))
+        .map(form_decode)
+        .collect()
+}
+
+/// Decode a form encoded query value, converting invalid UTF-8 lossily
+fn form_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let hex = value
+                    .get(i + 1..i + 3)
+                    .filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()));
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Platform whose command line length limit to check against, see [check_command_line]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// Args are passed as separate NUL terminated strings, limited by `ARG_MAX`
+    Unix,
+    /// Args are joined into a single UTF-16 command line of at most 32767 units
+    Windows,
+}
+
+impl Platform {
+    /// The platform the program is running on
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::Unix
+        }
+    }
+
+    /// Maximum length of a command line, conservative since the real
+    /// `ARG_MAX` also covers the environment and differs between systems
+    pub fn max_len(self) -> usize {
+        match self {
+            Platform::Unix => 128 * 1024,
+            // Excluding the terminating NUL
+            Platform::Windows => 32_766,
+        }
+    }
+
+    /// Length of `args` as passed to the OS
+    pub fn command_line_len<S: AsRef<str>>(self, args: &[S]) -> usize {
+        match self {
+            Platform::Unix => args.iter().map(|arg| arg.as_ref().len() + 1).sum(),
+            Platform::Windows => windows_command_line(args).encode_utf16().count(),
+        }
+    }
+}
+
+/// A command line over the limit of its [Platform], see [check_command_line]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandLineTooLong {
+    pub len: usize,
+    pub max: usize,
+    pub platform: Platform,
+}
+
+impl std::fmt::Display for CommandLineTooLong {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "command line of length {} exceeds the {:?} limit of {}, \
+            pass the args in a response file instead",
+            self.len, self.platform, self.max
+        )
+    }
+}
+
+impl std::error::Error for CommandLineTooLong {}
+
+/// Check that `args`, like from [Command::to_args], fit on a single command line
+/// on `platform`, returning the length they take up.
+///
+/// Tools spawning commands too long to fit should fall back to passing
+/// the args in a response file, like `tool @args.txt`
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let args = vec!["x".repeat(40_000)];
+/// assert!(check_command_line(&args, Platform::Unix).is_ok());
+///
+/// let err = check_command_line(&args, Platform::Windows).unwrap_err();
+/// assert_eq!(err.len, 40_000);
+/// ```
+pub fn check_command_line<S: AsRef<str>>(
+    args: &[S],
+    platform: Platform,
+) -> Result<usize, CommandLineTooLong> {
+    let len = platform.command_line_len(args);
+    let max = platform.max_len();
+    match len > max {
+        true => Err(CommandLineTooLong { len, max, platform }),
+        false => Ok(len),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CliParser, Flag, Positional};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_to_args() {
+        let args = "remote --verbose add origin --url x --tag a --tag b --debug --debug"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let cmd = CliParser::from_args(args)
+            .command(
+                Command::new("remote")
+                    .flag(Flag::new("--verbose"))
+                    .subcommand(
+                        Command::new("add")
+                            .positional_arg(Positional::new("NAME"))
+                            .flag(Flag::new("--url").positional())
+                            .flag(Flag::new("--tag").positional())
+                            .flag(Flag::new("--debug").counted())
+                            .flag(Flag::new("--depth").default_value("1")),
+                    ),
+            )
+            .on_duplicate(crate::DuplicatePolicy::Collect)
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            cmd.to_args(),
+            vec![
+                "remote",
+                "--verbose",
+                "add",
+                "origin",
+                "--debug",
+                "--debug",
+                "--tag",
+                "a",
+                "--tag",
+                "b",
+                "--url",
+                "x"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_command_line() {
+        let args = ["copy", "my file"];
+        assert_eq!(check_command_line(&args, Platform::Unix), Ok(13));
+        assert_eq!(check_command_line(&args, Platform::Windows), Ok(14));
+
+        let long = vec!["a".repeat(Platform::Unix.max_len())];
+        assert_eq!(
+            check_command_line(&long, Platform::Unix),
+            Err(CommandLineTooLong {
+                len: Platform::Unix.max_len() + 1,
+                max: Platform::Unix.max_len(),
+                platform: Platform::Unix,
+            })
+        );
+    }
+
+    /// Small deterministic generator, so failures are reproducible from the seed
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn chance(&mut self) -> bool {
+            self.below(2) == 0
+        }
+
+        fn word(&mut self) -> String {
+            let len = 1 + self.below(4);
+            (0..len)
+                .map(|_| (b'a' + self.below(26) as u8) as char)
+                .collect()
+        }
+    }
+
+    fn random_flag(rng: &mut Rng, id: String) -> Flag {
+        match rng.below(5) {
+            0 => Flag::new(&id),
+            1 => Flag::new(&id).positional(),
+            2 => Flag::new(&id).counted(),
+            3 => Flag::new(&id).value_delimiter(','),
+            _ => Flag::new(&id).tuple([
+                ("A", crate::ValueParser::new(String::from_str)),
+                ("B", crate::ValueParser::new(String::from_str)),
+            ]),
+        }
+    }
+
+    fn random_command(rng: &mut Rng, id: String, depth: usize) -> Command {
+        let mut command = Command::new(&id);
+        if rng.chance() {
+            command = command.positional();
+        }
+        for i in 0..rng.below(3) {
+            command = command.positional_arg(Positional::new(&format!("{}-P{}", id, i)));
+        }
+        for i in 0..rng.below(4) {
+            command = command.flag(random_flag(rng, format!("--{}-f{}", id, i)));
+        }
+        if depth < 2 {
+            for i in 0..rng.below(3) {
+                command =
+                    command.subcommand(random_command(rng, format!("{}s{}", id, i), depth + 1));
+            }
+        }
+        command
+    }
+
+    fn push_flag(rng: &mut Rng, flag: &Flag, args: &mut Vec<String>) {
+        args.push(flag.id.clone());
+        match (flag.kind, flag.tuple.len(), flag.value_delimiter) {
+            (FlagKind::Value, 0, Some(delimiter)) => {
+                let values: Vec<String> = (0..1 + rng.below(3)).map(|_| rng.word()).collect();
+                args.push(values.join(&delimiter.to_string()));
+            }
+            (FlagKind::Value, 0, None) => args.push(rng.word()),
+            (FlagKind::Value, arity, _) => args.extend((0..arity).map(|_| rng.word())),
+            _ => {}
+        }
+    }
+
+    /// Random valid args for `commands` and the global flags
+    fn random_args(rng: &mut Rng, commands: &[Command], globals: &[Flag]) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut path: Vec<&Command> = Vec::new();
+        let mut candidates: Vec<&Command> = commands.iter().collect();
+        while !candidates.is_empty() && (path.is_empty() || rng.chance()) {
+            let command = candidates[rng.below(candidates.len())];
+            args.push(command.id.clone());
+            if command.positional {
+                args.push(rng.word());
+            }
+            args.extend(command.positionals.iter().map(|_| rng.word()));
+            path.push(command);
+
+            let flags: Vec<&Flag> = globals
+                .iter()
+                .chain(path.iter().flat_map(|c| c.flags.values()))
+                .collect();
+            for _ in 0..rng.below(4) {
+                if !flags.is_empty() {
+                    let flag = flags[rng.below(flags.len())];
+                    push_flag(rng, flag, &mut args);
+                }
+            }
+            candidates = command.subcommands.values().collect();
+        }
+        args
+    }
+
+    /// The id, positional, flags, and named positionals of each command in the path
+    /// of `command`. Only counted flags keep their count, the others are emitted once
+    fn parsed(command: &Command) -> Vec<String> {
+        let mut levels = Vec::new();
+        let mut next = Some(command);
+        while let Some(command) = next {
+            let mut flags: Vec<_> = command
+                .parsed_flags
+                .values()
+                .map(|f| {
+                    let count = match f.kind {
+                        FlagKind::Count => f.count(),
+                        _ => usize::from(f.is_present()),
+                    };
+                    (f.id.clone(), f.values.clone(), count, f.source)
+                })
+                .collect();
+            flags.sort_by(|a, b| a.0.cmp(&b.0));
+            let mut positionals: Vec<_> = command
+                .parsed_positionals
+                .values()
+                .map(|p| (p.id.clone(), p.value.clone()))
+                .collect();
+            positionals.sort();
+            levels.push(format!(
+                "{} {:?} {:?} {:?}",
+                command.id, command.positional_val, flags, positionals
+            ));
+            next = command.subcommand.as_deref();
+        }
+        levels
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let policies = [
+            crate::DuplicatePolicy::LastWins,
+            crate::DuplicatePolicy::FirstWins,
+            crate::DuplicatePolicy::Collect,
+        ];
+
+        for seed in 1..500u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let commands: Vec<Command> = (0..1 + rng.below(3))
+                .map(|i| random_command(&mut rng, format!("c{}", i), 0))
+                .collect();
+            let globals: Vec<Flag> = (0..rng.below(3))
+                .map(|i| random_flag(&mut rng, format!("--g{}", i)))
+                .collect();
+            let policy = policies[rng.below(policies.len())];
+            let parser = |args: Vec<String>| {
+                let mut parser = CliParser::from_args(args.into_iter()).on_duplicate(policy);
+                for command in commands.iter() {
+                    parser = parser.command(command.clone());
+                }
+                for flag in globals.iter() {
+                    parser = parser.global_flag(flag.clone());
+                }
+                parser
+            };
+
+            let args = random_args(&mut rng, &commands, &globals);
+            let first = parser(args.clone())
+                .parse()
+                .unwrap_or_else(|e| panic!("seed {}: {:?} failed to parse: {:?}", seed, args, e));
+            let second = parser(first.to_args()).parse().unwrap();
+            assert_eq!(parsed(&first), parsed(&second), "seed {}: {:?}", seed, args);
+        }
+    }
+
+    #[test]
+    fn test_windows_quote() {
+        assert_eq!(windows_quote("plain"), "plain");
+        assert_eq!(windows_quote(""), r#""""#);
+        assert_eq!(
+            windows_quote(r"C:\Program Files\"),
+            r#""C:\Program Files\\""#
+        );
+        assert_eq!(windows_quote(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(windows_quote(r"a\b c"), r#""a\b c""#);
+    }
+
+    #[test]
+    fn test_shell_split() {
+        assert_eq!(shell_split("  a   b\tc\n").unwrap(), ["a", "b", "c"]);
+        assert_eq!(shell_split(r#"'' "" x"#).unwrap(), ["", "", "x"]);
+        assert_eq!(shell_split(r#"'a "b" \c'"#).unwrap(), [r#"a "b" \c"#]);
+        assert_eq!(shell_split(r#""a \$HOME \n""#).unwrap(), [r"a $HOME \n"]);
+        assert_eq!(
+            shell_split(r#"pre"fix"'ed' \"q"#).unwrap(),
+            ["prefixed", "\"q"]
+        );
+        assert!(shell_split("").unwrap().is_empty());
+        assert!(matches!(
+            shell_split(r#"say "hi"#),
+            Err(ParseError::UnterminatedQuote(arg)) if arg == "hi"
+        ));
+    }
+
+    #[test]
+    fn test_query_args() {
+        assert_eq!(
+            query_args("arg=a%20b&x=1&arg=&arg=%E2%9C%93&arg=100%&arg=%zz"),
+            ["a b", "", "\u{2713}", "100%", "%zz"]
+        );
+        assert!(query_args("").is_empty());
+        assert!(query_args("?").is_empty());
+    }
+}
@@ -1,17 +1,89 @@
 use std::{
-    collections::HashMap,
-    env::{args, Args},
+    any::Any,
+    collections::{HashMap, HashSet, VecDeque},
+    env::args_os,
+    ffi::{OsStr, OsString},
     fmt::Debug,
+    io::Write,
     iter::Peekable,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
 };
 
-/// Represents a main command.
-/// Currently does not support subcommands.
+// Lets the generated [CliCommand] implementations name `::cli_parser` in tests
+#[cfg(test)]
+extern crate self as cli_parser;
+
+mod completion;
+mod derive;
+mod dispatch;
+mod environment;
+mod fallback;
+mod help;
+mod invocation;
+mod markdown;
+mod matches;
+mod messages;
+mod provenance;
+#[cfg(feature = "serde")]
+mod serialize;
+mod sources;
+pub mod spec;
+mod standard;
+mod streams;
+mod style;
+mod suggest;
+mod telemetry;
+mod tree;
+pub mod validate;
+mod value;
+mod verbosity;
+
+#[cfg(feature = "derive")]
+pub use cli_parser_derive::CliCommand;
+pub use completion::{generate_completions, Shell, ValueHint, COMPLETIONS_COMMAND};
+#[doc(hidden)]
+pub use derive::internal as __derive;
+pub use derive::CliCommand;
+use dispatch::Handler;
+#[cfg(feature = "async")]
+pub use dispatch::HandlerFuture;
+pub use dispatch::{HandlerError, RunError};
+pub use environment::Environment;
+pub use fallback::Fallback;
+use fallback::Resolution;
+pub use help::DEFAULT_HELP_TEMPLATE;
+pub use invocation::{
+    check_command_line, query_args, shell_split, shell_split_limited, windows_command_line,
+    windows_quote, CommandLineTooLong, Platform,
+};
+pub use matches::{MatchedFlag, MatchedPositional, Matches, MatchesStore};
+pub use messages::Messages;
+pub use provenance::{ConfigEntry, ConfigFormat, ValueSource};
+use sources::{Layers, SourceCache};
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub use spec::SpecError;
+pub use spec::SPEC_VERSION;
+pub use standard::{StandardFlags, HELP, HELP_COMMAND, SHORT_HELP, VERSION};
+pub use streams::{Input, Stream, Streams};
+use style::{MessageKind, Style};
+use suggest::Completer;
+pub use suggest::{Suggestion, SuggestionKind, COMPLETE_COMMAND};
+pub use telemetry::ParseOutcome;
+use telemetry::{OutcomeHook, ParseHooks};
+pub use validate::Validator;
+use value::AnyValue;
+pub use value::{TupleValue, TypedFlag, ValueError, ValueParser};
+pub use verbosity::{Verbosity, QUIET, VERBOSE};
+
+/// Represents a main command, or a subcommand of another [Command].
 ///
 /// Uses builder pattern for construction
 ///
 /// ## Example
 /// ```
+/// # use cli_parser::*;
 /// let cmd = Command::new("help")
 ///     .positional()
 ///     .flag(Flag::new("--always"));
@@ -24,10 +96,49 @@ pub struct Command {
     positional: bool,
     // Actual positional value after parsing
     pub positional_val: Option<String>,
+    // Raw positional value, see [CliParser::from_args_os]
+    positional_os: Option<OsString>,
     // Does command take any flags?
     flags: HashMap<String, Flag>,
-    // actually parsed flags
+    /// Actually parsed flags, the outermost command also has the parsed global flags
     pub parsed_flags: HashMap<String, Flag>,
+    // Named positionals, parsed in order after the command id
+    positionals: Vec<Positional>,
+    // Actually parsed named positionals
+    pub parsed_positionals: HashMap<String, Positional>,
+    // Subcommands that can follow this command
+    subcommands: HashMap<String, Command>,
+    // Actually parsed subcommand
+    pub subcommand: Option<Box<Command>>,
+    /// All args following an unknown command, see [CliParser::allow_external_subcommands]
+    pub external_args: Option<Vec<String>>,
+    /// The [Flag::exclusive] flag that ended parsing early, set on the outermost command
+    pub exclusive_flag: Option<String>,
+    /// All args following the command, untouched, see [Command::trailing_args]
+    pub trailing: Vec<OsString>,
+    // Take all args following the positionals and flags as `trailing`
+    takes_trailing: bool,
+    // Capabilities the caller must have to use the command
+    capabilities: Vec<String>,
+    // Parsed, but left out of generated output like suggestions
+    hidden: bool,
+    // One line description, shown in command lists
+    about: Option<String>,
+    // Full description, shown in the help of the command
+    long_about: Option<String>,
+    // Free-form text shown above and below the generated lists in help
+    before_help: Option<String>,
+    after_help: Option<String>,
+    // Position in generated lists, before commands without one
+    display_order: Option<usize>,
+    // Flags left out of completions while this is the innermost command
+    complete_exclude: Vec<String>,
+    // Extra static words completed while this is the innermost command
+    complete_extra: Vec<String>,
+    // Maximum run time of the command once dispatched
+    timeout: Option<Duration>,
+    // Called with the parse result by [CliParser::run]
+    handler: Option<Handler>,
 }
 
 impl Command {
@@ -35,15 +146,36 @@ impl Command {
     ///
     /// ## Example
     /// ```
+    /// # use cli_parser::*;
     /// Command::new("version");
-    /// ````
+    /// ```
     pub fn new(id: &str) -> Self {
         Self {
             id: id.into(),
             positional: false,
             positional_val: None,
+            positional_os: None,
             flags: HashMap::new(),
             parsed_flags: HashMap::new(),
+            positionals: Vec::new(),
+            parsed_positionals: HashMap::new(),
+            subcommands: HashMap::new(),
+            subcommand: None,
+            external_args: None,
+            exclusive_flag: None,
+            trailing: Vec::new(),
+            takes_trailing: false,
+            capabilities: Vec::new(),
+            hidden: false,
+            about: None,
+            long_about: None,
+            before_help: None,
+            after_help: None,
+            display_order: None,
+            complete_exclude: Vec::new(),
+            complete_extra: Vec::new(),
+            timeout: None,
+            handler: None,
         }
     }
 
@@ -56,10 +188,340 @@ impl Command {
     /// Does the command have any flags associated?
     /// See [Flag]
     pub fn flag(mut self, flag: Flag) -> Self {
+        self.add_flag(flag);
+        self
+    }
+
+    /// Add a [Flag], like [Command::flag] but through a reference
+    pub fn add_flag(&mut self, flag: Flag) -> &mut Self {
         self.flags.insert(flag.id.clone(), flag);
         self
     }
 
+    /// Add a named [Positional], parsed in the order they are added
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// # use std::str::FromStr;
+    /// let cmd = Command::new("take")
+    ///     .positional_arg(Positional::new("COUNT").value_parser(usize::from_str));
+    /// ```
+    pub fn positional_arg(mut self, positional: Positional) -> Self {
+        self.positionals.push(positional);
+        self
+    }
+
+    /// Add a subcommand, parsed when its id follows this command
+    pub fn subcommand(mut self, command: Command) -> Self {
+        self.add_subcommand(command);
+        self
+    }
+
+    /// Add a subcommand, like [Command::subcommand] but through a reference
+    pub fn add_subcommand(&mut self, command: Command) -> &mut Self {
+        self.subcommands.insert(command.id.clone(), command);
+        self
+    }
+
+    /// A previously added subcommand, to change its definition
+    pub fn get_subcommand_mut(&mut self, id: &str) -> Option<&mut Command> {
+        self.subcommands.get_mut(id)
+    }
+
+    /// Parse the command as usual, but leave it out of generated help,
+    /// usage, and completion output, e.g. for internal or debug commands
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// One line description, shown next to the command in help and completions
+    pub fn about(mut self, about: &str) -> Self {
+        self.about = Some(about.into());
+        self
+    }
+
+    /// Full description, shown in the help of the command itself instead of [Command::about]
+    pub fn long_about(mut self, long_about: &str) -> Self {
+        self.long_about = Some(long_about.into());
+        self
+    }
+
+    /// Free-form text shown in the help of the command above the generated
+    /// lists of arguments, flags, and subcommands
+    pub fn before_help(mut self, text: &str) -> Self {
+        self.before_help = Some(text.into());
+        self
+    }
+
+    /// Free-form text shown at the end of the help of the command,
+    /// e.g. examples, links, or license notices
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("app")
+    ///     .command(Command::new("deploy").after_help("example: app deploy --env prod"));
+    /// assert!(parser
+    ///     .command_help(&["deploy"])
+    ///     .unwrap()
+    ///     .ends_with("\n\nexample: app deploy --env prod\n"));
+    /// ```
+    pub fn after_help(mut self, text: &str) -> Self {
+        self.after_help = Some(text.into());
+        self
+    }
+
+    /// List the command at position `order` in generated help and trees.
+    /// Commands without a display order follow, sorted by id
+    pub fn display_order(mut self, order: usize) -> Self {
+        self.display_order = Some(order);
+        self
+    }
+
+    /// The one line description, see [Command::about]
+    pub fn summary(&self) -> Option<&str> {
+        self.about.as_deref()
+    }
+
+    /// The full description, see [Command::long_about], falling back to [Command::about]
+    pub fn description(&self) -> Option<&str> {
+        self.long_about.as_deref().or(self.about.as_deref())
+    }
+
+    /// Require the caller to have `capability` to use the command,
+    /// see [CliParser::capability]
+    pub fn requires_capability(mut self, capability: &str) -> Self {
+        self.capabilities.push(capability.into());
+        self
+    }
+
+    /// Leave the flag `id`, of this command or a global, out of completions
+    /// while this is the innermost command. The flag is still parsed as usual
+    pub fn complete_exclude(mut self, id: &str) -> Self {
+        self.complete_exclude.push(id.into());
+        self
+    }
+
+    /// Complete the static `word` while this is the innermost command,
+    /// e.g. for well known positional values
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty()).command(
+    ///     Command::new("checkout")
+    ///         .complete_extra("main")
+    ///         .flag(Flag::new("--internal"))
+    ///         .complete_exclude("--internal"),
+    /// );
+    /// let suggestions = parser.suggest_next(&["checkout", ""]);
+    /// assert_eq!(suggestions.len(), 1);
+    /// assert_eq!(suggestions[0].value, "main");
+    /// ```
+    pub fn complete_extra(mut self, word: &str) -> Self {
+        self.complete_extra.push(word.into());
+        self
+    }
+
+    /// Capture all args following the positionals and flags of the command into
+    /// [Command::trailing], untouched and not converted to unicode, e.g. for exec
+    /// style wrappers passing them on to a child process. A leading `--` ends the
+    /// flags of the command, and is not captured. Subcommands are not parsed
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "exec --env prod -- ls -la".split(" ").map(std::ffi::OsString::from);
+    /// let cmd = CliParser::from_args_os(args)
+    ///     .command(Command::new("exec").flag(Flag::new("--env").positional()).trailing_args())
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.trailing, ["ls", "-la"]);
+    /// ```
+    pub fn trailing_args(mut self) -> Self {
+        self.takes_trailing = true;
+        self
+    }
+
+    /// Limit how long the command may run once dispatched to its [Command::async_handler],
+    /// failing with [RunError::TimedOut]. The handler future is dropped once the limit
+    /// passes, cancelling it. The limit applies to this command only, not its subcommands.
+    ///
+    /// Blocking [Command::handler]s can not be cancelled, dispatching one on a command
+    /// with a timeout fails with [RunError::TimeoutUnsupported] instead of running it
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The run time limit of the command, see [Command::timeout]
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// If the flag `id` was passed, see [Flag::is_present]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["build", "--force", "--include", "a,b", "--v", "--v"])
+    ///     .command(
+    ///         Command::new("build")
+    ///             .flag(Flag::new("--force"))
+    ///             .flag(Flag::new("--include").positional().value_delimiter(','))
+    ///             .flag(Flag::new("--out").positional().default_value("target"))
+    ///             .flag(Flag::new("--v").counted()),
+    ///     )
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert!(cmd.is_present("--force"));
+    /// assert!(!cmd.is_present("--out"));
+    /// assert_eq!(cmd.value_of("--out"), Some("target"));
+    /// assert_eq!(cmd.values_of("--include"), ["a", "b"]);
+    /// assert_eq!(cmd.occurrences_of("--v"), 2);
+    /// ```
+    pub fn is_present(&self, id: &str) -> bool {
+        self.parsed_flag(id).is_some_and(Flag::is_present)
+    }
+
+    /// The value of the flag `id`, including a default, see [Flag::value]
+    pub fn value_of(&self, id: &str) -> Option<&str> {
+        self.parsed_flag(id).and_then(Flag::value)
+    }
+
+    /// All values of the flag `id`, empty if it was not parsed
+    pub fn values_of(&self, id: &str) -> &[String] {
+        self.parsed_flag(id).map_or(&[], |f| &f.values)
+    }
+
+    /// Number of times the flag `id` was passed, see [Flag::count]
+    pub fn occurrences_of(&self, id: &str) -> usize {
+        self.parsed_flag(id).map_or(0, Flag::count)
+    }
+
+    /// A parsed flag by id, the leading `--` may be left out
+    fn parsed_flag(&self, id: &str) -> Option<&Flag> {
+        self.parsed_flags.get(&flag_id(id))
+    }
+
+    /// Every time a flag of this command was passed, with its index in the input args,
+    /// in the order passed, e.g. for CLIs where `-i a -o b -i c` pairs inputs and outputs.
+    /// Flags filled in from defaults are left out
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["convert", "--in", "a", "--out", "b", "--in", "c"])
+    ///     .command(
+    ///         Command::new("convert")
+    ///             .flag(Flag::new("--in").positional())
+    ///             .flag(Flag::new("--out").positional()),
+    ///     )
+    ///     .on_duplicate(DuplicatePolicy::Collect)
+    ///     .parse()
+    ///     .unwrap();
+    /// let order: Vec<(usize, &str)> = cmd
+    ///     .ordered_flags()
+    ///     .into_iter()
+    ///     .map(|(index, flag)| (index, flag.id.as_str()))
+    ///     .collect();
+    /// assert_eq!(order, [(1, "--in"), (3, "--out"), (5, "--in")]);
+    /// ```
+    pub fn ordered_flags(&self) -> Vec<(usize, &Flag)> {
+        ordered_flags(&self.parsed_flags, Flag::indices)
+    }
+
+    /// Ids of the parsed command and its parsed subcommands, outermost first
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["remote", "add"])
+    ///     .command(Command::new("remote").subcommand(Command::new("add")))
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.path(), ["remote", "add"]);
+    /// ```
+    pub fn path(&self) -> Vec<&str> {
+        let mut path = vec![self.id.as_str()];
+        let mut command = self;
+        while let Some(sub) = command.subcommand.as_deref() {
+            path.push(&sub.id);
+            command = sub;
+        }
+        path
+    }
+
+    /// The name and args of an external command, like `git foo` dispatching to
+    /// `git-foo`, see [CliParser::allow_external_subcommands]
+    pub fn external(&self) -> Option<(&str, &[String])> {
+        self.external_args
+            .as_deref()
+            .map(|args| (self.id.as_str(), args))
+    }
+
+    /// Resolve the parsed flags of this command and its parsed subcommands,
+    /// see [Flag::resolve]. `layers` prefixes config keys with the path to the command
+    fn resolve(&mut self, layers: &mut Layers, sandboxed: bool) -> Result<(), ParseError> {
+        let mut layers = layers.command(&self.id);
+        Flag::resolve(&self.flags, &mut self.parsed_flags, &mut layers, sandboxed)?;
+        if let Some(sub) = self.subcommand.as_mut() {
+            sub.resolve(&mut layers, sandboxed)?;
+        }
+        Ok(())
+    }
+
+    /// If this command or any of its subcommands defines the flag `id`
+    fn defines_flag(&self, id: &str) -> bool {
+        self.flags.contains_key(id) || self.subcommands.values().any(|c| c.defines_flag(id))
+    }
+
+    /// Validate the parsed flags of this command and its parsed subcommands
+    fn validate(&self) -> Result<(), ParseError> {
+        Flag::validate(&self.flags, &self.parsed_flags)?;
+        match &self.subcommand {
+            Some(sub) => sub.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the typed value of a parsed flag, see [Flag::get].
+    /// The leading `--` of `id` may be left out
+    pub fn flag_as<T: Any>(&self, id: &str) -> Option<&T> {
+        self.parsed_flag(id).and_then(|f| f.get::<T>())
+    }
+
+    /// Get all typed values of a parsed flag, see [Flag::get_all].
+    /// The leading `--` of `id` may be left out
+    pub fn flag_values_as<T: Any>(&self, id: &str) -> Vec<&T> {
+        self.parsed_flag(id)
+            .map(|f| f.get_all::<T>())
+            .unwrap_or_default()
+    }
+
+    /// If the positional value is the `-` sentinel, conventionally meaning stdin
+    pub fn is_stdin(&self) -> bool {
+        self.positional_val.as_deref() == Some(STDIN)
+    }
+
+    /// The raw positional value, not lossily converted when parsed
+    /// with [CliParser::from_args_os]
+    pub fn positional_os(&self) -> Option<&OsStr> {
+        self.positional_os.as_deref()
+    }
+
+    /// Get the typed value of a parsed named positional.
+    ///
+    /// Returns `None` if the positional was not parsed,
+    /// or if it was not converted to a `T` by its [ValueParser]
+    pub fn positional_as<T: Any>(&self, id: &str) -> Option<&T> {
+        self.parsed_positionals.get(id).and_then(|p| p.get::<T>())
+    }
+
     // /// Add a parsed flag to `parsed_flags``
     // fn parsed_flag(&mut self, flag: Flag) {
     //     self.parsed_flags.insert(flag.id.clone(), flag);
@@ -72,41 +534,137 @@ impl Command {
 ///
 /// ## Example
 /// ```
+/// # use cli_parser::*;
 /// let flag = Flag::new("--ip").positional();
 /// // Results in same Flag
 /// let flag = Flag::new("ip").positional();
-/// ````
+/// ```
 #[derive(Debug, Clone)]
 pub struct Flag {
     /// Id / name of flag
     pub id: String,
-    // If flag has an associated positional value or not
-    positional: bool,
+    // If flag is a presence flag, takes a positional value, or is counted
+    kind: FlagKind,
     // Actual parsed positional value
     pub positional_val: Option<String>,
+    /// All parsed positional values, more than one with [DuplicatePolicy::Collect]
+    pub values: Vec<String>,
+    // Raw parsed positional values, see [CliParser::from_args_os]
+    os_values: Vec<OsString>,
+    // Index in the input args of each parsed value, `None` for defaults
+    positions: Vec<Option<usize>>,
+    // Index in the input args of each time the flag was passed
+    indices: Vec<usize>,
+    // Converts the raw values into typed values
+    value_parser: Option<ValueParser>,
+    // Actual parsed typed values
+    typed_values: Vec<AnyValue>,
+    /// Number of times the flag was seen while parsing
+    pub occurrences: usize,
+    /// Where the parsed value came from
+    pub source: ValueSource,
     required: bool,
+    // Value used when the flag is not passed
+    default_val: Option<String>,
+    // Environment variable read when the flag is not passed
+    env: Option<String>,
+    // Config key read when the flag is not passed, nor set in the environment
+    config_key: Option<String>,
+    // If the positional value may start with `-`
+    allow_hyphen_values: bool,
+    // Capabilities the caller must have to use the flag
+    capabilities: Vec<String>,
+    // Parsed, but left out of generated output like suggestions
+    hidden: bool,
+    // Description, shown in help and completions
+    help: Option<String>,
+    // Detailed description, shown in `--help` instead of the description
+    long_help: Option<String>,
+    // Position in generated lists, before flags without one
+    display_order: Option<usize>,
+    // Title of the help section listing the flag, instead of `flags`
+    help_heading: Option<String>,
+    // Computes the values offered by completions at runtime
+    completer: Option<Completer>,
+    // Kind of value completed natively by completion scripts
+    value_hint: Option<ValueHint>,
+    // Collect the values of repeated occurrences, regardless of the [DuplicatePolicy]
+    multiple: bool,
+    // Split each value on the delimiter
+    value_delimiter: Option<char>,
+    // Rules every value must satisfy
+    validators: Vec<Validator>,
+    // Notice recorded when the flag is used, like `use --output instead`
+    deprecated: Option<String>,
+    // Bounds on the number of values when passed
+    min_values: Option<usize>,
+    max_values: Option<usize>,
+    // Stops parsing and skips required checks when passed, like `--help`
+    exclusive: bool,
+    // Names and parsers of the fixed values taken by each occurrence
+    tuple: Vec<(String, ValueParser)>,
 }
 
 impl Flag {
     /// Createa a new `Flag` builder
     pub fn new(id: &str) -> Self {
-        let new_id = if !id.starts_with("--") {
-            format!("--{}", id)
-        } else {
-            id.to_string()
-        };
-
         Self {
-            id: new_id.into(),
-            positional: false,
+            id: flag_id(id),
+            kind: FlagKind::default(),
             positional_val: None,
+            values: Vec::new(),
+            os_values: Vec::new(),
+            positions: Vec::new(),
+            indices: Vec::new(),
+            value_parser: None,
+            typed_values: Vec::new(),
+            occurrences: 0,
+            source: ValueSource::default(),
             required: false,
+            default_val: None,
+            env: None,
+            config_key: None,
+            allow_hyphen_values: false,
+            capabilities: Vec::new(),
+            hidden: false,
+            help: None,
+            long_help: None,
+            display_order: None,
+            help_heading: None,
+            completer: None,
+            value_hint: None,
+            multiple: false,
+            value_delimiter: None,
+            validators: Vec::new(),
+            deprecated: None,
+            min_values: None,
+            max_values: None,
+            exclusive: false,
+            tuple: Vec::new(),
         }
     }
 
-    /// If flag has associated positional value
-    pub fn positional(mut self) -> Self {
-        self.positional = true;
+    /// If flag has associated positional value, same as `takes_value(true)`
+    pub fn positional(self) -> Self {
+        self.takes_value(true)
+    }
+
+    /// If flag takes a positional value, [FlagKind::Value],
+    /// or is a boolean presence flag, [FlagKind::Bool]
+    pub fn takes_value(mut self, takes_value: bool) -> Self {
+        self.kind = if takes_value {
+            FlagKind::Value
+        } else {
+            FlagKind::Bool
+        };
+        self
+    }
+
+    /// Count the occurrences of the flag, like `-v -v`, see [FlagKind::Count].
+    ///
+    /// Counted flags may always be repeated, regardless of the [DuplicatePolicy]
+    pub fn counted(mut self) -> Self {
+        self.kind = FlagKind::Count;
         self
     }
 
@@ -114,344 +672,3861 @@ impl Flag {
         self.required = true;
         self
     }
-}
 
-/// Represents an error that occured during parsing of the Cli input, a [Command] or a [Flag].
-#[derive(Debug)]
-pub enum ParseError {
-    None,
-    MissingPositional,
-    NoCommands,
-    InvalidCommand(String),
-    InvalidFlag(String),
-    ExpectedCommand,
-    ExpectedPositional,
-    ExpectedFlag,
-    RequiredPositional,
-    MissingRequiredFlag(String),
-}
+    /// Accept positional values starting with `-`, like `--pattern -v`.
+    ///
+    /// Negative numbers, like `--offset -5`, are always accepted
+    pub fn allow_hyphen_values(mut self) -> Self {
+        self.allow_hyphen_values = true;
+        self
+    }
 
-/// Parses the CLI inputs based on provided `Commands`
-///
-/// Exposes a builder interface with `::new();`
-///
-/// ## Usage
-/// ```
-/// let app = CliParser::new()
-///     .command(Command::new("help")
-///     .command(Command::new("version")
-///     .parse()
-///     .unwrap();
-/// ```
-#[derive(Debug)]
-pub struct CliParser<It>
-where
-    It: Iterator<Item = String>,
-    It::Item: Debug,
-{
-    // Provided commands used for parsing
-    pub(crate) commands: HashMap<String, Command>,
-    // Input program arguments to parse into final [Command] struct
-    args: Peekable<It>,
-    // Global flags
-    pub(crate) global_flags: HashMap<String, Flag>,
-    // actually parsed flags
-    pub parsed_flags: HashMap<String, Flag>,
-}
+    /// Parse the flag as usual, but leave it out of generated help,
+    /// usage, and completion output, e.g. for internal or debug options
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
 
-impl<It> CliParser<It>
-where
-    It: Iterator<Item = String>,
-    It::Item: Debug,
-{
-    /// Create a new [CliParser] builder
+    /// Description, shown next to the flag in help and completions
+    pub fn help(mut self, help: &str) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Detailed description, like examples, shown in the full `--help` output
+    /// instead of the [Flag::help] shown in the `-h` summary
+    pub fn long_help(mut self, help: &str) -> Self {
+        self.long_help = Some(help.into());
+        self
+    }
+
+    /// List the flag at position `order` in generated help and trees, e.g. to put the
+    /// most used flags first. Flags without a display order follow, sorted by id
     ///
     /// ## Example
     /// ```
-    /// let app = CliParser::new()
-    ///     .command(Command::new("help")
-    ///     .command(Command::new("version")
-    ///     .parse()
-    ///     .unwrap();
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("app")
+    ///     .command(
+    ///         Command::new("run")
+    ///             .flag(Flag::new("--all"))
+    ///             .flag(Flag::new("--release"))
+    ///             .flag(Flag::new("--target").display_order(0)),
+    ///     );
+    /// assert!(parser
+    ///     .command_help(&["run"])
+    ///     .unwrap()
+    ///     .ends_with("flags:\n  --target\n  --all\n  --release\n"));
     /// ```
-    pub fn new() -> CliParser<Peekable<Args>> {
-        let mut args = args().peekable();
-        let _ = args.next().unwrap();
-
-        CliParser::from_args(args)
+    pub fn display_order(mut self, order: usize) -> Self {
+        self.display_order = Some(order);
+        self
+    }
 
-        // Self {
-        //     commands: HashMap::new(),
-        //     args: args,
-        //     flags: HashMap::new(),
-        //     parsed_flags: HashSet::new(),
-        // }
+    /// List the flag in its own help section titled `heading`, together with the other
+    /// flags of the heading, like `Networking`. The sections follow the flags without a heading
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("app")
+    ///     .command(
+    ///         Command::new("fetch")
+    ///             .flag(Flag::new("--all"))
+    ///             .flag(Flag::new("--proxy").positional().help_heading("Networking")),
+    ///     );
+    /// assert!(parser
+    ///     .command_help(&["fetch"])
+    ///     .unwrap()
+    ///     .ends_with("flags:\n  --all\n\nNetworking:\n  --proxy <VALUE>\n"));
+    /// ```
+    pub fn help_heading(mut self, heading: &str) -> Self {
+        self.help_heading = Some(heading.into());
+        self
     }
 
-    pub fn from_args(it: It) -> Self
+    /// Compute the values offered when completing the value of the flag at runtime,
+    /// like branch names or hostnames, from the prefix being typed.
+    /// Offered by [CliParser::suggest_next], and by generated completion scripts
+    /// through [CliParser::dynamic_completion]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let branches = ["main", "feature/help", "fix/typo"];
+    /// let parser = CliParser::from_args(std::iter::empty()).command(
+    ///     Command::new("checkout").flag(Flag::new("--branch").positional().complete_with(
+    ///         move |prefix| {
+    ///             let matching = branches.iter().filter(|b| b.starts_with(prefix));
+    ///             matching.map(|b| b.to_string()).collect()
+    ///         },
+    ///     )),
+    /// );
+    ///
+    /// let suggestions = parser.suggest_next(&["checkout", "--branch", "f"]);
+    /// assert_eq!(suggestions[0].value, "feature/help");
+    /// ```
+    pub fn complete_with<F>(mut self, completer: F) -> Self
     where
-        It: Iterator<Item = String>,
-        It::Item: Debug,
+        F: Fn(&str) -> Vec<String> + Send + Sync + 'static,
     {
-        Self {
-            commands: HashMap::new(),
-            args: it.peekable(),
-            global_flags: HashMap::new(),
-            parsed_flags: HashMap::new(),
-        }
+        self.completer = Some(Completer(std::sync::Arc::new(completer)));
+        self
     }
 
-    /// Add a [Command] to be parsed
-    pub fn command(mut self, command: Command) -> Self {
-        self.commands.insert(command.id.clone(), command);
+    /// Complete the value of the flag with the native completion of the shell for `hint`,
+    /// like file names or host names, in scripts from [generate_completions]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("app")
+    ///     .global_flag(Flag::new("--out-dir").positional().value_hint(ValueHint::DirPath));
+    ///
+    /// let script = generate_completions(Shell::Zsh, &parser);
+    /// assert!(script.contains("'--out-dir') _files -/"));
+    /// ```
+    pub fn value_hint(mut self, hint: ValueHint) -> Self {
+        self.value_hint = Some(hint);
         self
     }
 
-    /// Add a global [Flag] to be parsed
-    pub fn global_flag(mut self, flag: Flag) -> Self {
-        self.global_flags.insert(flag.id.clone(), flag);
+    /// The description, see [Flag::help]
+    pub fn description(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    /// The detailed description, falling back to the description, see [Flag::long_help]
+    pub fn long_description(&self) -> Option<&str> {
+        self.long_help.as_deref().or(self.help.as_deref())
+    }
+
+    /// Require the caller to have `capability` to use the flag,
+    /// see [CliParser::capability]
+    pub fn requires_capability(mut self, capability: &str) -> Self {
+        self.capabilities.push(capability.into());
         self
     }
 
-    /// Parse the provided program args into the constructed Command tree
+    /// Keep parsing the flag, but record a [ParseWarning::Deprecated] with `note`
+    /// whenever it is passed, see [CliParser::warn_deprecated]
     ///
-    /// ## Errors
-    /// If any parsing fails return a [ParseError] error
-    pub fn parse(&mut self) -> Result<Command, ParseError> {
-        self.parse_next(&mut None)
-    }
-
-    fn parse_next(&mut self, command: &mut Option<Command>) -> Result<Command, ParseError> {
-        self.parse_flags(command)?;
-        // Validate so far
-        if let Some(command) = command {
-            // Validate required flags
-            for (id, flag) in command.flags.iter() {
-                if flag.required {
-                    if !command.parsed_flags.contains_key(id) {
-                        Err(ParseError::MissingRequiredFlag(id.into()))?;
-                    }
-                }
-            }
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "build --out dist".split(" ").map(|s| s.to_string());
+    /// let mut parser = CliParser::from_args(args).command(
+    ///     Command::new("build")
+    ///         .flag(Flag::new("--out").positional().deprecated("use --output instead"))
+    ///         .flag(Flag::new("--output").positional()),
+    /// );
+    /// let cmd = parser.parse().unwrap();
+    /// assert_eq!(cmd.parsed_flags["--out"].deprecation(), Some("use --output instead"));
+    /// assert_eq!(
+    ///     parser.warnings,
+    ///     [ParseWarning::Deprecated("--out".into(), "use --output instead".into())]
+    /// );
+    /// ```
+    pub fn deprecated(mut self, note: &str) -> Self {
+        self.deprecated = Some(note.into());
+        self
+    }
+
+    /// Stop parsing when the flag is passed, like `--help` or `--version`,
+    /// skipping the remaining args and all checks for required flags and positionals.
+    ///
+    /// The flag is recorded in [Command::exclusive_flag]. If no command was
+    /// matched before it, an empty command with the id `""` is returned
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "copy --help".split(" ").map(|s| s.to_string());
+    /// let cmd = CliParser::from_args(args)
+    ///     .command(Command::new("copy").positional().flag(Flag::new("--out").required()))
+    ///     .global_flag(Flag::new("--help").exclusive())
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.exclusive_flag.as_deref(), Some("--help"));
+    /// ```
+    pub fn exclusive(mut self) -> Self {
+        self.exclusive = true;
+        self
+    }
+
+    /// Value to use when the flag is not passed, implies [Flag::positional]
+    pub fn default_value(mut self, value: &str) -> Self {
+        self.kind = FlagKind::Value;
+        self.default_val = Some(value.into());
+        self
+    }
+
+    /// Read the value from the environment variable `name` when the flag is not passed,
+    /// see [ValueSource] for the order of precedence.
+    ///
+    /// Values are split by the [Flag::value_delimiter] and validated like passed values.
+    /// Flags without a value are set by `true` or a count, and left unset by `false`
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// std::env::set_var("DEPLOY_REGION", "eu-north-1");
+    /// let cmd = CliParser::parse_from(["deploy"])
+    ///     .command(Command::new("deploy").flag(Flag::new("--region").positional().env("DEPLOY_REGION")))
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// let region = &cmd.parsed_flags["--region"];
+    /// assert_eq!(region.value(), Some("eu-north-1"));
+    /// assert_eq!(region.source, ValueSource::Env);
+    /// ```
+    pub fn env(mut self, name: &str) -> Self {
+        self.env = Some(name.into());
+        self
+    }
+
+    /// Read the value from the config key `key` when the flag is not passed, nor set by its
+    /// [Flag::env], see [CliParser::config_value]. Keys of the flags of a command are
+    /// prefixed with the path of the command, like `remote.add.tags` for the key `tags`
+    /// of a flag of `remote add`, keys of global flags are not prefixed.
+    ///
+    /// Values are read like [Flag::env] values
+    pub fn config_key(mut self, key: &str) -> Self {
+        self.config_key = Some(key.into());
+        self
+    }
+
+    /// Convert the values when parsing, e.g. with `u16::from_str`, implies [Flag::positional].
+    ///
+    /// All failed conversions of a flag are reported together
+    /// as [ParseError::InvalidValues]
+    pub fn value_parser<T, E, F>(mut self, f: F) -> Self
+    where
+        T: Any + Send + Sync,
+        E: std::fmt::Display,
+        F: Fn(&str) -> Result<T, E> + Send + Sync + 'static,
+    {
+        self.kind = FlagKind::Value;
+        self.value_parser = Some(ValueParser::new(f));
+        self
+    }
+
+    /// Accept any number of values, collecting repeated occurrences regardless of
+    /// the [DuplicatePolicy], each converted to a `T`, see [Flag::get_all].
+    ///
+    /// Combine with [Flag::value_delimiter] to also accept delimited lists
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// # use std::net::SocketAddr;
+    /// let args = "serve --bind 127.0.0.1:80,[::1]:80 --bind 0.0.0.0:443"
+    ///     .split(" ")
+    ///     .map(|s| s.to_string());
+    /// let cmd = CliParser::from_args(args)
+    ///     .command(Command::new("serve").flag(
+    ///         Flag::new("--bind").values_of_type::<SocketAddr>().value_delimiter(','),
+    ///     ))
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.flag_values_as::<SocketAddr>("--bind").len(), 3);
+    /// ```
+    pub fn values_of_type<T>(mut self) -> Self
+    where
+        T: FromStr + Any + Send + Sync,
+        T::Err: std::fmt::Display,
+    {
+        self.multiple = true;
+        self.value_parser(T::from_str)
+    }
+
+    /// Require at least `min` values when the flag is passed, implies collecting
+    /// repeated occurrences like [Flag::values_of_type].
+    /// Fewer values are reported as [ParseError::TooFewValues]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "diff --file a".split(" ").map(|s| s.to_string());
+    /// let result = CliParser::from_args(args)
+    ///     .command(Command::new("diff").flag(Flag::new("--file").min_values(2).max_values(3)))
+    ///     .parse();
+    /// assert!(matches!(result, Err(ParseError::TooFewValues(_, 1, 2))));
+    /// ```
+    pub fn min_values(mut self, min: usize) -> Self {
+        self.kind = FlagKind::Value;
+        self.multiple = true;
+        self.min_values = Some(min);
+        self
+    }
+
+    /// Allow at most `max` values, implies collecting repeated occurrences
+    /// like [Flag::values_of_type]. More values are reported as [ParseError::TooManyValues]
+    pub fn max_values(mut self, max: usize) -> Self {
+        self.kind = FlagKind::Value;
+        self.multiple = true;
+        self.max_values = Some(max);
+        self
+    }
+
+    /// Take a fixed number of values per occurrence, each with its own name and
+    /// [ValueParser], like `--map FROM TO`, see [Flag::get_tuple]. Implies [Flag::positional].
+    ///
+    /// Too few values are reported as [ParseError::TooFewValues]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// # use std::{path::PathBuf, str::FromStr};
+    /// let args = "mv --map a.txt b.txt --retries 3".split(" ").map(|s| s.to_string());
+    /// let cmd = CliParser::from_args(args)
+    ///     .command(
+    ///         Command::new("mv")
+    ///             .flag(Flag::new("--map").tuple([
+    ///                 ("FROM", ValueParser::new(PathBuf::from_str)),
+    ///                 ("TO", ValueParser::new(PathBuf::from_str)),
+    ///             ]))
+    ///             .flag(Flag::new("--retries").value_parser(u8::from_str)),
+    ///     )
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// let (from, to) = cmd.parsed_flags["--map"].get_tuple::<(PathBuf, PathBuf)>().unwrap();
+    /// assert_eq!((from.to_str(), to.to_str()), (Some("a.txt"), Some("b.txt")));
+    /// ```
+    pub fn tuple<'a>(mut self, parts: impl IntoIterator<Item = (&'a str, ValueParser)>) -> Self {
+        self.kind = FlagKind::Value;
+        self.tuple = parts
+            .into_iter()
+            .map(|(name, parser)| (name.to_string(), parser))
+            .collect();
+        self
+    }
+
+    /// Split each value on `delimiter`, like `--include a,b,c`, implies [Flag::positional]
+    pub fn value_delimiter(mut self, delimiter: char) -> Self {
+        self.kind = FlagKind::Value;
+        self.value_delimiter = Some(delimiter);
+        self
+    }
+
+    /// Check every value against `validator`, see [validate].
+    /// Failures are reported as [ParseError::Validation]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// use cli_parser::validate::one_of;
+    ///
+    /// let args = "log --level loud".split(" ").map(|s| s.to_string());
+    /// let result = CliParser::from_args(args)
+    ///     .command(Command::new("log").flag(
+    ///         Flag::new("--level").positional().validate_with(one_of(["info", "debug"])),
+    ///     ))
+    ///     .parse();
+    /// assert!(matches!(result, Err(ParseError::Validation(..))));
+    /// ```
+    pub fn validate_with(mut self, validator: Validator) -> Self {
+        self.validators.push(validator);
+        self
+    }
+
+    /// Check every value with `f`, returning the error message for invalid values.
+    /// Failures are reported as [ParseError::Validation]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "checkout --rev nope".split(" ").map(|s| s.to_string());
+    /// let result = CliParser::from_args(args)
+    ///     .command(Command::new("checkout").flag(Flag::new("--rev").positional().validator(|v| {
+    ///         match v == "main" {
+    ///             true => Ok(()),
+    ///             false => Err(format!("'{}' is not an existing git ref", v)),
+    ///         }
+    ///     })))
+    ///     .parse();
+    /// assert!(matches!(result, Err(ParseError::Validation(_, m)) if m.contains("git ref")));
+    /// ```
+    pub fn validator<F>(self, f: F) -> Self
+    where
+        F: Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validate_with(Validator::from_fn(f))
+    }
+
+    /// Check that every value is an existing file, see [validate::existing_file]
+    pub fn existing_file(self) -> Self {
+        self.validate_with(validate::existing_file())
+    }
+
+    /// Check that every value is an existing directory, see [validate::existing_dir]
+    pub fn existing_dir(self) -> Self {
+        self.validate_with(validate::existing_dir())
+    }
+
+    /// Check that a file can be created at every value, see [validate::creatable_path]
+    pub fn creatable_path(self) -> Self {
+        self.validate_with(validate::creatable_path())
+    }
+
+    /// Check every value against the regex `pattern`, behind the `regex` feature.
+    /// Failures are reported as [ParseError::Validation], naming the pattern
+    ///
+    /// ## Panics
+    /// If `pattern` is not a valid regex
+    #[cfg(feature = "regex")]
+    pub fn matches(self, pattern: &str) -> Self {
+        self.validate_with(validate::matches(pattern))
+    }
+
+    /// Get the parsed value as `T`, if a [ValueParser] produced one
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.typed_values.last().and_then(|v| v.downcast_ref::<T>())
+    }
+
+    /// Get the typed values of the last occurrence of a [Flag::tuple] flag as a tuple,
+    /// `None` if the types do not match
+    pub fn get_tuple<T: TupleValue>(&self) -> Option<T> {
+        value::last_tuple(&self.typed_values, self.tuple.len())
+    }
+
+    /// Get all parsed values as `T`, see [DuplicatePolicy::Collect]
+    pub fn get_all<T: Any>(&self) -> Vec<&T> {
+        self.typed_values
+            .iter()
+            .filter_map(|v| v.downcast_ref::<T>())
+            .collect()
+    }
+
+    /// The deprecation notice of the flag, see [Flag::deprecated]
+    pub fn deprecation(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    /// The [FlagKind] of the flag
+    pub fn kind(&self) -> FlagKind {
+        self.kind
+    }
+
+    /// If the flag was passed, flags filled in from a default are not present
+    pub fn is_present(&self) -> bool {
+        self.occurrences > 0
+    }
+
+    /// The parsed value of a [FlagKind::Value] flag
+    pub fn value(&self) -> Option<&str> {
+        self.positional_val.as_deref()
+    }
+
+    /// The raw parsed value, not lossily converted when parsed
+    /// with [CliParser::from_args_os]
+    pub fn value_os(&self) -> Option<&OsStr> {
+        match self.positional_val {
+            Some(_) => self.os_values.last().map(|v| v.as_os_str()),
+            None => None,
+        }
+    }
+
+    /// All raw parsed values, see [Flag::value_os]
+    pub fn values_os(&self) -> &[OsString] {
+        &self.os_values
+    }
+
+    /// If the value is the `-` sentinel, conventionally meaning stdin
+    pub fn is_stdin(&self) -> bool {
+        self.value() == Some(STDIN)
+    }
+
+    /// Number of times the flag was passed, mainly for [FlagKind::Count] flags
+    pub fn count(&self) -> usize {
+        self.occurrences
+    }
+
+    /// Index in the input args of each time the flag was passed, in order,
+    /// empty if filled in from a default. See [Command::ordered_flags]
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Fill in and validate the values of every flag in `recipes` missing from `parsed`
+    /// from the environment, the config, or the default, in that order, then convert
+    /// the values of every flag with a [ValueParser]
+    fn resolve(
+        recipes: &HashMap<String, Flag>,
+        parsed: &mut HashMap<String, Flag>,
+        layers: &mut Layers,
+        sandboxed: bool,
+    ) -> Result<(), ParseError> {
+        for recipe in recipes.values() {
+            let missing = !parsed.contains_key(&recipe.id);
+            // Untrusted definitions must not read the environment or config
+            let layered = (missing && !sandboxed)
+                .then(|| layers.lookup(recipe))
+                .flatten();
+            if let Some(layered) = &layered {
+                let flag = recipe
+                    .layered(&layered.values, layered.source, sandboxed)
+                    .map_err(|e| layered.locate(e))?;
+                parsed.extend(flag.map(|flag| (recipe.id.clone(), flag)));
+            } else if let (Some(default), true) = (&recipe.default_val, missing) {
+                let mut flag = Flag::new(&recipe.id);
+                flag.kind = recipe.kind;
+                flag.positional_val = Some(default.clone());
+                flag.values.push(default.clone());
+                flag.os_values.push(default.into());
+                flag.positions.push(None);
+                flag.source = ValueSource::Default;
+                check_validators(&recipe.id, &recipe.validators, &flag.values, sandboxed)?;
+                parsed.insert(recipe.id.clone(), flag);
+            }
+
+            let parsers: Vec<&ValueParser> = match &recipe.value_parser {
+                Some(value_parser) => vec![value_parser],
+                None => recipe.tuple.iter().map(|(_, parser)| parser).collect(),
+            };
+            if let (false, Some(flag)) = (parsers.is_empty(), parsed.get_mut(&recipe.id)) {
+                flag.convert(&parsers).map_err(|e| match &layered {
+                    Some(layered) => layered.locate(e),
+                    None => e,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The flag set by `values` from the environment or the config, see [Flag::env].
+    /// Flags without a value are set by the last of the values, `None` if set to `false`
+    fn layered(
+        &self,
+        values: &[String],
+        source: ValueSource,
+        sandboxed: bool,
+    ) -> Result<Option<Flag>, ParseError> {
+        let last = match values.last() {
+            Some(last) => last.as_str(),
+            None => return Ok(None),
+        };
+        let mut flag = Flag::new(&self.id);
+        flag.kind = self.kind;
+        flag.source = source;
+        if self.kind != FlagKind::Value {
+            flag.occurrences = match last {
+                "true" => 1,
+                "false" => return Ok(None),
+                count => count.parse().map_err(|_| {
+                    let message = format!("'{}' must be true, false or a count", count);
+                    ParseError::Validation(self.id.clone(), message)
+                })?,
+            };
+            return Ok(Some(flag).filter(Flag::is_present));
+        }
+
+        let values = values.iter().flat_map(|value| match self.value_delimiter {
+            Some(delimiter) => value.split(delimiter).collect(),
+            None => vec![value.as_str()],
+        });
+        for value in values {
+            flag.values.push(value.into());
+            flag.os_values.push(value.into());
+            flag.positions.push(None);
+        }
+        flag.positional_val = flag.values.last().cloned();
+        flag.value_delimiter = self.value_delimiter;
+        check_validators(&self.id, &self.validators, &flag.values, sandboxed)?;
+        Ok(Some(flag))
+    }
+
+    /// Check that every required flag in `recipes` is in `parsed`,
+    /// and that every parsed flag has an allowed number of values
+    fn validate(
+        recipes: &HashMap<String, Flag>,
+        parsed: &HashMap<String, Flag>,
+    ) -> Result<(), ParseError> {
+        for (id, flag) in recipes.iter() {
+            let received = match parsed.get(id) {
+                Some(parsed) => parsed.values.len(),
+                None if flag.required => Err(ParseError::MissingRequiredFlag(id.into()))?,
+                None => continue,
+            };
+            if let Some(min) = flag.min_values.filter(|&min| received < min) {
+                Err(ParseError::TooFewValues(id.into(), received, min))?;
+            }
+            if let Some(max) = flag.max_values.filter(|&max| received > max) {
+                Err(ParseError::TooManyValues(id.into(), received, max))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert all values, collecting every failure into a single error.
+    /// Values are converted by each of `parsers` in turn, see [Flag::tuple]
+    fn convert(&mut self, parsers: &[&ValueParser]) -> Result<(), ParseError> {
+        if let Some(raw) = self.os_values.iter().find(|raw| raw.to_str().is_none()) {
+            Err(ParseError::InvalidUnicode(raw.clone()))?;
+        }
+
+        let mut errors = Vec::new();
+        for (index, (value, position)) in self.values.iter().zip(self.positions.iter()).enumerate()
+        {
+            match parsers[index % parsers.len()].parse(value) {
+                Ok(typed) => self.typed_values.push(typed),
+                Err(reason) => errors.push(ValueError {
+                    index,
+                    position: *position,
+                    value: value.clone(),
+                    reason,
+                }),
+            }
+        }
+
+        if !errors.is_empty() {
+            Err(ParseError::InvalidValues(self.id.clone(), errors))?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of a [Flag], decides how it is parsed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlagKind {
+    /// Presence flag without a value, like `--force`
+    #[default]
+    Bool,
+    /// Flag taking a positional value, like `--out file`
+    Value,
+    /// Flag counting its occurrences, like `-v -v`
+    Count,
+}
+
+/// Represents a named positional value of a [Command]
+///
+/// Uses builder pattern to create
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// # use std::str::FromStr;
+/// let count = Positional::new("COUNT").value_parser(usize::from_str);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Positional {
+    /// Id / name of positional
+    pub id: String,
+    // Converts the raw value into a typed value
+    value_parser: Option<ValueParser>,
+    /// Actual parsed value
+    pub value: Option<String>,
+    // Raw parsed value, see [CliParser::from_args_os]
+    value_os: Option<OsString>,
+    // Actual parsed typed value
+    typed_value: Option<AnyValue>,
+    // Rules the value must satisfy
+    validators: Vec<Validator>,
+    // Index of the value in the input args
+    index: Option<usize>,
+}
+
+impl Positional {
+    /// Create a new `Positional` builder
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.into(),
+            value_parser: None,
+            value: None,
+            value_os: None,
+            typed_value: None,
+            validators: Vec::new(),
+            index: None,
+        }
+    }
+
+    /// Convert the value when parsing, e.g. with `usize::from_str`.
+    /// Conversion failures are reported as [ParseError::InvalidValue]
+    pub fn value_parser<T, E, F>(mut self, f: F) -> Self
+    where
+        T: Any + Send + Sync,
+        E: std::fmt::Display,
+        F: Fn(&str) -> Result<T, E> + Send + Sync + 'static,
+    {
+        self.value_parser = Some(ValueParser::new(f));
+        self
+    }
+
+    /// Check the value against `validator`, see [validate].
+    /// Failures are reported as [ParseError::Validation]
+    pub fn validate_with(mut self, validator: Validator) -> Self {
+        self.validators.push(validator);
+        self
+    }
+
+    /// The raw parsed value, not lossily converted when parsed
+    /// with [CliParser::from_args_os]
+    pub fn value_os(&self) -> Option<&OsStr> {
+        self.value_os.as_deref()
+    }
+
+    /// If the value is the `-` sentinel, conventionally meaning stdin
+    pub fn is_stdin(&self) -> bool {
+        self.value.as_deref() == Some(STDIN)
+    }
+
+    /// Index of the value in the input args
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Get the parsed value as `T`, if a [ValueParser] produced one
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.typed_value
+            .as_ref()
+            .and_then(|v| v.downcast_ref::<T>())
+    }
+}
+
+/// What to do when the same [Flag] is passed more than once
+///
+/// Set with [CliParser::on_duplicate], defaults to [DuplicatePolicy::LastWins]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Fail with [ParseError::DuplicateFlag]
+    Error,
+    /// Keep the value of the last occurrence
+    #[default]
+    LastWins,
+    /// Keep the value of the first occurrence
+    FirstWins,
+    /// Keep all values, in order, in [Flag::values]
+    Collect,
+}
+
+impl DuplicatePolicy {
+    /// Insert a parsed flag into `parsed`, resolving duplicates by the policy
+    fn insert(self, parsed: &mut HashMap<String, Flag>, mut flag: Flag) -> Result<(), ParseError> {
+        let existing = match parsed.get_mut(&flag.id) {
+            Some(existing) => existing,
+            None => {
+                parsed.insert(flag.id.clone(), flag);
+                return Ok(());
+            }
+        };
+
+        existing.occurrences += 1;
+        existing.indices.append(&mut flag.indices);
+        if existing.kind == FlagKind::Count {
+            return Ok(());
+        }
+        let policy = match existing.multiple {
+            true => DuplicatePolicy::Collect,
+            false => self,
+        };
+        match policy {
+            DuplicatePolicy::Error => Err(ParseError::DuplicateFlag(flag.id))?,
+            DuplicatePolicy::LastWins => {
+                flag.occurrences = existing.occurrences;
+                flag.indices = std::mem::take(&mut existing.indices);
+                *existing = flag;
+            }
+            DuplicatePolicy::FirstWins => {}
+            DuplicatePolicy::Collect => {
+                existing.values.append(&mut flag.values);
+                existing.os_values.append(&mut flag.os_values);
+                existing.positions.append(&mut flag.positions);
+                existing.positional_val = flag.positional_val;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How unexpected args are handled, set with [CliParser::strict] and [CliParser::lenient]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Unknown flags and unexpected args are errors
+    #[default]
+    Strict,
+    /// Unknown flags and unexpected args are skipped with a [ParseWarning]
+    Lenient,
+}
+
+/// Something parsed, but worth telling the user about, see [CliParser::warnings]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// A flag not defined globally or for the current command, skipped with [ParseMode::Lenient]
+    UnknownFlag(String),
+    /// An arg that is not a known command, subcommand, or value, skipped with [ParseMode::Lenient]
+    UnexpectedArg(String),
+    /// A [Flag::deprecated] flag was passed, (id, note)
+    Deprecated(String, String),
+    /// A config value whose key is the [Flag::config_key] of no flag, (key, file),
+    /// the file is `None` for [CliParser::config_value].
+    /// Environment variables are only looked up by the names given to [Flag::env],
+    /// so unknown ones are never seen
+    UnusedConfigKey(String, Option<String>),
+}
+
+/// Limits on the input args, set with [CliParser::limits].
+///
+/// Unlimited by default, useful when parsing untrusted input.
+///
+/// Limits are enforced as args are consumed. Constructors taking the whole input,
+/// like [CliParser::parse_from] and [CliParser::from_args_os], hold it in memory
+/// before that, so bound untrusted input before passing it, or split untrusted lines
+/// with [CliParser::parse_str_limited]. Args are never expanded from response files
+/// like `@args.txt`, each input arg counts once
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let limits = Limits {
+///     max_args: Some(64),
+///     max_arg_len: Some(4096),
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Max number of args
+    pub max_args: Option<usize>,
+    /// Max length in bytes of a single arg
+    pub max_arg_len: Option<usize>,
+}
+
+/// Which of the [Limits] was exceeded, and the offending count or length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Args(usize),
+    ArgLength(usize),
+}
+
+/// Represents an error that occured during parsing of the Cli input, a [Command] or a [Flag].
+#[derive(Debug)]
+pub enum ParseError {
+    None,
+    MissingPositional,
+    NoCommands,
+    InvalidCommand(String),
+    InvalidFlag(String),
+    ExpectedCommand,
+    ExpectedPositional,
+    ExpectedFlag,
+    RequiredPositional,
+    MissingRequiredFlag(String),
+    /// A value could not be converted by its [ValueParser], (id, reason)
+    InvalidValue(String, String),
+    /// Values of a flag could not be converted by its [ValueParser], (id, failures)
+    InvalidValues(String, Vec<ValueError>),
+    /// A flag was passed more than once with [DuplicatePolicy::Error]
+    DuplicateFlag(String),
+    /// An arg that had to be unicode was not, see [CliParser::from_args_os]
+    InvalidUnicode(OsString),
+    /// The caller lacks a capability required by a command or flag, (id, capability)
+    MissingCapability(String, String),
+    /// The input exceeded one of the configured [Limits]
+    LimitExceeded(Limit),
+    /// A value did not satisfy a [Validator], (id, message naming the rule)
+    Validation(String, String),
+    /// An abbreviation matched several commands, (abbreviation, candidates)
+    AmbiguousCommand(String, Vec<String>),
+    /// A flag got fewer values than [Flag::min_values], (id, received, min)
+    TooFewValues(String, usize, usize),
+    /// A flag got more values than [Flag::max_values], (id, received, max)
+    TooManyValues(String, usize, usize),
+    /// A quote was not closed when splitting a line, see [shell_split], (unterminated arg)
+    UnterminatedQuote(String),
+    /// A [CliParser::config_file] is not valid TOML, (path, reason)
+    InvalidConfigFile(String, String),
+    /// A value of a [CliParser::config_file] failed the validation or conversion
+    /// of its flag, (path, key, reason)
+    InvalidConfigValue(String, String, String),
+}
+
+impl ParseError {
+    /// Name of the error variant, without any of the values it carries
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParseError::None => "None",
+            ParseError::MissingPositional => "MissingPositional",
+            ParseError::NoCommands => "NoCommands",
+            ParseError::InvalidCommand(_) => "InvalidCommand",
+            ParseError::InvalidFlag(_) => "InvalidFlag",
+            ParseError::ExpectedCommand => "ExpectedCommand",
+            ParseError::ExpectedPositional => "ExpectedPositional",
+            ParseError::ExpectedFlag => "ExpectedFlag",
+            ParseError::RequiredPositional => "RequiredPositional",
+            ParseError::MissingRequiredFlag(_) => "MissingRequiredFlag",
+            ParseError::InvalidValue(_, _) => "InvalidValue",
+            ParseError::InvalidValues(_, _) => "InvalidValues",
+            ParseError::DuplicateFlag(_) => "DuplicateFlag",
+            ParseError::InvalidUnicode(_) => "InvalidUnicode",
+            ParseError::MissingCapability(_, _) => "MissingCapability",
+            ParseError::LimitExceeded(_) => "LimitExceeded",
+            ParseError::Validation(_, _) => "Validation",
+            ParseError::AmbiguousCommand(_, _) => "AmbiguousCommand",
+            ParseError::TooFewValues(_, _, _) => "TooFewValues",
+            ParseError::TooManyValues(_, _, _) => "TooManyValues",
+            ParseError::UnterminatedQuote(_) => "UnterminatedQuote",
+            ParseError::InvalidConfigFile(_, _) => "InvalidConfigFile",
+            ParseError::InvalidConfigValue(_, _, _) => "InvalidConfigValue",
+        }
+    }
+}
+
+/// The English message, see [Messages]
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Messages::new().error("C", self))
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::InvalidValues(_, errors) => errors
+                .first()
+                .map(|e| e as &(dyn std::error::Error + 'static)),
+            _ => None,
+        }
+    }
+}
+
+/// The id of the flag named `name`, starting with `--` unless it is a
+/// single character short flag like `-h`
+fn flag_id(name: &str) -> String {
+    let short = name.len() == 2 && name.starts_with('-') && name != "--";
+    match name.starts_with("--") || short {
+        true => name.to_string(),
+        false => format!("--{}", name),
+    }
+}
+
+/// Every occurrence of the flags in `parsed`, sorted by index in the input args.
+/// Shared by [Flag] and [MatchedFlag], with `indices` giving the indices of a flag
+fn ordered_flags<F>(parsed: &HashMap<String, F>, indices: fn(&F) -> &[usize]) -> Vec<(usize, &F)> {
+    let mut ordered: Vec<(usize, &F)> = parsed
+        .values()
+        .flat_map(|flag| indices(flag).iter().map(move |&index| (index, flag)))
+        .collect();
+    ordered.sort_by_key(|(index, _)| *index);
+    ordered
+}
+
+/// Check each of `values` against all `validators`, failing on the first violated rule.
+/// Validators reading the filesystem are skipped when `sandboxed`
+fn check_validators(
+    id: &str,
+    validators: &[Validator],
+    values: &[String],
+    sandboxed: bool,
+) -> Result<(), ParseError> {
+    for validator in validators.iter().filter(|v| !(sandboxed && v.reads_fs())) {
+        for value in values.iter() {
+            validator
+                .check(value)
+                .map_err(|message| ParseError::Validation(id.into(), message))?;
+        }
+    }
+    Ok(())
+}
+
+/// Show `candidates` as a numbered list on `output`, and read the choice from `input`,
+/// either a number or a candidate. Returns `None` if nothing valid was chosen
+fn pick_candidate(candidates: &[String], input: &Input, output: &mut impl Write) -> Option<usize> {
+    let numbered: Vec<String> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{}) {}", i + 1, c))
+        .collect();
+    write!(output, "Did you mean: {}\n> ", numbered.join(" ")).ok()?;
+    output.flush().ok()?;
+
+    let mut line = String::new();
+    input.read_line(&mut line).ok()?;
+    let choice = line.trim();
+    match choice.parse::<usize>() {
+        Ok(n) if (1..=candidates.len()).contains(&n) => Some(n - 1),
+        _ => candidates.iter().position(|c| c == choice),
+    }
+}
+
+/// Where parsing failed, see [CliParser::error_context]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Id of the innermost command matched before the failure
+    pub command: Option<String>,
+    /// Ids of the commands matched before the failure, outermost first
+    pub path: Vec<String>,
+    /// Index in the input args of the last arg consumed,
+    /// `None` if the failure was found after all args were parsed
+    pub position: Option<usize>,
+    /// The last arg consumed, as passed
+    pub token: Option<String>,
+    /// The known command or flag closest to an unknown one,
+    /// for [ParseError::InvalidCommand] and [ParseError::InvalidFlag]
+    pub suggestion: Option<String>,
+}
+
+impl ErrorContext {
+    /// Describe `error` with the context, like
+    /// `error in 'deploy': unknown flag '--froce' at position 1`
+    pub fn describe(&self, error: &ParseError) -> String {
+        self.describe_in(error, &Messages::new(), "C")
+    }
+
+    /// Describe `error` with the context in `locale`, see [Messages]
+    pub fn describe_in(&self, error: &ParseError, messages: &Messages, locale: &str) -> String {
+        let prefix = messages.template(locale, "error");
+        format!(
+            "{}{}",
+            prefix,
+            self.describe_after_prefix(error, messages, locale)
+        )
+    }
+
+    /// [ErrorContext::describe_in] without the leading `error` prefix
+    fn describe_after_prefix(
+        &self,
+        error: &ParseError,
+        messages: &Messages,
+        locale: &str,
+    ) -> String {
+        let mut message = String::new();
+        if let Some(command) = &self.command {
+            message.push_str(&messages.format(locale, "error.command", &[command]));
+        }
+        message.push_str(&format!(": {}", messages.error(locale, error)));
+        if let Some(position) = self.position {
+            let position = position.to_string();
+            message.push_str(&messages.format(locale, "error.position", &[&position]));
+        }
+        if let Some(suggestion) = &self.suggestion {
+            message.push_str(&messages.format(locale, "error.suggestion", &[suggestion]));
+        }
+        message
+    }
+}
+
+/// If an arg should be parsed as a flag.
+/// Negative numbers like `-5` or `-.5` and the lone `-` stdin sentinel are not flags,
+/// words parsing as numbers like `-inf` or `-nan` are
+fn looks_like_flag(arg: &str) -> bool {
+    let negative_number = arg.strip_prefix('-').is_some_and(|number| {
+        let digits = number.strip_prefix('.').unwrap_or(number);
+        digits.starts_with(|c: char| c.is_ascii_digit()) && number.parse::<f64>().is_ok()
+    });
+    arg.starts_with('-') && arg != STDIN && !negative_number
+}
+
+/// Conventional value meaning "read from stdin" (or write to stdout)
+const STDIN: &str = "-";
+
+/// Exit code of [CliParser::parse_or_exit] on errors
+pub const USAGE_EXIT_CODE: i32 = 2;
+
+/// Id of the global flag registered by [CliParser::show_config]
+pub const SHOW_CONFIG: &str = "--show-config";
+
+/// Parses the CLI inputs based on provided `Commands`
+///
+/// Exposes a builder interface with `::new();`
+///
+/// ## Usage
+/// ```no_run
+/// # use cli_parser::*;
+/// let app = CliParser::new()
+///     .command(Command::new("help"))
+///     .command(Command::new("version"))
+///     .parse()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct CliParser<It>
+where
+    It: Iterator<Item = String>,
+    It::Item: Debug,
+{
+    // Provided commands used for parsing
+    pub(crate) commands: HashMap<String, Command>,
+    // Input program arguments to parse into final [Command] struct
+    args: Peekable<It>,
+    // Global flags
+    pub(crate) global_flags: HashMap<String, Flag>,
+    // actually parsed flags
+    pub parsed_flags: HashMap<String, Flag>,
+    /// Unknown args skipped with [ParseMode::Lenient] and deprecated flags passed
+    pub warnings: Vec<ParseWarning>,
+    // If unknown args are errors or warnings
+    mode: ParseMode,
+    // How to handle flags passed more than once
+    duplicate_policy: DuplicatePolicy,
+    // Format of the opt-in `--show-config` global
+    show_config: Option<ConfigFormat>,
+    // Limits on the args to parse
+    limits: Limits,
+    // Number of args consumed so far
+    consumed: usize,
+    // The last input arg consumed
+    last_token: Option<String>,
+    // Raw args when parsing from [OsString]s, indexed by `consumed`
+    raw_args: Vec<OsString>,
+    // Accept DOS style `/flag` and `/flag:value` switches
+    windows_flags: bool,
+    // Accept `-flag` for long flags
+    single_dash_flags: bool,
+    // Args expanded from a single input arg, consumed before the next input arg
+    expanded: VecDeque<String>,
+    // If the last consumed arg came from `expanded`
+    last_expanded: bool,
+    // Overrides of the detected presentation settings
+    environment: Environment,
+    // User-facing strings, per locale
+    messages: Messages,
+    // Program name shown in help
+    bin_name: Option<String>,
+    // Printed for [VERSION], see [CliParser::version]
+    version: Option<String>,
+    // Layout of generated help
+    help_template: Option<String>,
+    // The `help` command is the built-in one, see [CliParser::help_command]
+    help_command: bool,
+    // Print the help when run without args, see [CliParser::help_on_empty]
+    help_on_empty: bool,
+    // Answer [COMPLETE_COMMAND], see [CliParser::dynamic_completion]
+    dynamic_completion: bool,
+    // The `completions` command is the built-in one, see [CliParser::completions_command]
+    completions_command: bool,
+    // Environment variables and config files read so far
+    sources: SourceCache,
+    // Values by config key, see [CliParser::config_value]
+    config: HashMap<String, String>,
+    // TOML files with config values, see [CliParser::config_file]
+    config_files: Vec<PathBuf>,
+    // Flag naming another config file, see [CliParser::config_flag]
+    config_flag: Option<String>,
+    // Capabilities of the caller
+    capabilities: HashSet<String>,
+    // Called with a summary after every parse
+    on_outcome: Option<OutcomeHook>,
+    // Called around every parse
+    hooks: ParseHooks,
+    // How often each command path was used, ranks suggestions
+    usage: HashMap<String, u64>,
+    // Application state passed to handlers through [Matches::context]
+    context: Option<std::sync::Arc<dyn Any + Send + Sync>>,
+    // Summary of the parse so far
+    outcome: ParseOutcome,
+    // Print deprecation warnings to stderr
+    warn_deprecated: bool,
+    // How to resolve unknown commands, in order
+    fallbacks: Vec<Fallback>,
+    // Let the user pick between ambiguous candidates when stdin is a terminal
+    interactive: bool,
+    // The exclusive flag that ended the current parse
+    exclusive_flag: Option<String>,
+    // Where generated output, errors, and prompts are written
+    streams: Streams,
+    // Where the last parse failed
+    error_context: Option<ErrorContext>,
+    // Closest known id to the unknown command or flag the last parse failed on
+    suggestion: Option<String>,
+    // Parse untrusted definitions without side effects
+    sandboxed: bool,
+}
+
+impl CliParser<std::vec::IntoIter<String>> {
+    /// Create a new [CliParser] builder from the program args.
+    ///
+    /// Args that are not valid unicode are reported as [ParseError::InvalidUnicode]
+    /// when parsing, instead of panicking, see [CliParser::from_args_os].
+    ///
+    /// On `wasm32-unknown-unknown` there are no program args, use [CliParser::parse_from],
+    /// [CliParser::parse_str], or [CliParser::parse_query] there instead
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use cli_parser::*;
+    /// let app = CliParser::new()
+    ///     .command(Command::new("help"))
+    ///     .command(Command::new("version"))
+    ///     .parse()
+    ///     .unwrap();
+    /// ```
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        CliParser::from_args_os(args_os().skip(1))
+    }
+
+    /// Create a new [CliParser] builder from [OsString] args, like [std::env::args_os].
+    ///
+    /// Values are kept untouched, see [Flag::value_os] and [Positional::value_os].
+    /// Commands, flags, and values converted by a [ValueParser] that are not
+    /// valid unicode are reported as [ParseError::InvalidUnicode]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = ["open", "file.txt"].map(std::ffi::OsString::from);
+    /// let cmd = CliParser::from_args_os(args)
+    ///     .command(Command::new("open").positional())
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.positional_os(), Some("file.txt".as_ref()));
+    /// ```
+    pub fn from_args_os<I: IntoIterator<Item = OsString>>(args: I) -> Self {
+        let raw_args: Vec<OsString> = args.into_iter().collect();
+        let lossy: Vec<String> = raw_args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+
+        let mut parser = CliParser::from_args(lossy.into_iter());
+        parser.raw_args = raw_args;
+        parser
+    }
+
+    /// Create a new [CliParser] builder from anything yielding strings,
+    /// like an array of `&str`, e.g. in tests or when embedding the parser
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["open", "file.txt", "--mode", "ro"])
+    ///     .command(
+    ///         Command::new("open")
+    ///             .positional()
+    ///             .flag(Flag::new("--mode").positional()),
+    ///     )
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.positional_val.as_deref(), Some("file.txt"));
+    /// ```
+    pub fn parse_from<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let args: Vec<String> = args.into_iter().map(Into::into).collect();
+        CliParser::from_args(args.into_iter())
+    }
+
+    /// Create a new [CliParser] builder from a single line, split into args
+    /// like a POSIX shell would, see [shell_split]. Useful for REPLs and tests
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_str(r#"deploy --tag "my app""#)
+    ///     .unwrap()
+    ///     .command(Command::new("deploy").flag(Flag::new("--tag").positional()))
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.parsed_flags["--tag"].value(), Some("my app"));
+    /// ```
+    pub fn parse_str(line: &str) -> Result<Self, ParseError> {
+        Ok(CliParser::from_args(shell_split(line)?.into_iter()))
+    }
+
+    /// [CliParser::parse_str] for untrusted lines, enforcing `limits` while splitting
+    /// the line and again when parsing, see [CliParser::limits]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let limits = Limits {
+    ///     max_args: Some(8),
+    ///     max_arg_len: Some(256),
+    /// };
+    /// let line = "deploy ".repeat(100);
+    /// assert!(matches!(
+    ///     CliParser::parse_str_limited(&line, limits),
+    ///     Err(ParseError::LimitExceeded(Limit::Args(9)))
+    /// ));
+    /// ```
+    pub fn parse_str_limited(line: &str, limits: Limits) -> Result<Self, ParseError> {
+        let args = shell_split_limited(line, limits)?;
+        Ok(CliParser::from_args(args.into_iter()).limits(limits))
+    }
+
+    /// Create a new [CliParser] builder from the `arg` parameters of a URL query string,
+    /// see [query_args]. Useful for browser based demos on WASM
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_query("?arg=greet&arg=--name&arg=Ada+Lovelace")
+    ///     .command(Command::new("greet").flag(Flag::new("--name").positional()))
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.value_of("--name"), Some("Ada Lovelace"));
+    /// ```
+    pub fn parse_query(query: &str) -> Self {
+        CliParser::from_args(query_args(query).into_iter())
+    }
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+    It::Item: Debug,
+{
+    pub fn from_args(it: It) -> Self
+    where
+        It: Iterator<Item = String>,
+        It::Item: Debug,
+    {
+        Self {
+            commands: HashMap::new(),
+            args: it.peekable(),
+            global_flags: HashMap::new(),
+            parsed_flags: HashMap::new(),
+            warnings: Vec::new(),
+            mode: ParseMode::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            show_config: None,
+            limits: Limits::default(),
+            consumed: 0,
+            last_token: None,
+            raw_args: Vec::new(),
+            windows_flags: false,
+            single_dash_flags: false,
+            expanded: VecDeque::new(),
+            last_expanded: false,
+            environment: Environment::default(),
+            messages: Messages::new(),
+            bin_name: None,
+            version: None,
+            help_template: None,
+            help_command: false,
+            help_on_empty: false,
+            dynamic_completion: false,
+            completions_command: false,
+            sources: SourceCache::default(),
+            config: HashMap::new(),
+            config_files: Vec::new(),
+            config_flag: None,
+            capabilities: HashSet::new(),
+            on_outcome: None,
+            hooks: ParseHooks::default(),
+            usage: HashMap::new(),
+            context: None,
+            outcome: ParseOutcome::default(),
+            warn_deprecated: false,
+            fallbacks: Vec::new(),
+            interactive: false,
+            exclusive_flag: None,
+            streams: Streams::default(),
+            error_context: None,
+            suggestion: None,
+            sandboxed: false,
+        }
+    }
+
+    /// Add a [Command] to be parsed
+    pub fn command(mut self, command: Command) -> Self {
+        self.add_command(command);
+        self
+    }
+
+    /// Add a [Command] to be parsed, like [CliParser::command] but through a reference,
+    /// e.g. for registering commands in a loop or from other functions
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let mut parser = CliParser::parse_from(["plugin-b", "--force"]);
+    /// for name in ["plugin-a", "plugin-b"] {
+    ///     parser.add_command(Command::new(name));
+    /// }
+    /// if let Some(cmd) = parser.get_command_mut("plugin-b") {
+    ///     cmd.add_flag(Flag::new("--force"));
+    /// }
+    /// assert!(parser.parse().is_ok());
+    /// ```
+    pub fn add_command(&mut self, command: Command) -> &mut Self {
+        // Your own `help` and `completions` commands replace the built-in ones
+        self.help_command &= command.id != HELP_COMMAND;
+        self.completions_command &= command.id != COMPLETIONS_COMMAND;
+        self.commands.insert(command.id.clone(), command);
+        self
+    }
+
+    /// A previously added [Command], to change its definition
+    pub fn get_command_mut(&mut self, id: &str) -> Option<&mut Command> {
+        self.commands.get_mut(id)
+    }
+
+    /// Embed all commands and global flags of another, independently built,
+    /// parser as the subcommands and flags of a new command `id`.
+    ///
+    /// The args of `other` are ignored, only its definitions are used.
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let toolx = CliParser::from_args(std::iter::empty())
+    ///     .command(Command::new("build"))
+    ///     .global_flag(Flag::new("--release"));
+    ///
+    /// let args = "toolx --release build".split(" ").map(|s| s.to_string());
+    /// let cmd = CliParser::from_args(args)
+    ///     .command_from(toolx, "toolx")
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.subcommand.unwrap().id, "build");
+    /// ```
+    pub fn command_from<Other>(self, other: CliParser<Other>, id: &str) -> Self
+    where
+        Other: Iterator<Item = String>,
+    {
+        let mut command = Command::new(id);
+        command.subcommands = other.commands;
+        command.flags = other.global_flags;
+        self.command(command)
+    }
+
+    /// Add a global [Flag] to be parsed
+    pub fn global_flag(mut self, flag: Flag) -> Self {
+        self.add_global_flag(flag);
+        self
+    }
+
+    /// Add a global [Flag] to be parsed, like [CliParser::global_flag] but through a reference
+    pub fn add_global_flag(&mut self, flag: Flag) -> &mut Self {
+        self.global_flags.insert(flag.id.clone(), flag);
+        self
+    }
+
+    /// Fail on anything unexpected, like unknown flags, see [ParseMode::Strict].
+    /// This is the default
+    pub fn strict(mut self) -> Self {
+        self.mode = ParseMode::Strict;
+        self
+    }
+
+    /// Skip anything unexpected, recording it in [CliParser::warnings],
+    /// see [ParseMode::Lenient]
+    pub fn lenient(mut self) -> Self {
+        self.mode = ParseMode::Lenient;
+        self
+    }
+
+    /// Also print a warning to [Streams::errors] when a [Flag::deprecated] flag is passed
+    pub fn warn_deprecated(mut self) -> Self {
+        self.warn_deprecated = true;
+        self
+    }
+
+    /// Accept any unique prefix of a command name, like `stat` for `status`,
+    /// same as adding [Fallback::Abbreviation].
+    ///
+    /// A prefix of several commands is a [ParseError::AmbiguousCommand],
+    /// unless the user picks one, see [CliParser::interactive]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "stat".split(" ").map(|s| s.to_string());
+    /// let cmd = CliParser::from_args(args)
+    ///     .command(Command::new("status"))
+    ///     .command(Command::new("stash"))
+    ///     .allow_abbreviations()
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.id, "status");
+    /// ```
+    pub fn allow_abbreviations(self) -> Self {
+        self.fallback(Fallback::Abbreviation)
+    }
+
+    /// When an abbreviation is ambiguous and [Streams::input] is a terminal, show a numbered
+    /// picker on [Streams::prompts], like `Did you mean: 1) stash 2) status`, and continue
+    /// with the selection read from [Streams::input]. Non-interactive input still fails
+    pub fn interactive(mut self) -> Self {
+        self.interactive = true;
+        self
+    }
+
+    /// Parse without side effects, for definitions from untrusted sources like
+    /// user supplied files or plugins. Handlers fail with [RunError::Sandboxed],
+    /// [Fallback::External] and the interactive picker are ignored, validators reading
+    /// the filesystem are skipped, and flags do not read their [Flag::env] or [Flag::config_key].
+    /// Combine with [CliParser::limits] for untrusted args
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["upload", "--src", "/etc/shadow"])
+    ///     .command(Command::new("upload").flag(Flag::new("--src").positional().existing_file()))
+    ///     .sandboxed()
+    ///     .parse()
+    ///     .unwrap();
+    /// ```
+    pub fn sandboxed(mut self) -> Self {
+        self.sandboxed = true;
+        self
+    }
+
+    /// Set how flags passed more than once are handled, see [DuplicatePolicy]
+    pub fn on_duplicate(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Set [Limits] on the args to parse, for parsing untrusted input
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Register the hidden [SHOW_CONFIG] global flag.
+    ///
+    /// When passed, the parsed command is returned once resolved and validated, with
+    /// [SHOW_CONFIG] as its [Command::exclusive_flag], to print with [CliParser::render_config].
+    /// [CliParser::parse_or_exit] prints the effective configuration and exits with `0`
+    pub fn show_config(mut self, format: ConfigFormat) -> Self {
+        self.show_config = Some(format);
+        self.global_flag(Flag::new(SHOW_CONFIG).hidden())
+    }
+
+    /// Accept DOS style switches, normalizing `/verbose` to `--verbose`
+    /// and `/out:file` to `--out file`.
+    ///
+    /// Only switches naming a defined flag are normalized, so paths like `/usr` are left as is
+    pub fn windows_flags(mut self) -> Self {
+        self.windows_flags = true;
+        self
+    }
+
+    /// Accept long flags with a single dash, like `-name value` in find or java,
+    /// in addition to `--name value`.
+    ///
+    /// Only args naming a defined flag are normalized, so values like `-5` are left as is
+    pub fn single_dash_flags(mut self) -> Self {
+        self.single_dash_flags = true;
+        self
+    }
+
+    /// Override the detected terminal width, color support, and locale
+    /// used for generated output, see [Environment]
+    pub fn environment(mut self, environment: Environment) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// Override the user-facing messages per locale, picked by
+    /// [Environment::effective_locale], see [Messages]
+    pub fn messages(mut self, messages: Messages) -> Self {
+        self.messages = messages;
+        self
+    }
+
+    /// Redirect where generated output, errors, and prompts are written, see [Streams]
+    pub fn streams(mut self, streams: Streams) -> Self {
+        self.streams = streams;
+        self
+    }
+
+    /// Set the config value of `key`, read by flags with that [Flag::config_key] when
+    /// they are not passed, nor set by their [Flag::env], see [ValueSource]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["remote", "add"])
+    ///     .command(Command::new("remote").subcommand(
+    ///         Command::new("add").flag(Flag::new("--tags").value_delimiter(',').config_key("tags")),
+    ///     ))
+    ///     .config_value("remote.add.tags", "ci,nightly")
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// let tags = &cmd.subcommand.unwrap().parsed_flags["--tags"];
+    /// assert_eq!(tags.values, ["ci", "nightly"]);
+    /// assert_eq!(tags.source, ValueSource::Config);
+    /// ```
+    pub fn config_value(mut self, key: &str, value: &str) -> Self {
+        self.config.insert(key.into(), value.into());
+        self
+    }
+
+    /// Forget the cached environment variables and config files, so they are read
+    /// again by the next parse. Sources are otherwise read only once per parser
+    pub fn invalidate_sources(&mut self) {
+        self.sources.clear();
+    }
+
+    /// Return an unknown (sub)command and all args following it as an external
+    /// command, see [Command::external], instead of failing with [ParseError::InvalidCommand].
+    /// Same as adding [Fallback::External]
+    ///
+    /// Lets applications dispatch to plugin binaries, git-style
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "foo --bar baz".split(" ").map(|s| s.to_string());
+    /// let cmd = CliParser::from_args(args)
+    ///     .command(Command::new("status"))
+    ///     .allow_external_subcommands()
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// if let Some((name, args)) = cmd.external() {
+    ///     assert_eq!(name, "foo");
+    ///     assert_eq!(args, ["--bar", "baz"]);
+    /// }
+    /// ```
+    pub fn allow_external_subcommands(self) -> Self {
+        self.fallback(Fallback::External)
+    }
+
+    /// Add a step to the pipeline resolving unknown (sub)commands, tried in the
+    /// order they are added. Commands no step resolves are a [ParseError::InvalidCommand],
+    /// suggesting the closest known command in [ErrorContext::suggestion]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "deploy prod".split(" ").map(|s| s.to_string());
+    /// let cmd = CliParser::from_args(args)
+    ///     .command(Command::new("status"))
+    ///     .fallback(Fallback::resolver(|name| match name {
+    ///         "deploy" => Some(Command::new("deploy").positional()),
+    ///         _ => None,
+    ///     }))
+    ///     .allow_external_subcommands()
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.positional_val.as_deref(), Some("prod"));
+    /// ```
+    pub fn fallback(mut self, fallback: Fallback) -> Self {
+        self.fallbacks.push(fallback);
+        self
+    }
+
+    /// Grant the caller `capability`.
+    ///
+    /// Commands and flags requiring capabilities the caller lacks are hidden from
+    /// suggestions and rejected with [ParseError::MissingCapability]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .command(Command::new("purge").requires_capability("admin"))
+    ///     .capability("admin");
+    /// ```
+    pub fn capability(mut self, capability: &str) -> Self {
+        self.capabilities.insert(capability.into());
+        self
+    }
+
+    /// If the caller has all capabilities `required`
+    fn has_capabilities(&self, required: &[String]) -> bool {
+        required.iter().all(|c| self.capabilities.contains(c))
+    }
+
+    /// Rank suggestions and ambiguous abbreviations by how often each command was used,
+    /// most used first. Keys are command paths joined by spaces, like `remote add`,
+    /// e.g. counted from [ParseOutcome::command_path] with [CliParser::on_outcome]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .command(Command::new("status"))
+    ///     .command(Command::new("stash"))
+    ///     .usage_frequency([("status".to_string(), 40), ("stash".to_string(), 2)]);
+    ///
+    /// let suggestions = parser.suggest_next(&["st"]);
+    /// assert_eq!(suggestions[0].value, "status");
+    /// ```
+    pub fn usage_frequency(mut self, counts: impl IntoIterator<Item = (String, u64)>) -> Self {
+        self.usage.extend(counts);
+        self
+    }
+
+    /// How often the command `id` under the commands `parent` was used
+    fn usage_of<S: AsRef<str>>(&self, parent: &[S], id: &str) -> u64 {
+        let mut path: Vec<&str> = parent.iter().map(|p| p.as_ref()).collect();
+        path.push(id);
+        self.usage.get(&path.join(" ")).copied().unwrap_or(0)
+    }
+
+    /// Check that the caller has all capabilities `required` to use `id`
+    fn check_capabilities(&self, id: &str, required: &[String]) -> Result<(), ParseError> {
+        match required.iter().find(|c| !self.capabilities.contains(*c)) {
+            Some(missing) => Err(ParseError::MissingCapability(id.into(), missing.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Register a callback invoked after every parse with an anonymized
+    /// [ParseOutcome], e.g. for collecting usage metrics
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .command(Command::new("deploy"))
+    ///     .on_outcome(|outcome| eprintln!("{:?}", outcome.command_path));
+    /// ```
+    pub fn on_outcome<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&ParseOutcome) + Send + Sync + 'static,
+    {
+        self.on_outcome = Some(OutcomeHook(std::sync::Arc::new(callback)));
+        self
+    }
+
+    /// Register a callback invoked at the start of every parse, e.g. for logging
+    /// or loading config. Callbacks run in the order they were registered
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .command(Command::new("deploy"))
+    ///     .before_parse(|| eprintln!("parsing"));
+    /// ```
+    pub fn before_parse<F>(mut self, callback: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.hooks.before.push(std::sync::Arc::new(callback));
+        self
+    }
+
+    /// Register a callback invoked with the result of every parse before it is
+    /// returned. The callback may inspect or replace the result, e.g. to log
+    /// errors or reject combinations the definition can not express.
+    /// Callbacks run in the order they were registered, before [CliParser::on_outcome]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let mut parser = CliParser::from_args(["deploy".to_string()].into_iter())
+    ///     .command(Command::new("deploy"))
+    ///     .after_parse(|res| {
+    ///         if res.as_ref().is_ok_and(|cmd| cmd.id == "deploy") {
+    ///             *res = Err(ParseError::Validation("deploy".into(), "frozen".into()));
+    ///         }
+    ///     });
+    /// assert!(parser.parse().is_err());
+    /// ```
+    pub fn after_parse<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&mut Result<Command, ParseError>) + Send + Sync + 'static,
+    {
+        self.hooks.after.push(std::sync::Arc::new(callback));
+        self
+    }
+
+    /// Parse like [CliParser::parse], but on error print it to [Streams::errors], followed by
+    /// the usage of the command that failed and a hint to pass [HELP] when it is registered,
+    /// and exit with [USAGE_EXIT_CODE], following the Unix convention for usage errors.
+    ///
+    /// When the [HELP] or [SHORT_HELP] flag, see [CliParser::help_flag], ended parsing, print
+    /// the detailed or summarized help of the matched command to [Streams::output]
+    /// and exit with `0` instead. The same goes for the help of the command named by the
+    /// built-in [HELP_COMMAND] command, see [CliParser::help_command], and for the
+    /// [CliParser::version] when [VERSION] is passed, and for the effective configuration
+    /// when [SHOW_CONFIG] is passed, see [CliParser::show_config]. With [CliParser::help_on_empty], running
+    /// without args prints the help of the program to [Streams::errors] and exits with
+    /// [USAGE_EXIT_CODE]. With [CliParser::dynamic_completion], [COMPLETE_COMMAND] prints
+    /// completions and exits with `0`, as does the built-in [COMPLETIONS_COMMAND] command
+    /// with the completion script of a shell, see [CliParser::completions_command].
+    /// See [CliParser::parse_or_print] to get the exit code instead of exiting
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::new()
+    ///     .command(Command::new("build"))
+    ///     .parse_or_exit();
+    /// ```
+    pub fn parse_or_exit(&mut self) -> Command {
+        self.parse_or_print()
+            .unwrap_or_else(|code| std::process::exit(code))
+    }
+
+    /// Parse and print like [CliParser::parse_or_exit], but return the exit code instead
+    /// of exiting, e.g. to capture the output through [Streams] in tests or GUI wrappers
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let output = Arc::new(Mutex::new(Vec::new()));
+    /// let code = CliParser::parse_from(["--version"])
+    ///     .bin_name("app")
+    ///     .version("1.2.0")
+    ///     .with_standard_flags()
+    ///     .streams(Streams {
+    ///         output: Stream::Writer(output.clone()),
+    ///         ..Default::default()
+    ///     })
+    ///     .parse_or_print()
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(code, 0);
+    /// assert_eq!(output.lock().unwrap().as_slice(), b"app 1.2.0\n");
+    /// ```
+    pub fn parse_or_print(&mut self) -> Result<Command, i32> {
+        if self.dynamic_completion && self.args.peek().is_some_and(|a| a == COMPLETE_COMMAND) {
+            let _ = write!(self.streams.output.clone(), "{}", self.complete_args());
+            return Err(0);
+        }
+        if self.help_on_empty && self.args.peek().is_none() {
+            let _ = write!(self.streams.errors.clone(), "{}", self.help());
+            return Err(USAGE_EXIT_CODE);
+        }
+        let printed = match self.parse() {
+            Ok(command) if matches!(command.exclusive_flag.as_deref(), Some(HELP | SHORT_HELP)) => {
+                let path: Vec<&str> = command
+                    .path()
+                    .into_iter()
+                    .filter(|id| !id.is_empty())
+                    .collect();
+                let long = command.exclusive_flag.as_deref() == Some(HELP);
+                let help = self
+                    .render_help(&path, long)
+                    .unwrap_or_else(|| self.render_help::<&str>(&[], long).unwrap());
+                Ok(help)
+            }
+            Ok(command)
+                if command.exclusive_flag.as_deref() == Some(VERSION) && self.version.is_some() =>
+            {
+                let version = self.version.as_deref().unwrap_or_default();
+                Ok(format!("{} {}\n", self.program_name(), version))
+            }
+            Ok(command) if self.help_command && command.id == HELP_COMMAND => {
+                self.help_command_output(&command)
+            }
+            Ok(command) if command.exclusive_flag.as_deref() == Some(SHOW_CONFIG) => {
+                Ok(self.render_config(&command))
+            }
+            // An exclusive flag skips the required shell, leaving the command to the caller
+            Ok(command) if command.exclusive_flag.is_some() => return Ok(command),
+            Ok(command) if self.completions_command && command.id == COMPLETIONS_COMMAND => {
+                match command.positional_as::<Shell>("SHELL") {
+                    Some(shell) => Ok(generate_completions(*shell, self)),
+                    None => Err(ParseError::MissingPositional),
+                }
+            }
+            Ok(command) => return Ok(command),
+            Err(e) => Err(e),
+        };
+        match printed {
+            Ok(text) => {
+                let _ = write!(self.streams.output.clone(), "{}", text);
+                Err(0)
+            }
+            Err(e) => {
+                let message = format!("{}{}", self.render_error(&e), self.error_hint());
+                let _ = writeln!(self.streams.errors.clone(), "{}", message);
+                Err(USAGE_EXIT_CODE)
+            }
+        }
+    }
+
+    /// Describe `error` with where the last parse failed, see [ErrorContext::describe].
+    ///
+    /// With the `color` feature, the `error` prefix is red and quoted names are bold
+    /// when [Environment::effective_color] for [Streams::errors]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let mut parser = CliParser::parse_from(["deploy", "--froce"])
+    ///     .environment(Environment::dumb())
+    ///     .command(Command::new("deploy"));
+    /// let err = parser.parse().unwrap_err();
+    /// assert_eq!(
+    ///     parser.render_error(&err),
+    ///     "error in 'deploy': unknown flag '--froce' at position 1"
+    /// );
+    /// ```
+    pub fn render_error(&self, error: &ParseError) -> String {
+        let context = self.error_context.clone().unwrap_or_default();
+        let locale = self.environment.effective_locale();
+        let prefix = self.messages.template(&locale, "error");
+        let rest = context.describe_after_prefix(error, &self.messages, &locale);
+        Style::new(&self.environment, &self.streams.errors).message(
+            MessageKind::Error,
+            prefix,
+            &rest,
+        )
+    }
+
+    /// Where the last [CliParser::parse] failed, `None` if it succeeded
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let args = "deploy --froce".split(" ").map(|s| s.to_string());
+    /// let mut parser = CliParser::from_args(args)
+    ///     .command(Command::new("deploy").flag(Flag::new("--force")));
+    /// let err = parser.parse().unwrap_err();
+    ///
+    /// let context = parser.error_context().unwrap();
+    /// assert_eq!(context.position, Some(1));
+    /// assert_eq!(
+    ///     context.describe(&err),
+    ///     "error in 'deploy': unknown flag '--froce' at position 1, did you mean '--force'?"
+    /// );
+    /// ```
+    pub fn error_context(&self) -> Option<&ErrorContext> {
+        self.error_context.as_ref()
+    }
+
+    /// Parse like [CliParser::parse], returning only the parsed data, see [Matches]
+    pub fn parse_matches(&mut self) -> Result<Matches, ParseError> {
+        self.parse().map(Matches::from)
+    }
+
+    /// Parse `args` instead of the args the parser was created with, so one
+    /// built parser can parse many arg vectors, e.g. in a server or REPL.
+    ///
+    /// Every call starts from a fresh state, [CliParser::warnings],
+    /// [CliParser::parsed_flags] and [CliParser::error_context] describe the latest call.
+    /// Args that are not valid unicode are handled like [CliParser::from_args_os]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let mut parser = CliParser::from_args(std::iter::empty())
+    ///     .command(Command::new("get").positional())
+    ///     .command(Command::new("quit"));
+    ///
+    /// assert_eq!(parser.parse_args(["get", "key"]).unwrap().positional_val.as_deref(), Some("key"));
+    /// assert_eq!(parser.parse_args(["quit"]).unwrap().id, "quit");
+    /// assert!(parser.parse_args(["unknown"]).is_err());
+    /// ```
+    pub fn parse_args<I, S>(&mut self, args: I) -> Result<Command, ParseError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        let raw_args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let lossy: Vec<String> = raw_args
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+
+        let mut parser = self.rebind(lossy.into_iter());
+        parser.raw_args = raw_args;
+        let res = parser.parse();
+
+        self.parsed_flags = parser.parsed_flags;
+        self.warnings = parser.warnings;
+        self.error_context = parser.error_context;
+        self.sources = parser.sources;
+        res
+    }
+
+    /// Parse `args` like [CliParser::parse_args], handing the parsed data to `store`
+    /// instead of returning it, see [MatchesStore]
+    pub fn parse_into<I, S, St>(&mut self, args: I, store: &mut St) -> Result<St::Key, ParseError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+        St: MatchesStore,
+    {
+        let command = self.parse_args(args)?;
+        Ok(store.store(Matches::from(command)))
+    }
+
+    /// A parser with the same definitions and settings as this one,
+    /// reading `args` from a fresh state
+    fn rebind<J>(&mut self, args: J) -> CliParser<J>
+    where
+        J: Iterator<Item = String>,
+    {
+        CliParser {
+            commands: self.commands.clone(),
+            args: args.peekable(),
+            global_flags: self.global_flags.clone(),
+            parsed_flags: HashMap::new(),
+            warnings: Vec::new(),
+            mode: self.mode,
+            duplicate_policy: self.duplicate_policy,
+            show_config: self.show_config,
+            limits: self.limits,
+            consumed: 0,
+            last_token: None,
+            raw_args: Vec::new(),
+            windows_flags: self.windows_flags,
+            single_dash_flags: self.single_dash_flags,
+            expanded: VecDeque::new(),
+            last_expanded: false,
+            environment: self.environment.clone(),
+            messages: self.messages.clone(),
+            bin_name: self.bin_name.clone(),
+            version: self.version.clone(),
+            help_template: self.help_template.clone(),
+            help_command: self.help_command,
+            help_on_empty: self.help_on_empty,
+            dynamic_completion: self.dynamic_completion,
+            completions_command: self.completions_command,
+            // Moved back after parsing, so the cache is kept across calls
+            sources: std::mem::take(&mut self.sources),
+            config: self.config.clone(),
+            config_files: self.config_files.clone(),
+            config_flag: self.config_flag.clone(),
+            capabilities: self.capabilities.clone(),
+            on_outcome: self.on_outcome.clone(),
+            hooks: self.hooks.clone(),
+            usage: self.usage.clone(),
+            context: self.context.clone(),
+            outcome: ParseOutcome::default(),
+            warn_deprecated: self.warn_deprecated,
+            fallbacks: self.fallbacks.clone(),
+            interactive: self.interactive,
+            exclusive_flag: None,
+            streams: self.streams.clone(),
+            error_context: None,
+            suggestion: None,
+            sandboxed: self.sandboxed,
+        }
+    }
+
+    /// Parse the provided program args into the constructed Command tree
+    ///
+    /// ## Errors
+    /// If any parsing fails return a [ParseError] error
+    pub fn parse(&mut self) -> Result<Command, ParseError> {
+        self.outcome = ParseOutcome::default();
+        self.exclusive_flag = None;
+        self.error_context = None;
+        self.suggestion = None;
+        for hook in &self.hooks.before {
+            hook();
+        }
+        let mut res = self.parse_resolved();
+        for hook in &self.hooks.after {
+            hook(&mut res);
+        }
+        if res.is_err() && self.error_context.is_none() {
+            // Failed after all args were parsed, no single arg is to blame
+            self.error_context = Some(ErrorContext {
+                command: self.outcome.command_path.last().cloned(),
+                path: self.outcome.command_path.clone(),
+                position: None,
+                token: None,
+                suggestion: None,
+            });
+        }
+
+        if let Some(hook) = &self.on_outcome {
+            let mut outcome = std::mem::take(&mut self.outcome);
+            outcome.error = res.as_ref().err().map(ParseError::kind);
+            (hook.0)(&outcome);
+        }
+        res
+    }
+
+    /// Parse, then resolve defaults and values of all flags
+    fn parse_resolved(&mut self) -> Result<Command, ParseError> {
+        let mut command = match self.parse_next() {
+            Ok(command) => command,
+            Err(e) => {
+                self.error_context = Some(ErrorContext {
+                    command: self.outcome.command_path.last().cloned(),
+                    path: self.outcome.command_path.clone(),
+                    position: self.consumed.checked_sub(1),
+                    token: self.last_token.clone(),
+                    suggestion: self.suggestion.take(),
+                });
+                return Err(e);
+            }
+        };
+
+        let config = self.config()?;
+        if !self.sandboxed {
+            let unused = self.unused_config_keys(&config);
+            self.warnings.extend(unused);
+        }
+        let mut layers = Layers {
+            sources: &mut self.sources,
+            config: &config,
+            prefix: String::new(),
+        };
+        Flag::resolve(
+            &self.global_flags,
+            &mut self.parsed_flags,
+            &mut layers,
+            self.sandboxed,
+        )?;
+        command.resolve(&mut layers, self.sandboxed)?;
+        // Return the global flags with the outermost command, they stay on the parser too
+        command.parsed_flags.extend(
+            self.parsed_flags
+                .iter()
+                .map(|(id, flag)| (id.clone(), flag.clone())),
+        );
+
+        if let Some(exclusive) = self.exclusive_flag.take() {
+            command.exclusive_flag = Some(exclusive);
+            return Ok(command);
+        }
+
+        // Validate once the full tree is resolved,
+        // flags may be passed after deeper subcommands
+        Flag::validate(&self.global_flags, &self.parsed_flags)?;
+        command.validate()?;
+
+        if self.show_config.is_some() && self.parsed_flags.contains_key(SHOW_CONFIG) {
+            command.exclusive_flag = Some(SHOW_CONFIG.into());
+        }
+
+        Ok(command)
+    }
+
+    /// Render every global flag and every flag of the parsed `command` and its
+    /// parsed subcommands with its resolved value and [ValueSource]
+    pub fn render_config(&self, command: &Command) -> String {
+        let globals = provenance::entries(
+            self.global_flags.values().filter(|f| f.id != SHOW_CONFIG),
+            &self.parsed_flags,
+        );
+        let mut scopes: Vec<provenance::Scope> = Vec::new();
+        let mut next = Some(command);
+        while let Some(command) = next {
+            let path = match scopes.last() {
+                Some(parent) => format!("{} {}", parent.path, command.id),
+                None => command.id.clone(),
+            };
+            let entries = provenance::entries(command.flags.values(), &command.parsed_flags);
+            scopes.push(provenance::Scope { path, entries });
+            next = command.subcommand.as_deref();
+        }
+
+        provenance::render(&globals, &scopes, self.show_config.unwrap_or_default())
+    }
+
+    /// Parse all args into the path of matched commands, outermost first,
+    /// then nest the path into a single [Command]
+    fn parse_next(&mut self) -> Result<Command, ParseError> {
+        let mut path: Vec<Command> = Vec::new();
+        loop {
+            self.parse_flags(&mut path)?;
+            while self.exclusive_flag.is_none() && self.skip_unexpected(path.last())? {
+                self.parse_flags(&mut path)?;
+            }
+
+            if self.exclusive_flag.is_some() || self.peek_arg()?.is_none() {
+                break;
+            }
+            if let Some(command) = path.last_mut().filter(|c| c.takes_trailing) {
+                command.trailing = self.parse_trailing()?;
+                break;
+            }
+            let command = self.parse_next_cmd(&path)?;
+            path.push(command);
+        }
+
+        let command = path.into_iter().rev().reduce(|sub, mut parent| {
+            parent.subcommand = Some(Box::new(sub));
+            parent
+        });
+        match (command, &self.exclusive_flag) {
+            (Some(command), _) => Ok(command),
+            (None, Some(_)) => Ok(Command::new("")),
+            (None, None) => Err(ParseError::ExpectedCommand),
+        }
+    }
+
+    /// With [ParseMode::Lenient], skip the next arg if it is not a known
+    /// (sub)command, recording a [ParseWarning]
+    fn skip_unexpected(&mut self, command: Option<&Command>) -> Result<bool, ParseError> {
+        let external = !self.sandboxed
+            && self
+                .fallbacks
+                .iter()
+                .any(|f| matches!(f, Fallback::External));
+        let trailing = command.is_some_and(|c| c.takes_trailing);
+        if self.mode != ParseMode::Lenient || external || trailing {
+            return Ok(false);
+        }
+        let arg = match self.peek_arg()?.cloned() {
+            Some(arg) => arg,
+            None => return Ok(false),
+        };
+        let known = match command {
+            Some(command) => command.subcommands.contains_key(&arg),
+            None => self.commands.contains_key(&arg),
+        };
+        if known {
+            return Ok(false);
+        }
+
+        self.next_arg()?;
+        self.warnings.push(ParseWarning::UnexpectedArg(arg));
+        Ok(true)
+    }
+
+    /// Take the next arg, enforcing the configured [Limits]
+    fn next_arg(&mut self) -> Result<Option<String>, ParseError> {
+        self.next_arg_if(|_| true)
+    }
+
+    /// Peek the next arg, expanding it first if needed
+    fn peek_arg(&mut self) -> Result<Option<&String>, ParseError> {
+        self.expand_next()?;
+        Ok(match self.expanded.front() {
+            Some(arg) => Some(arg),
+            None => self.args.peek(),
+        })
+    }
+
+    /// Take the next arg if it matches `pred`, enforcing the configured [Limits]
+    fn next_arg_if(
+        &mut self,
+        pred: impl FnOnce(&String) -> bool,
+    ) -> Result<Option<String>, ParseError> {
+        self.expand_next()?;
+        if let Some(arg) = self.expanded.front() {
+            if !pred(arg) {
+                return Ok(None);
+            }
+            self.last_expanded = true;
+            return Ok(self.expanded.pop_front());
+        }
+
+        let arg = match self.args.next_if(pred) {
+            Some(arg) => arg,
+            None => return Ok(None),
+        };
+        self.last_expanded = false;
+        self.count_arg(arg).map(Some)
+    }
+
+    /// Count a consumed input arg, enforcing the configured [Limits]
+    fn count_arg(&mut self, arg: String) -> Result<String, ParseError> {
+        self.consumed += 1;
+        self.last_token = Some(arg.clone());
+        if self.limits.max_args.is_some_and(|max| self.consumed > max) {
+            Err(ParseError::LimitExceeded(Limit::Args(self.consumed)))?;
+        }
+        if self.limits.max_arg_len.is_some_and(|max| arg.len() > max) {
+            Err(ParseError::LimitExceeded(Limit::ArgLength(arg.len())))?;
+        }
+        Ok(arg)
+    }
+
+    /// Expand the next input arg into one or more args, when all expanded
+    /// args have been consumed. Expands `/flag:value` with [CliParser::windows_flags]
+    /// and `-flag` with [CliParser::single_dash_flags]
+    fn expand_next(&mut self) -> Result<(), ParseError> {
+        if !self.expanded.is_empty() {
+            return Ok(());
+        }
+        let arg = match self.args.peek() {
+            Some(arg) => arg,
+            None => return Ok(()),
+        };
+
+        let (name, value) = match arg.strip_prefix('/') {
+            Some(switch) if self.windows_flags => match switch.split_once(':') {
+                Some((name, value)) => (format!("--{}", name), Some(value.to_string())),
+                None => (format!("--{}", switch), None),
+            },
+            _ if self.single_dash_flags && arg.starts_with('-') && !arg.starts_with("--") => {
+                (format!("-{}", arg), None)
+            }
+            _ => return Ok(()),
+        };
+        // Leave paths like `/usr` and values like `-5` untouched
+        if !self.defines_flag(&name) {
+            return Ok(());
+        }
+
+        let arg = self.args.next().unwrap();
+        self.count_arg(arg)?;
+        self.expanded.push_back(name);
+        self.expanded.extend(value);
+        Ok(())
+    }
+
+    /// If `id` is a global flag, or a flag of any command
+    fn defines_flag(&self, id: &str) -> bool {
+        self.global_flags.contains_key(id) || self.commands.values().any(|c| c.defines_flag(id))
+    }
+
+    /// The raw form of the last consumed arg `arg`
+    fn last_raw(&self, arg: &str) -> OsString {
+        if self.last_expanded {
+            return arg.into();
+        }
+        match self.raw_args.get(self.consumed.wrapping_sub(1)) {
+            Some(raw) => raw.clone(),
+            None => arg.into(),
+        }
+    }
+
+    /// Fail if the last consumed arg `arg` was not valid unicode
+    fn check_unicode(&self, arg: &str) -> Result<(), ParseError> {
+        let raw = self.last_raw(arg);
+        match raw.to_str() {
+            Some(_) => Ok(()),
+            None => Err(ParseError::InvalidUnicode(raw)),
+        }
+    }
+
+    fn parse_flags(&mut self, path: &mut [Command]) -> Result<(), ParseError> {
+        let trailing = path.last().is_some_and(|c| c.takes_trailing);
+        while self.exclusive_flag.is_none()
+            && self
+                .peek_arg()?
+                .is_some_and(|arg| looks_like_flag(arg) && !(trailing && arg == "--"))
+        {
+            self.parse_next_flag(path)?;
+        }
+        Ok(())
+    }
+
+    /// Parse the next flag, looking it up in the global flags,
+    /// then in the matched commands from the innermost and out
+    fn parse_next_flag(&mut self, path: &mut [Command]) -> Result<(), ParseError> {
+        let flag_str = match self.next_arg()? {
+            Some(flag) => flag,
+            None => Err(ParseError::ExpectedFlag)?,
+        };
+        self.check_unicode(&flag_str)?;
+
+        // Global flags take precedence over local, should maybe be other way around?
+        if let Some(glob_flag) = self.global_flags.get(&flag_str).cloned() {
+            let parsed_flag = self.parse_flag(&flag_str, &glob_flag)?;
+            self.duplicate_policy
+                .insert(&mut self.parsed_flags, parsed_flag)?;
+        } else if let Some(owner) = path
+            .iter_mut()
+            .rev()
+            .find(|c| c.flags.contains_key(&flag_str))
+        {
+            let local_flag = owner.flags[&flag_str].clone();
+            let parsed_flag = self.parse_flag(&flag_str, &local_flag)?;
+            self.duplicate_policy
+                .insert(&mut owner.parsed_flags, parsed_flag)?;
+        } else if self.mode == ParseMode::Lenient {
+            self.warnings.push(ParseWarning::UnknownFlag(flag_str));
+        } else {
+            let local = path.iter().flat_map(|c| c.flags.values());
+            let known = self.global_flags.values().chain(local);
+            let known = known.filter(|f| !f.hidden && self.has_capabilities(&f.capabilities));
+            self.suggestion = self.closest(&flag_str, known.map(|f| f.id.as_str()));
+            Err(ParseError::InvalidFlag(flag_str))?;
+        }
+
+        Ok(())
+    }
+
+    /// Parse a flag based on a flag_id and a flag_recipe
+    /// Parses positional values
+    fn parse_flag(&mut self, flag_str: &str, flag_recipe: &Flag) -> Result<Flag, ParseError> {
+        self.check_capabilities(&flag_recipe.id, &flag_recipe.capabilities)?;
+        self.outcome.flags.push(flag_recipe.id.clone());
+        if flag_recipe.exclusive {
+            self.exclusive_flag = Some(flag_recipe.id.clone());
+        }
+        let mut parsed_flag = Flag::new(flag_str);
+        parsed_flag.kind = flag_recipe.kind;
+        parsed_flag.multiple = flag_recipe.multiple;
+        parsed_flag.occurrences = 1;
+        parsed_flag.indices.push(self.consumed - 1);
+        if let Some(note) = &flag_recipe.deprecated {
+            if self.warn_deprecated {
+                let style = Style::new(&self.environment, &self.streams.errors);
+                let locale = self.environment.effective_locale();
+                let prefix = self.messages.template(&locale, "warning");
+                let id = style.bold(&flag_recipe.id);
+                let rest = self.messages.format(&locale, "deprecated", &[&id, note]);
+                let warning = style.message(MessageKind::Warning, prefix, &format!(": {}", rest));
+                let _ = writeln!(self.streams.errors.clone(), "{}", warning);
+            }
+            parsed_flag.deprecated = Some(note.clone());
+            self.warnings.push(ParseWarning::Deprecated(
+                flag_recipe.id.clone(),
+                note.clone(),
+            ));
+        }
+        parsed_flag.tuple = flag_recipe.tuple.clone();
+        parsed_flag.value_delimiter = flag_recipe.value_delimiter;
+        if flag_recipe.kind == FlagKind::Value && !flag_recipe.tuple.is_empty() {
+            for received in 0..flag_recipe.tuple.len() {
+                let value = match self
+                    .next_arg_if(|v| flag_recipe.allow_hyphen_values || !looks_like_flag(v))?
+                {
+                    Some(v) => v,
+                    None => Err(ParseError::TooFewValues(
+                        flag_recipe.id.clone(),
+                        received,
+                        flag_recipe.tuple.len(),
+                    ))?,
+                };
+                parsed_flag.os_values.push(self.last_raw(&value));
+                parsed_flag.positions.push(Some(self.consumed - 1));
+                parsed_flag.values.push(value);
+            }
+        } else if flag_recipe.kind == FlagKind::Value {
+            let value = match self
+                .next_arg_if(|v| flag_recipe.allow_hyphen_values || !looks_like_flag(v))?
+            {
+                Some(v) => v,
+                None => Err(ParseError::MissingPositional)?,
+            };
+            let position = Some(self.consumed - 1);
+
+            match flag_recipe.value_delimiter {
+                Some(delimiter) => {
+                    self.check_unicode(&value)?;
+                    for item in value.split(delimiter) {
+                        parsed_flag.os_values.push(item.into());
+                        parsed_flag.positions.push(position);
+                        parsed_flag.values.push(item.to_string());
+                    }
+                }
+                None => {
+                    parsed_flag.os_values.push(self.last_raw(&value));
+                    parsed_flag.positions.push(position);
+                    parsed_flag.values.push(value);
+                }
+            }
+        }
+        if flag_recipe.kind == FlagKind::Value {
+            parsed_flag.positional_val = parsed_flag.values.last().cloned();
+            check_validators(
+                &flag_recipe.id,
+                &flag_recipe.validators,
+                &parsed_flag.values,
+                self.sandboxed,
+            )?;
+        }
+        Ok(parsed_flag)
+    }
+
+    /// Parse the next (sub)command of the last command in `path` and its positionals
+    fn parse_next_cmd(&mut self, path: &[Command]) -> Result<Command, ParseError> {
+        let cmd_str: String = match self.next_arg()? {
+            Some(cmd_str) => cmd_str,
+            None => Err(ParseError::ExpectedCommand)?,
+        };
+        self.check_unicode(&cmd_str)?;
+
+        let commands = match path.last() {
+            Some(parent) => &parent.subcommands,
+            None => &self.commands,
+        };
+        let mut cmd_recipe = match commands.get(&cmd_str) {
+            Some(cmd) => cmd.clone(),
+            None => match self.resolve_unknown(commands, &cmd_str)? {
+                Some(Resolution::Command(cmd)) => *cmd,
+                Some(Resolution::External) => return self.parse_external(cmd_str),
+                None => {
+                    let known = commands.values();
+                    let known =
+                        known.filter(|c| !c.hidden && self.has_capabilities(&c.capabilities));
+                    self.suggestion = self.closest(&cmd_str, known.map(|c| c.id.as_str()));
+                    Err(ParseError::InvalidCommand(cmd_str))?
+                }
+            },
+        };
+        self.check_capabilities(&cmd_recipe.id, &cmd_recipe.capabilities)?;
+        self.outcome.command_path.push(cmd_recipe.id.clone());
+
+        // Leave an exclusive flag to be parsed instead of taking it as a positional
+        if let Some(arg) = self.peek_arg()?.cloned() {
+            let flag = self
+                .global_flags
+                .get(&arg)
+                .or(cmd_recipe.flags.get(&arg))
+                .or_else(|| path.iter().rev().find_map(|c| c.flags.get(&arg)));
+            if flag.is_some_and(|flag| flag.exclusive) {
+                return Ok(cmd_recipe);
+            }
         }
-        if self.args.peek().is_some() {
-            self.parse_next_cmd(command)
-        } else {
-            Ok(command.to_owned().unwrap())
+
+        if cmd_recipe.positional {
+            match self.next_arg()? {
+                Some(pos) => {
+                    cmd_recipe.positional_os = Some(self.last_raw(&pos));
+                    cmd_recipe.positional_val = Some(pos);
+                }
+                None => Err(ParseError::ExpectedPositional)?,
+            }
+        }
+
+        for recipe in cmd_recipe.positionals.iter() {
+            let parsed = self.parse_positional(recipe)?;
+            cmd_recipe
+                .parsed_positionals
+                .insert(parsed.id.clone(), parsed);
+        }
+
+        Ok(cmd_recipe)
+    }
+
+    /// Take all remaining input args untouched, skipping a leading `--`
+    fn parse_trailing(&mut self) -> Result<Vec<OsString>, ParseError> {
+        let mut tail: Vec<OsString> = self.expanded.drain(..).map(OsString::from).collect();
+        let mut leading = tail.is_empty();
+        while let Some(arg) = self.args.next() {
+            let arg = self.count_arg(arg)?;
+            self.last_expanded = false;
+            if std::mem::take(&mut leading) && arg == "--" {
+                continue;
+            }
+            tail.push(self.last_raw(&arg));
         }
+        Ok(tail)
+    }
+
+    /// Take all remaining args as the args of the external command `name`
+    fn parse_external(&mut self, name: String) -> Result<Command, ParseError> {
+        let mut external = Command::new(&name);
+        let mut args = Vec::new();
+        while let Some(arg) = self.next_arg()? {
+            args.push(arg);
+        }
+        external.external_args = Some(args);
+        self.outcome.command_path.push(name);
+        Ok(external)
+    }
+
+    /// Parse a named positional based on its recipe,
+    /// checking its validators and converting it if it has a [ValueParser]
+    fn parse_positional(&mut self, recipe: &Positional) -> Result<Positional, ParseError> {
+        let raw = match self.next_arg()? {
+            Some(raw) => raw,
+            None => Err(ParseError::ExpectedPositional)?,
+        };
+
+        check_validators(
+            &recipe.id,
+            &recipe.validators,
+            std::slice::from_ref(&raw),
+            self.sandboxed,
+        )?;
+        let mut parsed = Positional::new(&recipe.id);
+        if let Some(value_parser) = &recipe.value_parser {
+            self.check_unicode(&raw)?;
+            let typed = value_parser
+                .parse(&raw)
+                .map_err(|e| ParseError::InvalidValue(recipe.id.clone(), e))?;
+            parsed.typed_value = Some(typed);
+        }
+        parsed.value_os = Some(self.last_raw(&raw));
+        parsed.value = Some(raw);
+        parsed.index = Some(self.consumed - 1);
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_from_str() {
+        let args = "help".split(" ").map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(Command::new("help"))
+            .command(Command::new("version"))
+            .parse()
+            .unwrap();
+        println!("{:?}", parse_res);
+    }
+
+    #[test]
+    fn test_multi_flags() {
+        let args = "command --flag1 --flag2 test"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let cmd = CliParser::from_args(args)
+            .command(
+                Command::new("command")
+                    .flag(Flag::new("--flag1"))
+                    .flag(Flag::new("--flag2").positional()),
+            )
+            .command(Command::new("version"))
+            .parse()
+            .unwrap();
+
+        assert_eq!("command", cmd.id);
+        assert!(cmd.parsed_flags.contains_key("--flag1"));
+        assert!(cmd
+            .parsed_flags
+            .get("--flag2")
+            .is_some_and(|f| f.positional_val == Some("test".into())));
+
+        // println!("{:?}", cmd);
+    }
+    #[test]
+    fn test_positional_command_and_flags() {
+        let args = "with_positional something --test something --test2 somethingelse"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let _parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("with_positional")
+                    .positional()
+                    .flag(Flag::new("--test").positional())
+                    .flag(Flag::new("--test2").positional()),
+            )
+            .command(Command::new("version"))
+            .parse()
+            .unwrap();
+
+        println!("{:?}", _parse_res)
+    }
+
+    #[test]
+    fn test_positional_command() {
+        let args = "with_positional test".split(" ").map(|s| s.to_string());
+
+        let _parse_res = CliParser::from_args(args)
+            .command(Command::new("with_positional").positional())
+            .command(Command::new("version"))
+            .parse()
+            .unwrap();
+    }
+    #[test]
+    fn test_required_flag() {
+        let args = "help --test banaa".split(" ").map(|s| s.to_string());
+
+        let _parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("help")
+                    // .positional()
+                    .flag(Flag::new("test").positional().required()),
+            )
+            .command(Command::new("version"))
+            .parse()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_retrieval() {
+        let args = "help --test banana".split(" ").map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(Command::new("help").flag(Flag::new("test").positional().required()))
+            .command(Command::new("version"))
+            .parse()
+            .unwrap();
+
+        assert_eq!(parse_res.id.as_str(), "help");
+
+        assert_eq!(parse_res.positional_val, None);
+        assert!(parse_res.parsed_flags.contains_key("--test"));
+
+        assert!(parse_res
+            .parsed_flags
+            .get("--test")
+            .is_some_and(|f| f.positional_val.as_ref().is_some_and(|v| v == "banana")));
+        // println!("{:?}", parse_res);
+    }
+
+    #[test]
+    fn test_glob_and_local_flags() {
+        let args = "command --glob1 --local1 --glob2"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(Command::new("command").flag(Flag::new("--local1").required()))
+            .global_flag(Flag::new("--glob1"))
+            .global_flag(Flag::new("--glob2"))
+            .parse()
+            .unwrap();
+
+        assert!(parse_res.parsed_flags.contains_key("--glob1"));
+        assert!(parse_res.parsed_flags.contains_key("--glob2"));
+        assert!(parse_res.parsed_flags.contains_key("--local1"));
+    }
+
+    #[test]
+    fn test_typed_positional() {
+        let args = "take 3 apples".split(" ").map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("take")
+                    .positional_arg(Positional::new("COUNT").value_parser(usize::from_str))
+                    .positional_arg(Positional::new("WHAT")),
+            )
+            .parse()
+            .unwrap();
+
+        assert_eq!(parse_res.positional_as::<usize>("COUNT"), Some(&3));
+        assert!(parse_res
+            .parsed_positionals
+            .get("WHAT")
+            .is_some_and(|p| p.value.as_deref() == Some("apples")));
+    }
+
+    #[test]
+    fn test_typed_positional_invalid() {
+        let args = "take many".split(" ").map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("take")
+                    .positional_arg(Positional::new("COUNT").value_parser(usize::from_str)),
+            )
+            .parse();
+
+        assert!(matches!(parse_res, Err(ParseError::InvalidValue(id, _)) if id == "COUNT"));
+    }
+
+    #[test]
+    fn test_duplicate_policy() {
+        let parse = |policy| {
+            let args = "command --out a --out b".split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(Command::new("command").flag(Flag::new("--out").positional()))
+                .on_duplicate(policy)
+                .parse()
+        };
+
+        let value = |cmd: &Command| cmd.parsed_flags["--out"].positional_val.clone();
+
+        assert_eq!(
+            value(&parse(DuplicatePolicy::LastWins).unwrap()),
+            Some("b".into())
+        );
+        assert_eq!(
+            value(&parse(DuplicatePolicy::FirstWins).unwrap()),
+            Some("a".into())
+        );
+        assert!(matches!(
+            parse(DuplicatePolicy::Error),
+            Err(ParseError::DuplicateFlag(id)) if id == "--out"
+        ));
+
+        let collected = parse(DuplicatePolicy::Collect).unwrap();
+        assert_eq!(collected.parsed_flags["--out"].values, vec!["a", "b"]);
+        assert_eq!(collected.parsed_flags["--out"].occurrences, 2);
+    }
+
+    #[test]
+    fn test_render_config() {
+        let args = "serve --port 80".split(" ").map(|s| s.to_string());
+
+        let mut parser = CliParser::from_args(args)
+            .command(
+                Command::new("serve")
+                    .flag(Flag::new("--port").positional())
+                    .flag(Flag::new("--host").default_value("localhost"))
+                    .flag(Flag::new("--tls")),
+            )
+            .global_flag(Flag::new("--verbose"))
+            .show_config(ConfigFormat::Json);
+        let cmd = parser.parse().unwrap();
+
+        assert_eq!(cmd.parsed_flags["--host"].source, ValueSource::Default);
+        assert_eq!(
+            parser.render_config(&cmd),
+            concat!(
+                r#"{"command":"serve","global":[{"id":"--verbose","value":null,"source":null}],"#,
+                r#""local":[{"command":"serve","flags":["#,
+                r#"{"id":"--host","value":"localhost","source":"default"},"#,
+                r#"{"id":"--port","value":"80","source":"cli"},"#,
+                r#"{"id":"--tls","value":null,"source":null}]}]}"#
+            )
+        );
+
+        let cmd = parser.parse_args(["serve", "--show-config"]).unwrap();
+        assert_eq!(cmd.exclusive_flag.as_deref(), Some(SHOW_CONFIG));
+
+        let mut parser = CliParser::parse_from(["remote", "add", "--url", "x", "--show-config"])
+            .command(
+                Command::new("remote")
+                    .flag(Flag::new("--verbose"))
+                    .subcommand(Command::new("add").flag(Flag::new("--url").positional())),
+            )
+            .show_config(ConfigFormat::Table);
+        let cmd = parser.parse().unwrap();
+        assert_eq!(
+            parser.render_config(&cmd),
+            concat!(
+                "SCOPE        SETTING              VALUE                SOURCE\n",
+                "remote       --verbose            -                    unset\n",
+                "remote add   --url                x                    cli\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_layered_values() {
+        std::env::set_var("CLI_PARSER_TEST_PORT", "8080");
+        std::env::set_var("CLI_PARSER_TEST_TLS", "true");
+        let parse = |args: &str| {
+            CliParser::parse_from(args.split_whitespace())
+                .command(
+                    Command::new("serve")
+                        .flag(
+                            Flag::new("--port")
+                                .env("CLI_PARSER_TEST_PORT")
+                                .config_key("port")
+                                .default_value("80"),
+                        )
+                        .flag(Flag::new("--host").config_key("host").default_value("::"))
+                        .flag(Flag::new("--tls").env("CLI_PARSER_TEST_TLS"))
+                        .flag(Flag::new("--workers").positional().config_key("workers")),
+                )
+                .global_flag(Flag::new("-v").counted().config_key("verbosity"))
+                .config_value("serve.port", "9090")
+                .config_value("serve.host", "localhost")
+                .config_value("verbosity", "2")
+                .parse()
+        };
+
+        let cmd = parse("serve --port 1").unwrap();
+        let source = |id: &str| cmd.parsed_flags[id].source;
+        assert_eq!(cmd.value_of("--port"), Some("1"));
+        assert_eq!(source("--port"), ValueSource::Cli);
+        assert_eq!(cmd.value_of("--host"), Some("localhost"));
+        assert_eq!(source("--host"), ValueSource::Config);
+        assert!(cmd.is_present("--tls"));
+        assert_eq!(source("--tls"), ValueSource::Env);
+        assert_eq!(cmd.occurrences_of("-v"), 2);
+        assert!(!cmd.parsed_flags.contains_key("--workers"));
+
+        let cmd = parse("serve").unwrap();
+        assert_eq!(cmd.value_of("--port"), Some("8080"));
+        assert_eq!(cmd.parsed_flags["--port"].source, ValueSource::Env);
+
+        let sandboxed = CliParser::parse_from(["serve"])
+            .command(
+                Command::new("serve").flag(
+                    Flag::new("--port")
+                        .env("CLI_PARSER_TEST_PORT")
+                        .config_key("port"),
+                ),
+            )
+            .config_value("serve.port", "9090")
+            .sandboxed()
+            .parse()
+            .unwrap();
+        assert!(!sandboxed.parsed_flags.contains_key("--port"));
+
+        std::env::set_var("CLI_PARSER_TEST_TLS", "sometimes");
+        assert!(matches!(
+            parse("serve"),
+            Err(ParseError::Validation(id, message))
+                if id == "--tls" && message == "'sometimes' must be true, false or a count"
+        ));
+    }
+
+    #[test]
+    fn test_subcommands() {
+        let args = "remote --verbose add origin"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("remote")
+                    .flag(Flag::new("--verbose"))
+                    .subcommand(Command::new("add").positional()),
+            )
+            .parse()
+            .unwrap();
+
+        assert_eq!(parse_res.id, "remote");
+        assert!(parse_res.parsed_flags.contains_key("--verbose"));
+        let sub = parse_res.subcommand.unwrap();
+        assert_eq!(sub.id, "add");
+        assert_eq!(sub.positional_val, Some("origin".into()));
+    }
+
+    #[test]
+    fn test_command_from() {
+        let toolx = CliParser::from_args(std::iter::empty())
+            .command(Command::new("build").flag(Flag::new("--target").positional()))
+            .global_flag(Flag::new("--release"));
+
+        let args = "toolx --release build --target x86"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(Command::new("build"))
+            .command_from(toolx, "toolx")
+            .parse()
+            .unwrap();
+
+        assert_eq!(parse_res.id, "toolx");
+        assert!(parse_res.parsed_flags.contains_key("--release"));
+        let sub = parse_res.subcommand.unwrap();
+        assert_eq!(sub.id, "build");
+        assert!(sub.parsed_flags.contains_key("--target"));
+    }
+
+    #[test]
+    fn test_negative_number_values() {
+        let args = "seek -5 --offset -5 --pattern -v"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("seek")
+                    .positional()
+                    .flag(Flag::new("--offset").positional())
+                    .flag(Flag::new("--pattern").positional().allow_hyphen_values()),
+            )
+            .parse()
+            .unwrap();
+
+        assert_eq!(parse_res.positional_val, Some("-5".into()));
+        assert_eq!(
+            parse_res.parsed_flags["--offset"].positional_val,
+            Some("-5".into())
+        );
+        assert_eq!(
+            parse_res.parsed_flags["--pattern"].positional_val,
+            Some("-v".into())
+        );
+
+        assert!(looks_like_flag("-inf") && looks_like_flag("-nan") && looks_like_flag("-e5"));
+        assert!(!looks_like_flag("-.5") && !looks_like_flag("-1e3"));
+    }
+
+    #[test]
+    fn test_hyphen_value_rejected() {
+        let args = "seek --offset --verbose".split(" ").map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("seek")
+                    .flag(Flag::new("--offset").positional())
+                    .flag(Flag::new("--verbose")),
+            )
+            .parse();
+
+        assert!(matches!(parse_res, Err(ParseError::MissingPositional)));
+    }
+
+    #[test]
+    fn test_limits() {
+        let parse = |input: &str, limits| {
+            let args = input.split(" ").map(|s| s.to_string()).collect::<Vec<_>>();
+            CliParser::from_args(args.into_iter())
+                .command(Command::new("echo").flag(Flag::new("--msg").positional()))
+                .limits(limits)
+                .parse()
+        };
+        let limits = Limits {
+            max_args: Some(3),
+            max_arg_len: Some(8),
+        };
+
+        assert!(parse("echo --msg hello", limits).is_ok());
+        assert!(matches!(
+            parse("echo --msg hello --msg hi", limits),
+            Err(ParseError::LimitExceeded(Limit::Args(4)))
+        ));
+        assert!(matches!(
+            parse("echo --msg hello_world", limits),
+            Err(ParseError::LimitExceeded(Limit::ArgLength(11)))
+        ));
+        assert!(matches!(
+            CliParser::parse_str_limited("echo --msg 'hello world'", limits),
+            Err(ParseError::LimitExceeded(Limit::ArgLength(11)))
+        ));
+    }
+
+    #[test]
+    fn test_flag_kinds() {
+        let args = "build --force --jobs 4 --verbose --verbose"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("build")
+                    .flag(Flag::new("--force").takes_value(false))
+                    .flag(Flag::new("--jobs").takes_value(true))
+                    .flag(Flag::new("--verbose").counted()),
+            )
+            .on_duplicate(DuplicatePolicy::Error)
+            .parse()
+            .unwrap();
+
+        let force = &parse_res.parsed_flags["--force"];
+        assert_eq!(force.kind(), FlagKind::Bool);
+        assert!(force.is_present());
+        assert_eq!(force.value(), None);
+
+        assert_eq!(parse_res.parsed_flags["--jobs"].value(), Some("4"));
+        assert_eq!(parse_res.parsed_flags["--verbose"].count(), 2);
     }
 
-    fn parse_flags(&mut self, command: &mut Option<Command>) -> Result<(), ParseError> {
-        while self.args.peek().is_some_and(|arg| arg.starts_with("-")) {
-            self.parse_next_flag(command)?;
-        }
-        Ok(())
+    #[test]
+    fn test_stdin_sentinel() {
+        let args = "cat - --out -".split(" ").map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("cat")
+                    .positional()
+                    .flag(Flag::new("--out").positional()),
+            )
+            .parse()
+            .unwrap();
+
+        assert!(parse_res.is_stdin());
+        assert!(parse_res.parsed_flags["--out"].is_stdin());
     }
 
-    fn parse_next_flag(&mut self, command: &mut Option<Command>) -> Result<(), ParseError> {
-        let flag_str = match self.args.next() {
-            Some(flag) => flag,
-            None => Err(ParseError::ExpectedFlag)?,
+    #[test]
+    #[cfg(unix)]
+    fn test_parse_from_os_args() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let invalid = OsString::from_vec(vec![b'f', 0xff, b'o']);
+        let args = vec![
+            "open".into(),
+            invalid.clone(),
+            "--out".into(),
+            invalid.clone(),
+        ];
+
+        let parse_res = CliParser::from_args_os(args)
+            .command(
+                Command::new("open")
+                    .positional_arg(Positional::new("PATH"))
+                    .flag(Flag::new("--out").positional()),
+            )
+            .parse()
+            .unwrap();
+
+        let path = &parse_res.parsed_positionals["PATH"];
+        assert_eq!(path.value_os(), Some(invalid.as_os_str()));
+        assert_eq!(path.value.as_deref(), Some("f\u{FFFD}o"));
+        assert_eq!(
+            parse_res.parsed_flags["--out"].value_os(),
+            Some(invalid.as_os_str())
+        );
+    }
+
+    #[test]
+    fn test_flag_value_errors_aggregated() {
+        let parse = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(
+                    Command::new("scan")
+                        .flag(Flag::new("--port").value_parser(u16::from_str))
+                        .flag(
+                            Flag::new("--retries")
+                                .default_value("3")
+                                .value_parser(u8::from_str),
+                        ),
+                )
+                .on_duplicate(DuplicatePolicy::Collect)
+                .parse()
         };
 
-        // Global flags take precedence over local, should maybe be other way around?
-        if self.global_flags.contains_key(&flag_str) {
-            let glob_flag = (*self.global_flags.get(&flag_str).unwrap()).clone();
-            let parsed_flag = self.parse_flag(&flag_str, &glob_flag)?;
-            self.parsed_flags.insert(flag_str.into(), parsed_flag);
-        } else if command
-            .as_ref()
-            .is_some_and(|c| c.flags.contains_key(&flag_str))
-        {
-            let local_flag = (*command.as_ref().unwrap().flags.get(&flag_str).unwrap()).clone();
-            let parsed_flag = self.parse_flag(&flag_str, &local_flag)?;
+        let cmd = parse("scan --port 80 --port 443").unwrap();
+        assert_eq!(cmd.parsed_flags["--port"].get_all::<u16>(), vec![&80, &443]);
+        assert_eq!(cmd.flag_as::<u16>("--port"), Some(&443));
+        assert_eq!(cmd.flag_as::<u8>("--retries"), Some(&3));
 
-            command
-                .as_mut()
-                .unwrap()
-                .parsed_flags
-                .insert(flag_str.into(), parsed_flag);
-        } else {
-            Err(ParseError::InvalidFlag(flag_str))?;
+        match parse("scan --port http --port 80 --port -1") {
+            Err(ParseError::InvalidValues(id, errors)) => {
+                assert_eq!(id, "--port");
+                let bad: Vec<_> = errors
+                    .iter()
+                    .map(|e| (e.position, e.value.as_str()))
+                    .collect();
+                assert_eq!(bad, vec![(Some(2), "http"), (Some(6), "-1")]);
+            }
+            other => panic!("expected InvalidValues, got {:?}", other),
         }
+    }
 
-        Ok(())
+    #[test]
+    #[cfg(unix)]
+    fn test_invalid_unicode() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let invalid = OsString::from_vec(vec![b'-', b'-', 0xff]);
+        let parse_res = CliParser::from_args_os(vec!["open".into(), invalid.clone()])
+            .command(Command::new("open"))
+            .parse();
+
+        assert!(matches!(parse_res, Err(ParseError::InvalidUnicode(arg)) if arg == invalid));
+
+        let invalid = OsString::from_vec(vec![b'4', 0xff]);
+        let parse_res = CliParser::from_args_os(vec!["take".into(), invalid.clone()])
+            .command(
+                Command::new("take")
+                    .positional_arg(Positional::new("COUNT").value_parser(usize::from_str)),
+            )
+            .parse();
+
+        assert!(matches!(parse_res, Err(ParseError::InvalidUnicode(arg)) if arg == invalid));
     }
 
-    /// Parse a flag based on a flag_id and a flag_recipe
-    /// Parses positional values
-    fn parse_flag(&mut self, flag_str: &str, flag_recipe: &Flag) -> Result<Flag, ParseError> {
-        let mut parsed_flag = Flag::new(flag_str);
-        if flag_recipe.positional {
-            parsed_flag.positional_val = match self.args.next() {
-                Some(v) => Some(v),
-                None => Err(ParseError::MissingPositional)?,
-            };
-        }
-        Ok(parsed_flag)
+    #[test]
+    fn test_on_outcome() {
+        use std::sync::{Arc, Mutex};
+
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let parse = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            let outcomes = outcomes.clone();
+            CliParser::from_args(args)
+                .command(
+                    Command::new("remote")
+                        .subcommand(Command::new("add").flag(Flag::new("--url").positional())),
+                )
+                .global_flag(Flag::new("--quiet"))
+                .on_outcome(move |o| outcomes.lock().unwrap().push(o.clone()))
+                .parse()
+        };
+
+        let _ = parse("--quiet remote add --url secret");
+        let _ = parse("remote add --token secret");
+
+        let outcomes = outcomes.lock().unwrap();
+        assert_eq!(
+            outcomes[0],
+            ParseOutcome {
+                command_path: vec!["remote".into(), "add".into()],
+                flags: vec!["--quiet".into(), "--url".into()],
+                error: None,
+            }
+        );
+        assert_eq!(outcomes[1].error, Some("InvalidFlag"));
+        assert!(outcomes[1].flags.is_empty());
     }
 
-    /// Recursively parse a command based on constructed cli recipe
-    fn parse_next_cmd(&mut self, command: &mut Option<Command>) -> Result<Command, ParseError> {
-        let cmd_str: String = match self.args.next() {
-            Some(cmd_str) => cmd_str,
-            None => Err(ParseError::ExpectedCommand)?,
+    #[test]
+    fn test_parse_hooks() {
+        use std::sync::{Arc, Mutex};
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (before, after) = (log.clone(), log.clone());
+        let outcome = log.clone();
+        let mut parser = CliParser::from_args(["deploy".to_string()].into_iter())
+            .command(Command::new("deploy"))
+            .before_parse(move || before.lock().unwrap().push("before".to_string()))
+            .after_parse(move |res| {
+                after.lock().unwrap().push(format!("after {}", res.is_ok()));
+                *res = Err(ParseError::NoCommands);
+            })
+            .on_outcome(move |o| outcome.lock().unwrap().push(format!("{:?}", o.error)));
+
+        assert!(matches!(parser.parse(), Err(ParseError::NoCommands)));
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["before", "after true", "Some(\"NoCommands\")"]
+        );
+    }
+
+    #[test]
+    fn test_windows_flags() {
+        let args = "copy /usr/src /verbose /out:C:\\dest"
+            .split(" ")
+            .map(|s| s.to_string());
+
+        let parse_res = CliParser::from_args(args)
+            .command(
+                Command::new("copy")
+                    .positional()
+                    .flag(Flag::new("--verbose"))
+                    .flag(Flag::new("--out").positional()),
+            )
+            .windows_flags()
+            .parse()
+            .unwrap();
+
+        assert_eq!(parse_res.positional_val, Some("/usr/src".into()));
+        assert!(parse_res.parsed_flags.contains_key("--verbose"));
+        assert_eq!(parse_res.parsed_flags["--out"].value(), Some("C:\\dest"));
+    }
+
+    #[test]
+    fn test_single_dash_flags() {
+        let parse = |single_dash: bool| {
+            let args = "find -name foo --type f -maxdepth -1"
+                .split(" ")
+                .map(|s| s.to_string());
+            let mut parser = CliParser::from_args(args).command(
+                Command::new("find")
+                    .flag(Flag::new("--name").positional())
+                    .flag(Flag::new("--type").positional())
+                    .flag(Flag::new("--maxdepth").positional()),
+            );
+            if single_dash {
+                parser = parser.single_dash_flags();
+            }
+            parser.parse()
         };
 
-        // TODO: Prune branches, branches on cmd_str and cmd_recipe.id
-        let mut cmd_recipe = match command {
-            Some(recipe) => recipe.to_owned(),
+        let parse_res = parse(true).unwrap();
+        assert_eq!(parse_res.parsed_flags["--name"].value(), Some("foo"));
+        assert_eq!(parse_res.parsed_flags["--type"].value(), Some("f"));
+        assert_eq!(parse_res.parsed_flags["--maxdepth"].value(), Some("-1"));
 
-            None => match self.commands.get(&cmd_str) {
-                Some(cmd) => (*cmd).clone(),
-                None => Err(ParseError::InvalidCommand(cmd_str))?,
-            },
+        assert!(matches!(parse(false), Err(ParseError::InvalidFlag(f)) if f == "-name"));
+    }
+
+    #[test]
+    fn test_strict_and_lenient() {
+        let parser = || {
+            let args = "typo build --release --fast extra"
+                .split(" ")
+                .map(|s| s.to_string());
+            CliParser::from_args(args).command(Command::new("build").flag(Flag::new("--release")))
         };
 
-        if cmd_recipe.positional {
-            match self.args.next() {
-                Some(pos) => cmd_recipe.positional_val = Some(pos),
-                None => Err(ParseError::ExpectedPositional)?,
-            }
-        }
+        assert!(matches!(
+            parser().strict().parse(),
+            Err(ParseError::InvalidCommand(c)) if c == "typo"
+        ));
 
-        self.parse_next(&mut Some(cmd_recipe))
+        let mut lenient = parser().lenient();
+        let parse_res = lenient.parse().unwrap();
+        assert_eq!(parse_res.id, "build");
+        assert!(parse_res.parsed_flags.contains_key("--release"));
+        assert_eq!(
+            lenient.warnings,
+            vec![
+                ParseWarning::UnexpectedArg("typo".into()),
+                ParseWarning::UnknownFlag("--fast".into()),
+                ParseWarning::UnexpectedArg("extra".into()),
+            ]
+        );
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn test_required_flag_deferred_to_subcommands() {
+        let parse = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(
+                    Command::new("remote")
+                        .flag(Flag::new("--name").positional().required())
+                        .subcommand(
+                            Command::new("add")
+                                .flag(Flag::new("--url").positional().required())
+                                .subcommand(Command::new("mirror")),
+                        ),
+                )
+                .global_flag(Flag::new("--token").positional().required())
+                .parse()
+        };
+
+        let parse_res = parse("remote add mirror --url x --name origin --token t").unwrap();
+        assert_eq!(parse_res.parsed_flags["--name"].value(), Some("origin"));
+        let add = parse_res.subcommand.unwrap();
+        assert_eq!(add.parsed_flags["--url"].value(), Some("x"));
+
+        assert!(matches!(
+            parse("remote add mirror --url x --token t"),
+            Err(ParseError::MissingRequiredFlag(id)) if id == "--name"
+        ));
+        assert!(matches!(
+            parse("remote add --url x --name origin"),
+            Err(ParseError::MissingRequiredFlag(id)) if id == "--token"
+        ));
+    }
 
     #[test]
-    fn test_parse_from_str() {
-        let args = "help".split(" ").map(|s| s.to_string());
+    fn test_external_subcommands() {
+        let args = "--verbose remote prune --dry-run origin"
+            .split(" ")
+            .map(|s| s.to_string());
 
         let parse_res = CliParser::from_args(args)
-            .command(Command::new("help"))
-            .command(Command::new("version"))
+            .command(Command::new("remote").subcommand(Command::new("add")))
+            .global_flag(Flag::new("--verbose"))
+            .allow_external_subcommands()
             .parse()
             .unwrap();
-        println!("{:?}", parse_res);
+
+        assert_eq!(parse_res.id, "remote");
+        assert_eq!(parse_res.external(), None);
+        let sub = parse_res.subcommand.unwrap();
+        assert_eq!(
+            sub.external(),
+            Some((
+                "prune",
+                &["--dry-run".to_string(), "origin".to_string()][..]
+            ))
+        );
     }
 
     #[test]
-    fn test_multi_flags() {
-        let args = "command --flag1 --flag2 test"
+    fn test_capabilities() {
+        let parse = |input: &str, capabilities: &[&str]| {
+            let args = input.split(" ").map(|s| s.to_string());
+            let mut parser = CliParser::from_args(args)
+                .command(Command::new("purge").requires_capability("admin"))
+                .command(
+                    Command::new("list")
+                        .flag(Flag::new("--all-tenants").requires_capability("admin")),
+                );
+            for capability in capabilities {
+                parser = parser.capability(capability);
+            }
+            parser.parse()
+        };
+
+        assert!(parse("purge", &["admin"]).is_ok());
+        assert!(parse("list --all-tenants", &["admin"]).is_ok());
+        assert!(matches!(
+            parse("purge", &[]),
+            Err(ParseError::MissingCapability(id, cap)) if id == "purge" && cap == "admin"
+        ));
+        assert!(matches!(
+            parse("list --all-tenants", &["user"]),
+            Err(ParseError::MissingCapability(id, _)) if id == "--all-tenants"
+        ));
+    }
+
+    #[test]
+    fn test_values_of_type() {
+        let parse = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(
+                    Command::new("sum").flag(
+                        Flag::new("--n")
+                            .values_of_type::<i32>()
+                            .value_delimiter(','),
+                    ),
+                )
+                .on_duplicate(DuplicatePolicy::Error)
+                .parse()
+        };
+
+        let cmd = parse("sum --n 1,2 --n -3").unwrap();
+        assert_eq!(cmd.flag_values_as::<i32>("--n"), vec![&1, &2, &-3]);
+
+        match parse("sum --n 1,x --n y") {
+            Err(ParseError::InvalidValues(_, errors)) => {
+                let bad: Vec<_> = errors.iter().map(|e| (e.index, e.position)).collect();
+                assert_eq!(bad, vec![(1, Some(2)), (2, Some(4))]);
+            }
+            other => panic!("expected InvalidValues, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validators() {
+        use crate::validate::{len, not, one_of};
+
+        let branch = || {
+            Command::new("checkout")
+                .positional_arg(Positional::new("BRANCH").validate_with(not(one_of(["HEAD"]))))
+                .flag(
+                    Flag::new("--track")
+                        .default_value("origin")
+                        .validate_with(len(1..=6)),
+                )
+        };
+
+        let args = "checkout main".split(" ").map(|s| s.to_string());
+        let cmd = CliParser::from_args(args)
+            .command(branch())
+            .parse()
+            .unwrap();
+        assert_eq!(
+            cmd.parsed_positionals["BRANCH"].value.as_deref(),
+            Some("main")
+        );
+
+        let args = "checkout HEAD".split(" ").map(|s| s.to_string());
+        match CliParser::from_args(args).command(branch()).parse() {
+            Err(ParseError::Validation(id, message)) => {
+                assert_eq!(id, "BRANCH");
+                assert_eq!(message, "'HEAD' must not be one of: HEAD");
+            }
+            other => panic!("expected validation error, got {:?}", other),
+        }
+
+        let args = "checkout main --track upstream"
             .split(" ")
             .map(|s| s.to_string());
+        let result = CliParser::from_args(args).command(branch()).parse();
+        assert!(matches!(result, Err(ParseError::Validation(id, _)) if id == "--track"));
+    }
 
-        let cmd = CliParser::from_args(args)
+    #[test]
+    fn test_abbreviations() {
+        let parse = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(Command::new("status"))
+                .command(Command::new("stash"))
+                .allow_abbreviations()
+                .parse()
+        };
+
+        assert_eq!(parse("stat").unwrap().id, "status");
+        assert!(matches!(
+            parse("st"),
+            Err(ParseError::AmbiguousCommand(abbrev, candidates))
+                if abbrev == "st" && candidates == ["stash", "status"]
+        ));
+        assert!(matches!(parse("stop"), Err(ParseError::InvalidCommand(_))));
+
+        let candidates = ["stash".to_string(), "status".to_string()];
+        let mut output = Vec::new();
+        let answer = |line: &'static str| Input::reader(line.as_bytes());
+        let picked = pick_candidate(&candidates, &answer("2\n"), &mut output);
+        assert_eq!(picked, Some(1));
+        assert_eq!(output, b"Did you mean: 1) stash 2) status\n> ");
+        assert_eq!(
+            pick_candidate(&candidates, &answer("stash\n"), &mut Vec::new()),
+            Some(0)
+        );
+        assert_eq!(
+            pick_candidate(&candidates, &answer("3\n"), &mut Vec::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_min_max_values() {
+        let parse = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(
+                    Command::new("tag")
+                        .flag(
+                            Flag::new("--name")
+                                .min_values(2)
+                                .max_values(3)
+                                .value_delimiter(','),
+                        )
+                        .flag(Flag::new("--note").max_values(1)),
+                )
+                .parse()
+        };
+
+        let cmd = parse("tag --name a,b --name c").unwrap();
+        assert_eq!(cmd.parsed_flags["--name"].values, vec!["a", "b", "c"]);
+        assert!(parse("tag").is_ok());
+        assert!(matches!(
+            parse("tag --name a"),
+            Err(ParseError::TooFewValues(id, 1, 2)) if id == "--name"
+        ));
+        assert!(matches!(
+            parse("tag --name a,b,c,d"),
+            Err(ParseError::TooManyValues(_, 4, 3))
+        ));
+        assert!(matches!(
+            parse("tag --note x --note y"),
+            Err(ParseError::TooManyValues(id, 2, 1)) if id == "--note"
+        ));
+    }
+
+    #[test]
+    fn test_exclusive_flag() {
+        let parse = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(
+                    Command::new("copy")
+                        .positional_arg(Positional::new("SRC"))
+                        .flag(Flag::new("--out").positional().required())
+                        .flag(Flag::new("--version").exclusive()),
+                )
+                .global_flag(Flag::new("--help").exclusive())
+                .global_flag(Flag::new("--token").required())
+                .parse()
+        };
+
+        let cmd = parse("--help copy --unknown").unwrap();
+        assert_eq!(cmd.id, "");
+        assert_eq!(cmd.exclusive_flag.as_deref(), Some("--help"));
+
+        let cmd = parse("copy --version").unwrap();
+        assert_eq!(cmd.id, "copy");
+        assert_eq!(cmd.exclusive_flag.as_deref(), Some("--version"));
+        // Flags of ancestors are not taken as the positional of a subcommand
+        let cmd = CliParser::parse_from(["remote", "add", "--version"])
             .command(
-                Command::new("command")
-                    .flag(Flag::new("--flag1"))
-                    .flag(Flag::new("--flag2").positional()),
+                Command::new("remote")
+                    .flag(Flag::new("--version").exclusive())
+                    .subcommand(Command::new("add").positional()),
             )
-            .command(Command::new("version"))
             .parse()
             .unwrap();
+        assert_eq!(cmd.exclusive_flag.as_deref(), Some("--version"));
+        assert_eq!(cmd.subcommand.unwrap().positional_val, None);
 
-        assert_eq!("command", cmd.id);
-        assert!(cmd.parsed_flags.get("--flag1").is_some());
-        assert!(cmd
-            .parsed_flags
-            .get("--flag2")
-            .is_some_and(|f| f.positional_val == Some("test".into())));
+        let cmd = parse("copy src --help").unwrap();
+        assert_eq!(cmd.parsed_positionals["SRC"].value.as_deref(), Some("src"));
 
-        // println!("{:?}", cmd);
+        assert!(matches!(
+            parse("copy src --out dest"),
+            Err(ParseError::MissingRequiredFlag(id)) if id == "--token"
+        ));
     }
+
     #[test]
-    fn test_positional_command_and_flags() {
-        let args = "with_positional something --test something --test2 somethingelse"
+    fn test_error_display() {
+        use std::error::Error;
+
+        let args = "serve --port x --port 80 --port y"
             .split(" ")
             .map(|s| s.to_string());
+        let err = CliParser::from_args(args)
+            .command(Command::new("serve").flag(Flag::new("--port").values_of_type::<u16>()))
+            .parse()
+            .unwrap_err();
 
-        let _parse_res = CliParser::from_args(args)
+        assert_eq!(
+            err.to_string(),
+            "invalid values for '--port': 'x': invalid digit found in string, \
+            'y': invalid digit found in string"
+        );
+        assert_eq!(
+            err.source().unwrap().to_string(),
+            "'x': invalid digit found in string"
+        );
+
+        let boxed: Box<dyn Error> = ParseError::InvalidCommand("dpeloy".into()).into();
+        assert_eq!(boxed.to_string(), "unknown command 'dpeloy'");
+    }
+
+    #[test]
+    fn test_streams() {
+        let errors = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let args = "build --out dist".split(" ").map(|s| s.to_string());
+        CliParser::from_args(args)
             .command(
-                Command::new("with_positional")
-                    .positional()
-                    .flag(Flag::new("--test").positional())
-                    .flag(Flag::new("--test2").positional()),
+                Command::new("build").flag(Flag::new("--out").positional().deprecated("use -o")),
             )
-            .command(Command::new("version"))
+            .warn_deprecated()
+            .streams(Streams {
+                errors: Stream::Writer(errors.clone()),
+                ..Default::default()
+            })
             .parse()
             .unwrap();
 
-        println!("{:?}", _parse_res)
+        assert_eq!(
+            String::from_utf8(errors.lock().unwrap().clone()).unwrap(),
+            "warning: --out is deprecated, use -o\n"
+        );
     }
 
     #[test]
-    fn test_positional_command() {
-        let args = "with_positional test".split(" ").map(|s| s.to_string());
+    fn test_error_context() {
+        let parser = |input: &'static str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args).command(
+                Command::new("deploy")
+                    .flag(Flag::new("--env").positional().required())
+                    .subcommand(Command::new("rollback")),
+            )
+        };
 
-        let _parse_res = CliParser::from_args(args)
-            .command(Command::new("with_positional").positional())
-            .command(Command::new("version"))
-            .parse()
-            .unwrap();
+        let mut unknown = parser("deploy rollback --froce");
+        let err = unknown.parse().unwrap_err();
+        let context = unknown.error_context().unwrap();
+        assert_eq!(context.command.as_deref(), Some("rollback"));
+        assert_eq!(context.token.as_deref(), Some("--froce"));
+        assert_eq!(
+            context.describe(&err),
+            "error in 'rollback': unknown flag '--froce' at position 2"
+        );
+
+        let mut missing = parser("deploy rollback");
+        let err = missing.parse().unwrap_err();
+        assert_eq!(
+            missing.error_context().unwrap().describe(&err),
+            "error in 'rollback': missing required flag '--env'"
+        );
+
+        let mut ok = parser("deploy --env prod");
+        assert!(ok.parse().is_ok());
+        assert!(ok.error_context().is_none());
     }
+
     #[test]
-    fn test_required_flag() {
-        let args = "help --test banaa".split(" ").map(|s| s.to_string());
+    fn test_tuple_flag() {
+        let parse = |input: &str| {
+            let args = input.split(" ").map(|s| s.to_string());
+            CliParser::from_args(args)
+                .command(Command::new("remap").flag(Flag::new("--key").tuple([
+                    ("FROM", ValueParser::new(char::from_str)),
+                    ("TO", ValueParser::new(u32::from_str)),
+                ])))
+                .on_duplicate(DuplicatePolicy::Collect)
+                .parse()
+        };
 
-        let parse_res = CliParser::from_args(args)
+        let cmd = parse("remap --key a 1 --key b 2").unwrap();
+        let key = &cmd.parsed_flags["--key"];
+        assert_eq!(key.get_tuple::<(char, u32)>(), Some(('b', 2)));
+        assert_eq!(key.get_tuple::<(u32, u32)>(), None);
+        assert_eq!(
+            cmd.to_args(),
+            ["remap", "--key", "a", "1", "--key", "b", "2"]
+        );
+
+        assert!(matches!(
+            parse("remap --key a"),
+            Err(ParseError::TooFewValues(id, 1, 2)) if id == "--key"
+        ));
+        assert!(matches!(
+            parse("remap --key a b"),
+            Err(ParseError::InvalidValues(_, errors)) if errors[0].value == "b"
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_trailing_args_untouched() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let raw = OsString::from_vec(vec![b'f', 0xff, b'o']);
+        let args = ["exec", "--verbose", "--", "--", "-v"].map(OsString::from);
+        let cmd = CliParser::from_args_os(args.into_iter().chain([raw.clone()]))
+            .lenient()
             .command(
-                Command::new("help")
-                    // .positional()
-                    .flag(Flag::new("test").positional().required()),
+                Command::new("exec")
+                    .flag(Flag::new("--verbose"))
+                    .trailing_args(),
             )
-            .command(Command::new("version"))
             .parse()
             .unwrap();
+
+        assert!(cmd.parsed_flags.contains_key("--verbose"));
+        assert_eq!(
+            cmd.trailing,
+            [OsString::from("--"), OsString::from("-v"), raw]
+        );
+        assert_eq!(
+            cmd.to_args(),
+            ["exec", "--verbose", "--", "--", "-v", "f\u{FFFD}o"]
+        );
     }
 
     #[test]
-    fn test_retrieval() {
-        let args = "help --test banana".split(" ").map(|s| s.to_string());
+    fn test_sandboxed() {
+        let parser = |input: &str| {
+            CliParser::parse_from(input.split(" "))
+                .command(
+                    Command::new("upload")
+                        .flag(Flag::new("--src").positional().existing_file())
+                        .flag(
+                            Flag::new("--name")
+                                .positional()
+                                .validate_with(validate::len(..4)),
+                        )
+                        .handler(|_| Ok(())),
+                )
+                .allow_external_subcommands()
+                .sandboxed()
+        };
 
-        let parse_res = CliParser::from_args(args)
-            .command(Command::new("help").flag(Flag::new("test").positional().required()))
-            .command(Command::new("version"))
-            .parse()
+        let cmd = parser("upload --src /missing/file").parse().unwrap();
+        assert_eq!(cmd.parsed_flags["--src"].value(), Some("/missing/file"));
+        assert!(matches!(
+            parser("upload --name toolong").parse(),
+            Err(ParseError::Validation(..))
+        ));
+        assert!(matches!(
+            parser("rm -rf").parse(),
+            Err(ParseError::InvalidCommand(c)) if c == "rm"
+        ));
+        assert!(matches!(parser("upload").run(), Err(RunError::Sandboxed)));
+    }
+
+    #[test]
+    fn test_parse_args_reuse() {
+        let mut parser = CliParser::from_args(std::iter::empty())
+            .command(Command::new("get").flag(Flag::new("--key").positional()))
+            .global_flag(Flag::new("--verbose"))
+            .lenient();
+
+        let cmd = parser
+            .parse_args(["--verbose", "get", "--key", "a", "stray"])
             .unwrap();
+        assert_eq!(cmd.parsed_flags["--key"].value(), Some("a"));
+        assert_eq!(parser.warnings.len(), 1);
 
-        assert_eq!(parse_res.id.as_str(), "help");
+        let cmd = parser.parse_args(["get", "--key", "b"]).unwrap();
+        assert_eq!(cmd.parsed_flags["--key"].value(), Some("b"));
+        assert!(!parser.parsed_flags.contains_key("--verbose"));
+        assert!(parser.warnings.is_empty());
 
-        assert_eq!(parse_res.positional_val, None);
-        assert!(parse_res.parsed_flags.get("--test").is_some());
+        assert!(parser.parse_args(["get", "--key"]).is_err());
+        assert_eq!(parser.error_context().unwrap().position, Some(1));
+    }
 
-        assert!(parse_res
-            .parsed_flags
-            .get("--test")
-            .is_some_and(|f| f.positional_val.as_ref().is_some_and(|v| v == "banana")));
-        // println!("{:?}", parse_res);
+    #[test]
+    fn test_parse_into() {
+        let mut parser = CliParser::from_args(std::iter::empty())
+            .command(Command::new("get").flag(Flag::new("--key").positional()));
+        let mut store: Vec<Matches> = Vec::new();
+
+        assert_eq!(
+            parser
+                .parse_into(["get", "--key", "a"], &mut store)
+                .unwrap(),
+            0
+        );
+        assert!(parser.parse_into(["put"], &mut store).is_err());
+        assert_eq!(
+            parser
+                .parse_into(["get", "--key", "b"], &mut store)
+                .unwrap(),
+            1
+        );
+
+        let keys: Vec<_> = store.iter().map(|m| m.value_of("key")).collect();
+        assert_eq!(keys, [Some("a"), Some("b")]);
     }
 
     #[test]
-    fn test_glob_and_local_flags() {
-        let args = "command --glob1 --local1 --glob2"
-            .split(" ")
-            .map(|s| s.to_string());
+    fn test_flag_indices() {
+        let cmd = CliParser::parse_from([
+            "--verbose",
+            "copy",
+            "src",
+            "--to",
+            "a",
+            "--verbose",
+            "--to",
+            "b",
+        ])
+        .command(
+            Command::new("copy")
+                .positional_arg(Positional::new("SRC"))
+                .flag(Flag::new("--to").positional()),
+        )
+        .global_flag(Flag::new("--verbose").counted())
+        .on_duplicate(DuplicatePolicy::LastWins)
+        .parse()
+        .unwrap();
 
-        let parse_res = CliParser::from_args(args)
-            .command(Command::new("command").flag(Flag::new("--local1").positional().required()))
-            .global_flag(Flag::new("--glob1"))
-            // .global_flag(Flag::new("--glob2"))
+        assert_eq!(cmd.parsed_flags["--verbose"].indices(), [0, 5]);
+        assert_eq!(cmd.parsed_flags["--to"].indices(), [3, 6]);
+        assert_eq!(cmd.parsed_flags["--to"].value(), Some("b"));
+        assert_eq!(cmd.parsed_positionals["SRC"].index(), Some(2));
+    }
+
+    #[test]
+    fn test_typed_flag() {
+        let retries = TypedFlag::<u8>::new("retries");
+        let parse = |input: &str| {
+            CliParser::parse_from(input.split(" "))
+                .command(Command::new("fetch"))
+                .global_flag(retries.flag().default_value("3"))
+                .parse_matches()
+        };
+
+        assert_eq!(retries.get_from(&parse("fetch").unwrap()), Some(&3));
+        assert_eq!(
+            retries.get_from(&parse("--retries 7 fetch").unwrap()),
+            Some(&7)
+        );
+        assert!(matches!(
+            parse("--retries many fetch"),
+            Err(ParseError::InvalidValues(id, _)) if id == "--retries"
+        ));
+    }
+
+    #[test]
+    fn test_flag_as_bare_id() {
+        let cmd = CliParser::parse_from(["serve", "--port", "8080", "--tag", "1", "--tag", "2"])
+            .command(
+                Command::new("serve")
+                    .flag(Flag::new("--port").positional().value_parser(u32::from_str))
+                    .flag(Flag::new("--tag").positional().value_parser(u8::from_str)),
+            )
+            .on_duplicate(DuplicatePolicy::Collect)
             .parse()
             .unwrap();
 
-        assert!(parse_res.parsed_flags.contains_key("--glob1"));
-        assert!(parse_res.parsed_flags.contains_key("--glob2"));
-        assert!(parse_res.parsed_flags.contains_key("--local1"));
+        assert_eq!(cmd.flag_as::<u32>("port"), Some(&8080));
+        assert_eq!(cmd.flag_as::<u32>("--port"), Some(&8080));
+        assert_eq!(cmd.flag_values_as::<u8>("tag"), [&1, &2]);
+    }
+
+    #[test]
+    fn test_parse_or_print() {
+        use std::sync::{Arc, Mutex};
+
+        let run = |args: &[&str]| {
+            let output = Arc::new(Mutex::new(Vec::new()));
+            let errors = Arc::new(Mutex::new(Vec::new()));
+            let code = CliParser::parse_from(args.iter().copied())
+                .bin_name("app")
+                .environment(Environment::dumb())
+                .command(Command::new("build"))
+                .with_standard_flags()
+                .help_on_empty()
+                .completions_command()
+                .streams(Streams {
+                    output: Stream::Writer(output.clone()),
+                    errors: Stream::Writer(errors.clone()),
+                    ..Default::default()
+                })
+                .parse_or_print()
+                .map(|cmd| cmd.id);
+            let text = |buf: Arc<Mutex<Vec<u8>>>| String::from_utf8(buf.lock().unwrap().clone());
+            (code, text(output).unwrap(), text(errors).unwrap())
+        };
+
+        assert_eq!(run(&["build"]), (Ok("build".into()), "".into(), "".into()));
+        let (code, output, errors) = run(&["build", "-h"]);
+        assert_eq!((code, errors.as_str()), (Err(0), ""));
+        assert!(output.starts_with("usage: app build [FLAGS]\n"));
+        let (code, output, errors) = run(&["help", "nope"]);
+        assert_eq!((code, output.as_str()), (Err(USAGE_EXIT_CODE), ""));
+        assert!(errors.starts_with("error: unknown command 'nope'\n\nusage: app <COMMAND>"));
+        let (_, _, errors) = run(&["build", "--nope"]);
+        assert!(errors.ends_with(concat!(
+            "\n\nusage: app build [FLAGS]\n\n",
+            "for more information, try '--help'\n"
+        )));
+        // Without a version set, the command is left to the caller
+        assert_eq!(run(&["--version"]).0, Ok("".into()));
+        let (code, output, errors) = run(&[]);
+        assert_eq!((code, output.as_str()), (Err(USAGE_EXIT_CODE), ""));
+        assert!(errors.starts_with("usage: app <COMMAND> [FLAGS]\n"));
+        let (code, output, _) = run(&["completions", "fish"]);
+        assert_eq!(code, Err(0));
+        assert!(output.starts_with("function __fish_app_command\n"));
+        // Without a version set, the exclusive flag skips the shell
+        assert_eq!(
+            run(&["completions", "--version"]).0,
+            Ok(COMPLETIONS_COMMAND.into())
+        );
+        let (_, _, errors) = run(&["completions", "tcsh"]);
+        assert!(errors.contains("'tcsh' must be one of: bash, zsh, fish, powershell,"));
     }
 
     #[test]
     #[ignore]
     /// THis does not work as intended atm
     fn test_parse_from_env() {
-        let args = args().collect::<Vec<_>>();
+        let _args = std::env::args().collect::<Vec<_>>();
 
-        let parse_res = CliParser::<Args>::new()
+        let _parse_res = CliParser::new()
             .command(
                 Command::new("help")
                     .positional()
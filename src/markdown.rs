@@ -0,0 +1,162 @@
+use crate::{help::HelpPage, CliParser, Command};
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Render the reference documentation of the whole command tree as Markdown,
+    /// e.g. to publish on a website.
+    ///
+    /// The program gets a top level section with its commands and global flags,
+    /// then every visible command gets a section, depth first and in display order,
+    /// with its description, usage, and tables of its arguments, flags, and subcommands
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let docs = CliParser::from_args(std::iter::empty())
+    ///     .bin_name("app")
+    ///     .command(
+    ///         Command::new("deploy")
+    ///             .about("Deploy the app")
+    ///             .flag(Flag::new("--env").positional().help("Target environment")),
+    ///     )
+    ///     .markdown();
+    ///
+    /// assert!(docs.starts_with("# app\n\nUsage: `app <COMMAND> [FLAGS]`\n"));
+    /// assert!(docs.contains("## app deploy\n\nDeploy the app\n"));
+    /// assert!(docs.contains("| `--env <VALUE>` | Target environment |\n"));
+    /// ```
+    pub fn markdown(&self) -> String {
+        let mut out = String::new();
+        let root = self
+            .help_page::<&str>(&[], true)
+            .expect("the program help is always found");
+        self.markdown_section(&mut out, "#", &[], root);
+        let mut path = Vec::new();
+        for command in self.visible_commands(&self.commands) {
+            self.markdown_command(&mut out, command, &mut path);
+        }
+        out
+    }
+
+    fn markdown_command<'a>(
+        &self,
+        out: &mut String,
+        command: &'a Command,
+        path: &mut Vec<&'a str>,
+    ) {
+        path.push(&command.id);
+        if let Some(page) = self.help_page(path, true) {
+            self.markdown_section(out, "##", path, page);
+        }
+        for sub in self.visible_commands(&command.subcommands) {
+            self.markdown_command(out, sub, path);
+        }
+        path.pop();
+    }
+
+    fn markdown_section(&self, out: &mut String, level: &str, path: &[&str], page: HelpPage) {
+        let mut title = vec![self.program_name()];
+        title.extend(path.iter().map(|id| id.to_string()));
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{} {}\n\n", level, title.join(" ")));
+        if let Some(description) = &page.description {
+            out.push_str(&format!("{}\n\n", description));
+        }
+        out.push_str(&format!(
+            "{}: `{}`\n",
+            capitalize(&page.usage_label),
+            page.usage
+        ));
+
+        if let Some(before) = &page.before {
+            out.push_str(&format!("\n{}\n", before));
+        }
+        for section in page.sections {
+            // Global flags are only listed once, for the program
+            if section.rows.is_empty() || (section.key == "global_flags" && !path.is_empty()) {
+                continue;
+            }
+            out.push_str(&format!("\n### {}\n\n", capitalize(&section.title)));
+            out.push_str("| Name | Description |\n| --- | --- |\n");
+            for (label, description) in section.rows {
+                out.push_str(&format!(
+                    "| `{}` | {} |\n",
+                    escape(&label),
+                    escape(&description)
+                ));
+            }
+        }
+        if let Some(after) = &page.after {
+            out.push_str(&format!("\n{}\n", after));
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Escape characters breaking a table cell
+fn escape(cell: &str) -> String {
+    cell.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_markdown() {
+        let docs = CliParser::from_args(std::iter::empty())
+            .bin_name("git")
+            .command(
+                Command::new("remote")
+                    .about("Manage remotes")
+                    .subcommand(
+                        Command::new("add")
+                            .positional_arg(Positional::new("NAME"))
+                            .flag(Flag::new("--mode").positional().help("fetch | push")),
+                    )
+                    .subcommand(Command::new("debug").hidden()),
+            )
+            .help_flag()
+            .markdown();
+
+        assert_eq!(
+            docs,
+            concat!(
+                "# git\n\n",
+                "Usage: `git <COMMAND> [FLAGS]`\n\n",
+                "### Commands\n\n",
+                "| Name | Description |\n| --- | --- |\n",
+                "| `remote` | Manage remotes |\n\n",
+                "### Global flags\n\n",
+                "| Name | Description |\n| --- | --- |\n",
+                "| `--help` |  |\n",
+                "| `-h` |  |\n",
+                "\n## git remote\n\n",
+                "Manage remotes\n\n",
+                "Usage: `git remote <COMMAND> [FLAGS]`\n\n",
+                "### Commands\n\n",
+                "| Name | Description |\n| --- | --- |\n",
+                "| `add` |  |\n",
+                "\n## git remote add\n\n",
+                "Usage: `git remote add <NAME> [FLAGS]`\n\n",
+                "### Arguments\n\n",
+                "| Name | Description |\n| --- | --- |\n",
+                "| `<NAME>` |  |\n\n",
+                "### Flags\n\n",
+                "| Name | Description |\n| --- | --- |\n",
+                "| `--mode <VALUE>` | fetch \\| push |\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    sync::Arc,
+};
+
+use crate::{
+    flag_id, ordered_flags,
+    value::{self, AnyValue},
+    Command, Flag, Positional, TupleValue, ValueSource, STDIN,
+};
+
+/// The parsed data of a matched [Command], without any of its definition,
+/// returned by [crate::CliParser::parse_matches]
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let args = "remote --verbose add origin".split(" ").map(|s| s.to_string());
+/// let matches = CliParser::from_args(args)
+///     .command(
+///         Command::new("remote")
+///             .flag(Flag::new("--verbose"))
+///             .subcommand(Command::new("add").positional()),
+///     )
+///     .parse_matches()
+///     .unwrap();
+///
+/// assert!(matches.flag("--verbose").is_some());
+/// assert_eq!(matches.subcommand().unwrap().positional(), Some("origin"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Matches {
+    /// Id of the matched command
+    pub id: String,
+    pub(crate) positional: Option<String>,
+    positional_os: Option<OsString>,
+    pub(crate) flags: HashMap<String, MatchedFlag>,
+    pub(crate) positionals: HashMap<String, MatchedPositional>,
+    pub(crate) subcommand: Option<Box<Matches>>,
+    pub(crate) external_args: Option<Vec<String>>,
+    pub(crate) exclusive_flag: Option<String>,
+    pub(crate) trailing: Vec<OsString>,
+    // Application state, see [crate::CliParser::context]
+    pub(crate) context: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl Matches {
+    /// The parsed positional value of the command
+    pub fn positional(&self) -> Option<&str> {
+        self.positional.as_deref()
+    }
+
+    /// The raw positional value, see [Command::positional_os]
+    pub fn positional_os(&self) -> Option<&OsStr> {
+        self.positional_os.as_deref()
+    }
+
+    /// If the positional value is the `-` sentinel, conventionally meaning stdin
+    pub fn is_stdin(&self) -> bool {
+        self.positional() == Some(STDIN)
+    }
+
+    /// A parsed flag, including flags filled in from a default.
+    /// The leading `--` of `id` may be left out
+    pub fn flag(&self, id: &str) -> Option<&MatchedFlag> {
+        self.flags.get(&flag_id(id))
+    }
+
+    /// If the flag `id` was passed, see [Command::is_present]
+    pub fn is_present(&self, id: &str) -> bool {
+        self.flag(id).is_some_and(MatchedFlag::is_present)
+    }
+
+    /// The value of the flag `id`, including a default, see [Flag::value]
+    pub fn value_of(&self, id: &str) -> Option<&str> {
+        self.flag(id).and_then(MatchedFlag::value)
+    }
+
+    /// All values of the flag `id`, empty if it was not parsed
+    pub fn values_of(&self, id: &str) -> &[String] {
+        self.flag(id).map_or(&[], |f| &f.values)
+    }
+
+    /// Number of times the flag `id` was passed, see [Flag::count]
+    pub fn occurrences_of(&self, id: &str) -> usize {
+        self.flag(id).map_or(0, MatchedFlag::count)
+    }
+
+    /// All parsed flags, by id
+    pub fn flags(&self) -> &HashMap<String, MatchedFlag> {
+        &self.flags
+    }
+
+    /// Every time a flag was passed, with its index in the input args, in the order passed,
+    /// see [Command::ordered_flags]
+    pub fn ordered_flags(&self) -> Vec<(usize, &MatchedFlag)> {
+        ordered_flags(&self.flags, MatchedFlag::indices)
+    }
+
+    /// Get the typed value of a parsed flag, see [Flag::get]
+    pub fn flag_as<T: Any>(&self, id: &str) -> Option<&T> {
+        self.flag(id).and_then(|f| f.get::<T>())
+    }
+
+    /// A parsed named positional
+    pub fn positional_arg(&self, id: &str) -> Option<&MatchedPositional> {
+        self.positionals.get(id)
+    }
+
+    /// Get the typed value of a parsed named positional, see [Positional::get]
+    pub fn positional_as<T: Any>(&self, id: &str) -> Option<&T> {
+        self.positional_arg(id).and_then(|p| p.get::<T>())
+    }
+
+    /// The matched subcommand
+    pub fn subcommand(&self) -> Option<&Matches> {
+        self.subcommand.as_deref()
+    }
+
+    /// Ids of the matched command and its matched subcommands, outermost first,
+    /// see [Command::path]
+    pub fn path(&self) -> Vec<&str> {
+        let mut path = vec![self.id.as_str()];
+        let mut matches = self;
+        while let Some(sub) = matches.subcommand() {
+            path.push(&sub.id);
+            matches = sub;
+        }
+        path
+    }
+
+    /// The name and args of an external command, see [Command::external]
+    pub fn external(&self) -> Option<(&str, &[String])> {
+        self.external_args
+            .as_deref()
+            .map(|args| (self.id.as_str(), args))
+    }
+
+    /// The untouched args following the command, see [Command::trailing_args]
+    pub fn trailing(&self) -> &[OsString] {
+        &self.trailing
+    }
+
+    /// The exclusive flag that ended parsing early, see [Flag::exclusive]
+    pub fn exclusive_flag(&self) -> Option<&str> {
+        self.exclusive_flag.as_deref()
+    }
+
+    /// The application state registered with [crate::CliParser::context],
+    /// `None` if there is none or it is not a `T`
+    pub fn context<T: Any>(&self) -> Option<&T> {
+        self.context.as_deref().and_then(|c| c.downcast_ref::<T>())
+    }
+}
+
+/// Where [crate::CliParser::parse_into] keeps parsed data, e.g. an arena or slab
+/// holding the results of parsing millions of stored command lines.
+///
+/// The [Matches] of each parse are handed over by value, so a store can keep them as
+/// they are, like `Vec<Matches>` does, or flatten them into its own layout and drop them
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// // Values of all parses in one buffer, instead of maps per parse
+/// #[derive(Default)]
+/// struct Columns {
+///     commands: Vec<String>,
+///     values: Vec<String>,
+/// }
+///
+/// impl MatchesStore for Columns {
+///     type Key = usize;
+///
+///     fn store(&mut self, matches: Matches) -> usize {
+///         self.values.extend(matches.values_of("--env").iter().cloned());
+///         self.commands.push(matches.id);
+///         self.commands.len() - 1
+///     }
+/// }
+///
+/// let mut parser = CliParser::parse_from(std::iter::empty::<String>())
+///     .command(Command::new("deploy").flag(Flag::new("--env").positional()));
+/// let mut columns = Columns::default();
+/// for line in [["deploy", "--env", "prod"], ["deploy", "--env", "test"]] {
+///     parser.parse_into(line, &mut columns).unwrap();
+/// }
+/// assert_eq!(columns.values, ["prod", "test"]);
+/// ```
+pub trait MatchesStore {
+    /// Refers to the stored data of one parse
+    type Key;
+
+    /// Keep the parsed data of one parse
+    fn store(&mut self, matches: Matches) -> Self::Key;
+}
+
+/// Keeps every [Matches] as is, keyed by index
+impl MatchesStore for Vec<Matches> {
+    type Key = usize;
+
+    fn store(&mut self, matches: Matches) -> usize {
+        self.push(matches);
+        self.len() - 1
+    }
+}
+
+/// Keeps only the parsed data, dropping the definition
+impl From<Command> for Matches {
+    fn from(command: Command) -> Self {
+        let flags = command.parsed_flags.into_iter();
+        let positionals = command.parsed_positionals.into_iter();
+        Self {
+            id: command.id,
+            positional: command.positional_val,
+            positional_os: command.positional_os,
+            flags: flags.map(|(id, f)| (id, f.into())).collect(),
+            positionals: positionals.map(|(id, p)| (id, p.into())).collect(),
+            subcommand: command.subcommand.map(|sub| Box::new((*sub).into())),
+            external_args: command.external_args,
+            exclusive_flag: command.exclusive_flag,
+            trailing: command.trailing,
+            context: None,
+        }
+    }
+}
+
+/// The parsed data of a [Flag], see [Matches::flag]
+#[derive(Debug, Clone, Default)]
+pub struct MatchedFlag {
+    /// Id of the flag
+    pub id: String,
+    /// All parsed values, see [Flag::values]
+    pub values: Vec<String>,
+    // The parsed value of a value flag
+    value: Option<String>,
+    // Raw parsed values
+    os_values: Vec<OsString>,
+    // Values converted by the [crate::ValueParser] of the flag
+    typed_values: Vec<AnyValue>,
+    // Number of typed values taken by each occurrence of a [Flag::tuple] flag
+    arity: usize,
+    // Index in the input args of each time the flag was passed
+    indices: Vec<usize>,
+    /// Number of times the flag was passed
+    pub occurrences: usize,
+    /// Where the parsed values came from
+    pub source: ValueSource,
+}
+
+impl MatchedFlag {
+    /// If the flag was passed, see [Flag::is_present]
+    pub fn is_present(&self) -> bool {
+        self.occurrences > 0
+    }
+
+    /// The parsed value, see [Flag::value]
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// The raw parsed value, see [Flag::value_os]
+    pub fn value_os(&self) -> Option<&OsStr> {
+        self.value
+            .as_ref()
+            .and(self.os_values.last())
+            .map(|v| v.as_os_str())
+    }
+
+    /// All raw parsed values, see [Flag::values_os]
+    pub fn values_os(&self) -> &[OsString] {
+        &self.os_values
+    }
+
+    /// If the value is the `-` sentinel, conventionally meaning stdin
+    pub fn is_stdin(&self) -> bool {
+        self.value() == Some(STDIN)
+    }
+
+    /// Number of times the flag was passed, see [Flag::count]
+    pub fn count(&self) -> usize {
+        self.occurrences
+    }
+
+    /// Index in the input args of each time the flag was passed, see [Flag::indices]
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Get the parsed value as `T`, see [Flag::get]
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.typed_values.last().and_then(|v| v.downcast_ref::<T>())
+    }
+
+    /// Get the typed values of the last occurrence as a tuple, see [Flag::get_tuple]
+    pub fn get_tuple<T: TupleValue>(&self) -> Option<T> {
+        value::last_tuple(&self.typed_values, self.arity)
+    }
+
+    /// Get all parsed values as `T`, see [Flag::get_all]
+    pub fn get_all<T: Any>(&self) -> Vec<&T> {
+        self.typed_values
+            .iter()
+            .filter_map(|v| v.downcast_ref::<T>())
+            .collect()
+    }
+}
+
+impl From<Flag> for MatchedFlag {
+    fn from(flag: Flag) -> Self {
+        Self {
+            arity: flag.tuple.len(),
+            id: flag.id,
+            values: flag.values,
+            value: flag.positional_val,
+            os_values: flag.os_values,
+            typed_values: flag.typed_values,
+            indices: flag.indices,
+            occurrences: flag.occurrences,
+            source: flag.source,
+        }
+    }
+}
+
+/// The parsed data of a [Positional], see [Matches::positional_arg]
+#[derive(Debug, Clone, Default)]
+pub struct MatchedPositional {
+    /// Id of the positional
+    pub id: String,
+    /// The parsed value
+    pub value: Option<String>,
+    // Raw parsed value
+    value_os: Option<OsString>,
+    // Value converted by the [crate::ValueParser] of the positional
+    typed_value: Option<AnyValue>,
+    // Index of the value in the input args
+    index: Option<usize>,
+}
+
+impl MatchedPositional {
+    /// The raw parsed value, see [Positional::value_os]
+    pub fn value_os(&self) -> Option<&OsStr> {
+        self.value_os.as_deref()
+    }
+
+    /// If the value is the `-` sentinel, conventionally meaning stdin
+    pub fn is_stdin(&self) -> bool {
+        self.value.as_deref() == Some(STDIN)
+    }
+
+    /// Index of the value in the input args
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Get the parsed value as `T`, see [Positional::get]
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.typed_value
+            .as_ref()
+            .and_then(|v| v.downcast_ref::<T>())
+    }
+}
+
+impl From<Positional> for MatchedPositional {
+    fn from(positional: Positional) -> Self {
+        Self {
+            id: positional.id,
+            value: positional.value,
+            value_os: positional.value_os,
+            typed_value: positional.typed_value,
+            index: positional.index,
+        }
+    }
+}
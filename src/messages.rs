@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use crate::{Limit, ParseError};
+
+/// The English messages, by key. Keys of errors are their [ParseError::kind],
+/// except the two [ParseError::LimitExceeded] limits
+const ENGLISH: &[(&str, &str)] = &[
+    ("None", "unknown error"),
+    ("MissingPositional", "flag is missing its value"),
+    ("NoCommands", "no commands are defined"),
+    ("InvalidCommand", "unknown command '{0}'"),
+    ("InvalidFlag", "unknown flag '{0}'"),
+    ("ExpectedCommand", "expected a command"),
+    ("ExpectedPositional", "expected a positional value"),
+    ("ExpectedFlag", "expected a flag"),
+    ("RequiredPositional", "missing a required positional value"),
+    ("MissingRequiredFlag", "missing required flag '{0}'"),
+    ("InvalidValue", "invalid value for '{0}': {1}"),
+    ("InvalidValues", "invalid values for '{0}': {1}"),
+    ("DuplicateFlag", "flag '{0}' was passed more than once"),
+    ("InvalidUnicode", "argument {0} is not valid unicode"),
+    ("MissingCapability", "'{0}' requires the '{1}' capability"),
+    ("LimitExceeded.Args", "too many arguments, got {0}"),
+    (
+        "LimitExceeded.ArgLength",
+        "argument too long, got {0} bytes",
+    ),
+    ("Validation", "invalid value for '{0}': {1}"),
+    ("AmbiguousCommand", "ambiguous command '{0}', could be: {1}"),
+    ("TooFewValues", "'{0}' takes at least {2} values, got {1}"),
+    ("TooManyValues", "'{0}' takes at most {2} values, got {1}"),
+    ("UnterminatedQuote", "unterminated quote in '{0}'"),
+    ("InvalidConfigFile", "invalid config file '{0}': {1}"),
+    (
+        "InvalidConfigValue",
+        "invalid value for '{1}' in '{0}': {2}",
+    ),
+    ("error", "error"),
+    ("error.command", " in '{0}'"),
+    ("error.position", " at position {0}"),
+    ("error.suggestion", ", did you mean '{0}'?"),
+    ("warning", "warning"),
+    ("deprecated", "{0} is deprecated, {1}"),
+    ("required", "required"),
+    ("default", "default"),
+    ("usage", "usage"),
+    ("arguments", "arguments"),
+    ("flags", "flags"),
+    ("commands", "commands"),
+    ("global_flags", "global flags"),
+    ("hint.help", "for more information, try '{0}'"),
+];
+
+/// A catalog of user-facing messages, overriding the English ones per locale,
+/// set with [crate::CliParser::messages].
+///
+/// Messages are looked up by key in the exact locale, like `nb_NO`, then its
+/// language, like `nb`, then in English. Keys of errors are their [ParseError::kind],
+/// except `LimitExceeded.Args` and `LimitExceeded.ArgLength`, and `{0}`, `{1}`, ...
+/// are replaced by the values in the order of the variant fields.
+/// The other keys are `error`, `error.command`, `error.position`, `error.suggestion`, `warning`,
+/// `deprecated`, `required`, `default`, and the help headings `usage`, `arguments`,
+/// `flags`, `commands`, and `global_flags`, and `hint.help` naming the help flag after errors
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let messages = Messages::new()
+///     .message("nb", "error", "feil")
+///     .message("nb", "InvalidFlag", "ukjent flagg '{0}'");
+///
+/// let error = ParseError::InvalidFlag("--froce".into());
+/// assert_eq!(messages.error("nb_NO", &error), "ukjent flagg '--froce'");
+/// assert_eq!(messages.error("C", &error), "unknown flag '--froce'");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Messages {
+    // Overridden templates, by locale and key
+    locales: HashMap<String, HashMap<String, String>>,
+}
+
+impl Messages {
+    /// Create a catalog with only the English messages
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override the message `key` in `locale` with `template`
+    pub fn message(mut self, locale: &str, key: &str, template: &str) -> Self {
+        self.locales
+            .entry(locale.into())
+            .or_default()
+            .insert(key.into(), template.into());
+        self
+    }
+
+    /// The template of `key` in `locale`, empty if the key is unknown
+    pub fn template(&self, locale: &str, key: &str) -> &str {
+        let language = locale.split(['_', '-']).next().unwrap_or_default();
+        [locale, language]
+            .iter()
+            .find_map(|l| self.locales.get(*l)?.get(key))
+            .map(String::as_str)
+            .or_else(|| ENGLISH.iter().find(|(k, _)| *k == key).map(|(_, t)| *t))
+            .unwrap_or_default()
+    }
+
+    /// The message `key` in `locale`, with `{i}` replaced by `args[i]`
+    pub fn format(&self, locale: &str, key: &str, args: &[&str]) -> String {
+        let mut message = self.template(locale, key).to_string();
+        for (i, arg) in args.iter().enumerate() {
+            message = message.replace(&format!("{{{}}}", i), arg);
+        }
+        message
+    }
+
+    /// The message of `error` in `locale`
+    pub fn error(&self, locale: &str, error: &ParseError) -> String {
+        let (key, args) = error_args(error);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.format(locale, key, &args)
+    }
+}
+
+/// The message key of `error`, and the values replacing its placeholders
+fn error_args(error: &ParseError) -> (&'static str, Vec<String>) {
+    let args = match error {
+        ParseError::InvalidCommand(id)
+        | ParseError::InvalidFlag(id)
+        | ParseError::MissingRequiredFlag(id)
+        | ParseError::DuplicateFlag(id)
+        | ParseError::UnterminatedQuote(id) => vec![id.clone()],
+        ParseError::InvalidValue(id, reason)
+        | ParseError::MissingCapability(id, reason)
+        | ParseError::Validation(id, reason)
+        | ParseError::InvalidConfigFile(id, reason) => vec![id.clone(), reason.clone()],
+        ParseError::InvalidConfigValue(path, key, reason) => {
+            vec![path.clone(), key.clone(), reason.clone()]
+        }
+        ParseError::InvalidValues(id, errors) => {
+            let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            vec![id.clone(), errors.join(", ")]
+        }
+        ParseError::InvalidUnicode(arg) => vec![format!("{:?}", arg)],
+        ParseError::LimitExceeded(Limit::Args(n)) => {
+            return ("LimitExceeded.Args", vec![n.to_string()])
+        }
+        ParseError::LimitExceeded(Limit::ArgLength(n)) => {
+            return ("LimitExceeded.ArgLength", vec![n.to_string()])
+        }
+        ParseError::AmbiguousCommand(abbrev, candidates) => {
+            vec![abbrev.clone(), candidates.join(", ")]
+        }
+        ParseError::TooFewValues(id, received, bound)
+        | ParseError::TooManyValues(id, received, bound) => {
+            vec![id.clone(), received.to_string(), bound.to_string()]
+        }
+        ParseError::None
+        | ParseError::MissingPositional
+        | ParseError::NoCommands
+        | ParseError::ExpectedCommand
+        | ParseError::ExpectedPositional
+        | ParseError::ExpectedFlag
+        | ParseError::RequiredPositional => Vec::new(),
+    };
+    (error.kind(), args)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        let messages = Messages::new()
+            .message(
+                "de",
+                "TooFewValues",
+                "'{0}' braucht mindestens {2} Werte, nicht {1}",
+            )
+            .message("de_AT", "TooFewValues", "servus {0}")
+            .message("de", "required", "erforderlich");
+
+        let error = ParseError::TooFewValues("--tag".into(), 1, 2);
+        assert_eq!(
+            messages.error("de_DE", &error),
+            "'--tag' braucht mindestens 2 Werte, nicht 1"
+        );
+        assert_eq!(messages.error("de_AT", &error), "servus --tag");
+        assert_eq!(messages.error("fr_FR", &error), error.to_string());
+        assert_eq!(messages.template("de-CH", "required"), "erforderlich");
+        assert_eq!(messages.template("de", "unknown"), "");
+        assert_eq!(
+            messages.error("de", &ParseError::LimitExceeded(Limit::ArgLength(9))),
+            "argument too long, got 9 bytes"
+        );
+    }
+}
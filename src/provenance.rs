@@ -0,0 +1,172 @@
+use std::{collections::HashMap, fmt::Write};
+
+use crate::Flag;
+
+/// Where the value of a parsed [Flag] came from.
+///
+/// A flag passed on the command line takes precedence over its environment variable,
+/// which takes precedence over its config key, which takes precedence over its default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValueSource {
+    /// Passed on the command line
+    #[default]
+    Cli,
+    /// Read from the variable of [Flag::env]
+    Env,
+    /// Read from the key of [Flag::config_key], see [crate::CliParser::config_value]
+    Config,
+    /// Filled in from [Flag::default_value]
+    Default,
+}
+
+impl ValueSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueSource::Cli => "cli",
+            ValueSource::Env => "env",
+            ValueSource::Config => "config",
+            ValueSource::Default => "default",
+        }
+    }
+}
+
+/// Output format of the `--show-config` global, see [crate::CliParser::show_config]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// One resolved setting of the effective configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigEntry {
+    /// Id of the flag
+    pub id: String,
+    /// Resolved value, `None` for flags without a value
+    pub value: Option<String>,
+    /// Source of the value, `None` if the flag was not set at all
+    pub source: Option<ValueSource>,
+}
+
+/// Collect an entry for every flag in `recipes`, looking up the resolved values in `parsed`
+pub(crate) fn entries<'a>(
+    recipes: impl Iterator<Item = &'a Flag>,
+    parsed: &HashMap<String, Flag>,
+) -> Vec<ConfigEntry> {
+    let mut entries: Vec<ConfigEntry> = recipes
+        .map(|recipe| match parsed.get(&recipe.id) {
+            Some(flag) => ConfigEntry {
+                id: recipe.id.clone(),
+                value: flag.positional_val.clone(),
+                source: Some(flag.source),
+            },
+            None => ConfigEntry {
+                id: recipe.id.clone(),
+                value: None,
+                source: None,
+            },
+        })
+        .collect();
+    entries.sort_by(|a, b| a.id.cmp(&b.id));
+    entries
+}
+
+/// The flags of one command in the parsed path, see [render]
+pub(crate) struct Scope {
+    // Ids of the command and its parents, like `remote add`
+    pub(crate) path: String,
+    pub(crate) entries: Vec<ConfigEntry>,
+}
+
+/// Render the effective configuration of the global flags and the flags of
+/// every command in the parsed path, outermost first
+pub(crate) fn render(globals: &[ConfigEntry], scopes: &[Scope], format: ConfigFormat) -> String {
+    match format {
+        ConfigFormat::Table => render_table(globals, scopes),
+        ConfigFormat::Json => render_json(globals, scopes),
+    }
+}
+
+fn render_table(globals: &[ConfigEntry], scopes: &[Scope]) -> String {
+    let locals = scopes
+        .iter()
+        .flat_map(|scope| scope.entries.iter().map(|e| (scope.path.as_str(), e)));
+    let rows = globals.iter().map(|e| ("global", e)).chain(locals);
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<12} {:<20} {:<20} SOURCE",
+        "SCOPE", "SETTING", "VALUE"
+    );
+    for (scope, entry) in rows {
+        let _ = writeln!(
+            out,
+            "{:<12} {:<20} {:<20} {}",
+            scope,
+            entry.id,
+            entry.value.as_deref().unwrap_or("-"),
+            entry.source.map(|s| s.as_str()).unwrap_or("unset"),
+        );
+    }
+    out
+}
+
+fn render_json(globals: &[ConfigEntry], scopes: &[Scope]) -> String {
+    let locals: Vec<String> = scopes
+        .iter()
+        .map(|scope| {
+            format!(
+                "{{\"command\":{},\"flags\":{}}}",
+                json_str(&scope.path),
+                json_entries(&scope.entries)
+            )
+        })
+        .collect();
+    let command = scopes.last().map_or("", |scope| scope.path.as_str());
+    format!(
+        "{{\"command\":{},\"global\":{},\"local\":[{}]}}",
+        json_str(command),
+        json_entries(globals),
+        locals.join(",")
+    )
+}
+
+fn json_entries(entries: &[ConfigEntry]) -> String {
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|e| {
+            format!(
+                "{{\"id\":{},\"value\":{},\"source\":{}}}",
+                json_str(&e.id),
+                e.value.as_deref().map(json_str).unwrap_or("null".into()),
+                e.source
+                    .map(|s| json_str(s.as_str()))
+                    .unwrap_or("null".into()),
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
+}
+
+/// Quote and escape a string as a JSON string literal
+pub(crate) fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,176 @@
+//! [serde::Serialize] for parse results, behind the `serde` feature.
+//!
+//! Only parsed data is serialized, never any of the definition. Maps are
+//! sorted by id so the output is stable, and raw values are converted lossily
+//!
+//! ## Example
+//! ```
+//! # use cli_parser::*;
+//! let matches = CliParser::parse_from(["deploy", "--env", "prod"])
+//!     .command(Command::new("deploy").flag(Flag::new("--env").positional()))
+//!     .parse_matches()
+//!     .unwrap();
+//!
+//! let json = serde_json::to_value(&matches).unwrap();
+//! assert_eq!(json["flags"]["--env"]["values"][0], "prod");
+//! ```
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+};
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::{Command, Flag, MatchedFlag, MatchedPositional, Matches, Positional, ValueSource};
+
+impl Serialize for Matches {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Parsed {
+            id: &self.id,
+            positional: &self.positional,
+            flags: &self.flags,
+            positionals: &self.positionals,
+            subcommand: &self.subcommand,
+            external_args: &self.external_args,
+            trailing: &self.trailing,
+            exclusive_flag: &self.exclusive_flag,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Serialized the same as its [Matches], without building them
+impl Serialize for Command {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Parsed {
+            id: &self.id,
+            positional: &self.positional_val,
+            flags: &self.parsed_flags,
+            positionals: &self.parsed_positionals,
+            subcommand: &self.subcommand,
+            external_args: &self.external_args,
+            trailing: &self.trailing,
+            exclusive_flag: &self.exclusive_flag,
+        }
+        .serialize(serializer)
+    }
+}
+
+// The parsed data shared by [Matches] and [Command], borrowed from either
+struct Parsed<'a, F, P, C> {
+    id: &'a str,
+    positional: &'a Option<String>,
+    flags: &'a HashMap<String, F>,
+    positionals: &'a HashMap<String, P>,
+    subcommand: &'a Option<Box<C>>,
+    external_args: &'a Option<Vec<String>>,
+    trailing: &'a [OsString],
+    exclusive_flag: &'a Option<String>,
+}
+
+impl<F: Serialize, P: Serialize, C: Serialize> Serialize for Parsed<'_, F, P, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let flags: BTreeMap<&str, &F> = self.flags.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        let positionals: BTreeMap<&str, &P> = self
+            .positionals
+            .iter()
+            .map(|(k, v)| (k.as_str(), v))
+            .collect();
+        let trailing: Vec<String> = self
+            .trailing
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+
+        let mut state = serializer.serialize_struct("Matches", 8)?;
+        state.serialize_field("id", self.id)?;
+        state.serialize_field("positional", self.positional)?;
+        state.serialize_field("flags", &flags)?;
+        state.serialize_field("positionals", &positionals)?;
+        state.serialize_field("subcommand", self.subcommand)?;
+        state.serialize_field("external_args", self.external_args)?;
+        state.serialize_field("trailing", &trailing)?;
+        state.serialize_field("exclusive_flag", self.exclusive_flag)?;
+        state.end()
+    }
+}
+
+impl Serialize for MatchedFlag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_flag(serializer, &self.values, self.occurrences, self.source)
+    }
+}
+
+/// Serialized as its value
+impl Serialize for MatchedPositional {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+impl Serialize for Flag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_flag(serializer, &self.values, self.occurrences, self.source)
+    }
+}
+
+fn serialize_flag<S: Serializer>(
+    serializer: S,
+    values: &[String],
+    occurrences: usize,
+    source: ValueSource,
+) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_struct("Flag", 3)?;
+    state.serialize_field("values", values)?;
+    state.serialize_field("occurrences", &occurrences)?;
+    state.serialize_field("source", &source)?;
+    state.end()
+}
+
+/// Serialized as its value
+impl Serialize for Positional {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+impl Serialize for ValueSource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_serialize_matches() {
+        let cmd = CliParser::parse_from(["remote", "--verbose", "add", "origin", "--", "-x"])
+            .command(
+                Command::new("remote")
+                    .flag(Flag::new("--verbose"))
+                    .subcommand(
+                        Command::new("add")
+                            .positional_arg(Positional::new("NAME"))
+                            .flag(Flag::new("--fetch").positional().default_value("yes"))
+                            .trailing_args(),
+                    ),
+            )
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&cmd).unwrap(),
+            concat!(
+                r#"{"id":"remote","positional":null,"#,
+                r#""flags":{"--verbose":{"values":[],"occurrences":1,"source":"cli"}},"#,
+                r#""positionals":{},"subcommand":{"id":"add","positional":null,"#,
+                r#""flags":{"--fetch":{"values":["yes"],"occurrences":0,"source":"default"}},"#,
+                r#""positionals":{"NAME":"origin"},"subcommand":null,"external_args":null,"#,
+                r#""trailing":["-x"],"exclusive_flag":null},"#,
+                r#""external_args":null,"trailing":[],"exclusive_flag":null}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env::var,
+    path::PathBuf,
+};
+#[cfg(feature = "toml")]
+use std::{fs::read_to_string, path::Path};
+
+use crate::{CliParser, Command, Flag, ParseError, ParseWarning, ValueSource};
+
+/// Values by config key, and the file they were read from,
+/// `None` for values set with [CliParser::config_value]
+pub(crate) type Config = HashMap<String, (Vec<String>, Option<PathBuf>)>;
+
+/// Cached lookups of environment variables and config files, so a parser
+/// reused for many invocations only reads each source once.
+///
+/// Cleared with [crate::CliParser::invalidate_sources]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceCache {
+    // Value of each variable looked up, `None` if unset or not unicode
+    vars: HashMap<String, Option<String>>,
+    // Content of each file read, `None` if it could not be read
+    files: HashMap<PathBuf, Option<String>>,
+}
+
+impl SourceCache {
+    /// The value of the environment variable `name`
+    pub(crate) fn var(&mut self, name: &str) -> Option<&str> {
+        self.vars
+            .entry(name.into())
+            .or_insert_with(|| var(name).ok())
+            .as_deref()
+    }
+
+    /// The content of the file at `path`
+    #[cfg(feature = "toml")]
+    pub(crate) fn read(&mut self, path: &Path) -> Option<&str> {
+        self.files
+            .entry(path.into())
+            .or_insert_with(|| read_to_string(path).ok())
+            .as_deref()
+    }
+
+    /// Forget all cached lookups
+    pub(crate) fn clear(&mut self) {
+        self.vars.clear();
+        self.files.clear();
+    }
+
+    /// The values of the TOML file at `path` by key, with the keys of tables prefixed
+    /// by the table, like `remote.add.tags` for `tags` in `[remote.add]`.
+    /// Empty if the file can not be read
+    #[cfg(feature = "toml")]
+    pub(crate) fn toml(&mut self, path: &Path) -> Result<Vec<(String, Vec<String>)>, ParseError> {
+        let invalid =
+            |reason: String| ParseError::InvalidConfigFile(path.display().to_string(), reason);
+        let table: toml::Table = match self.read(path) {
+            Some(content) => content
+                .parse()
+                .map_err(|e: toml::de::Error| invalid(e.message().trim().into()))?,
+            None => return Ok(Vec::new()),
+        };
+        let mut values = Vec::new();
+        flatten("", &table, &mut values).map_err(invalid)?;
+        Ok(values)
+    }
+}
+
+/// Collect the values of `table` into `out`, with their keys prefixed by `prefix`
+#[cfg(feature = "toml")]
+fn flatten(
+    prefix: &str,
+    table: &toml::Table,
+    out: &mut Vec<(String, Vec<String>)>,
+) -> Result<(), String> {
+    for (key, value) in table {
+        let key = format!("{}{}", prefix, key);
+        match value {
+            toml::Value::Table(table) => flatten(&format!("{}.", key), table, out)?,
+            toml::Value::Array(items) => {
+                let values = items
+                    .iter()
+                    .map(|item| {
+                        scalar(item).ok_or_else(|| format!("'{}' holds nested values", key))
+                    })
+                    .collect::<Result<_, _>>()?;
+                out.push((key, values));
+            }
+            value => out.push((key, scalar(value).into_iter().collect())),
+        }
+    }
+    Ok(())
+}
+
+/// `value` as a flag value, `None` for arrays and tables
+#[cfg(feature = "toml")]
+fn scalar(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Array(_) | toml::Value::Table(_) => None,
+        value => Some(value.to_string()),
+    }
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Read config values from the TOML file at `path` when parsing, behind the `toml`
+    /// feature. Tables hold the values of commands, and keys are the [Flag::config_key]
+    /// of their flags, a missing file is skipped.
+    ///
+    /// Files added later override earlier ones, and values set with
+    /// [CliParser::config_value] override all files. Values failing the validation or
+    /// conversion of their flag fail with [ParseError::InvalidConfigValue]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let path = std::env::temp_dir().join("cli_parser_doc_config.toml");
+    /// std::fs::write(&path, "verbose = true\n\n[deploy]\nregions = [\"eu\", \"us\"]\n").unwrap();
+    ///
+    /// let cmd = CliParser::parse_from(["deploy"])
+    ///     .command(Command::new("deploy").flag(Flag::new("--region").positional().config_key("regions")))
+    ///     .global_flag(Flag::new("--verbose").config_key("verbose"))
+    ///     .config_file(&path)
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert!(cmd.is_present("--verbose"));
+    /// assert_eq!(cmd.parsed_flags["--region"].values, ["eu", "us"]);
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn config_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.config_files.push(path.as_ref().into());
+        self
+    }
+
+    /// Register the global flag `id` naming a TOML file to read config values from,
+    /// like `--config app.toml`, behind the `toml` feature. The file is read once the
+    /// args are parsed, before the values of flags that were not passed are resolved,
+    /// so it may also set required flags.
+    ///
+    /// The file overrides every [CliParser::config_file], unlike those it must exist.
+    /// [CliParser::sandboxed] parsers do not read it
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let path = std::env::temp_dir().join("cli_parser_doc_config_flag.toml");
+    /// std::fs::write(&path, "[deploy]\ntoken = \"secret\"\n").unwrap();
+    ///
+    /// let cmd = CliParser::parse_from(["deploy", "--config", path.to_str().unwrap()])
+    ///     .command(Command::new("deploy").flag(Flag::new("--token").positional().required().config_key("token")))
+    ///     .config_flag("--config")
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(cmd.parsed_flags["--token"].value(), Some("secret"));
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn config_flag(mut self, id: &str) -> Self {
+        self.config_flag = Some(crate::flag_id(id));
+        self.global_flag(
+            Flag::new(id)
+                .positional()
+                .value_hint(crate::ValueHint::FilePath)
+                .help("Read config values from a TOML file"),
+        )
+    }
+
+    /// The values of every [CliParser::config_file], then of the file passed to the
+    /// [CliParser::config_flag], under the [CliParser::config_value] values.
+    /// Files are not read by [CliParser::sandboxed] parsers
+    pub(crate) fn config(&mut self) -> Result<Config, ParseError> {
+        #[allow(unused_mut)]
+        let mut config = Config::new();
+        #[cfg(feature = "toml")]
+        if !self.sandboxed {
+            let passed = self
+                .config_flag
+                .as_ref()
+                .and_then(|id| self.parsed_flags.get(id))
+                .map_or(&[][..], |flag| &flag.values[..]);
+            for path in passed.iter().map(PathBuf::from) {
+                if self.sources.read(&path).is_none() {
+                    let reason = "the file can not be read".to_string();
+                    return Err(ParseError::InvalidConfigFile(
+                        path.display().to_string(),
+                        reason,
+                    ));
+                }
+            }
+            let files = self.config_files.iter().cloned();
+            for path in files.chain(passed.iter().map(PathBuf::from)) {
+                for (key, values) in self.sources.toml(&path)? {
+                    config.insert(key, (values, Some(path.clone())));
+                }
+            }
+        }
+        let values = self.config.iter();
+        config.extend(values.map(|(key, value)| (key.clone(), (vec![value.clone()], None))));
+        Ok(config)
+    }
+
+    /// Warnings for the keys of `config` that no flag of any command reads
+    pub(crate) fn unused_config_keys(&self, config: &Config) -> Vec<ParseWarning> {
+        let mut known: HashSet<String> = self
+            .global_flags
+            .values()
+            .filter_map(|flag| flag.config_key.clone())
+            .collect();
+        let mut commands: Vec<(String, &Command)> = self
+            .commands
+            .values()
+            .map(|command| (format!("{}.", command.id), command))
+            .collect();
+        while let Some((prefix, command)) = commands.pop() {
+            let keys = command
+                .flags
+                .values()
+                .filter_map(|flag| flag.config_key.as_ref());
+            known.extend(keys.map(|key| format!("{}{}", prefix, key)));
+            let subcommands = command.subcommands.values();
+            commands.extend(subcommands.map(|sub| (format!("{}{}.", prefix, sub.id), sub)));
+        }
+        let mut unused: Vec<_> = config
+            .iter()
+            .filter(|(key, _)| !known.contains(*key))
+            .collect();
+        unused.sort_by_key(|(key, _)| *key);
+        unused
+            .into_iter()
+            .map(|(key, (_, file))| {
+                let file = file.as_ref().map(|file| file.display().to_string());
+                ParseWarning::UnusedConfigKey(key.clone(), file)
+            })
+            .collect()
+    }
+}
+
+/// The environment and config values flags fall back to when not passed,
+/// see [Flag::env] and [Flag::config_key]
+pub(crate) struct Layers<'a> {
+    pub(crate) sources: &'a mut SourceCache,
+    pub(crate) config: &'a Config,
+    // Prefix of the config keys of the flags of the current command, like `remote.add.`
+    pub(crate) prefix: String,
+}
+
+impl Layers<'_> {
+    /// The layers of the command `id`, nested in the current command
+    pub(crate) fn command(&mut self, id: &str) -> Layers<'_> {
+        let mut prefix = self.prefix.clone();
+        if !id.is_empty() {
+            prefix.push_str(id);
+            prefix.push('.');
+        }
+        Layers {
+            sources: self.sources,
+            config: self.config,
+            prefix,
+        }
+    }
+
+    /// The values of `flag` in the environment, or else in the config
+    pub(crate) fn lookup(&mut self, flag: &Flag) -> Option<Layered> {
+        if let Some(value) = flag.env.as_deref().and_then(|name| self.sources.var(name)) {
+            return Some(Layered {
+                values: vec![value.into()],
+                source: ValueSource::Env,
+                origin: None,
+            });
+        }
+        let key = format!("{}{}", self.prefix, flag.config_key.as_deref()?);
+        let (values, file) = self.config.get(&key)?;
+        Some(Layered {
+            values: values.clone(),
+            source: ValueSource::Config,
+            origin: file.clone().map(|file| (file, key)),
+        })
+    }
+}
+
+/// The values of a flag that was not passed, see [Layers::lookup]
+pub(crate) struct Layered {
+    pub(crate) values: Vec<String>,
+    pub(crate) source: ValueSource,
+    // Config file and key the values were read from
+    pub(crate) origin: Option<(PathBuf, String)>,
+}
+
+impl Layered {
+    /// `error` about the values, naming the config file and key they were read from
+    pub(crate) fn locate(&self, error: ParseError) -> ParseError {
+        let reason = match &error {
+            ParseError::Validation(_, message) => message.clone(),
+            ParseError::InvalidValues(_, errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                errors.join(", ")
+            }
+            _ => return error,
+        };
+        match &self.origin {
+            Some((file, key)) => {
+                ParseError::InvalidConfigValue(file.display().to_string(), key.clone(), reason)
+            }
+            None => error,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cached_until_cleared() {
+        let path = std::env::temp_dir().join(format!("cli_parser_sources_{}", std::process::id()));
+        std::fs::write(&path, "first").unwrap();
+
+        let mut cache = SourceCache::default();
+        assert_eq!(cache.read(&path), Some("first"));
+
+        std::fs::write(&path, "second").unwrap();
+        assert_eq!(cache.read(&path), Some("first"));
+
+        cache.clear();
+        assert_eq!(cache.read(&path), Some("second"));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cache.read(&path), Some("second"));
+        cache.clear();
+        assert_eq!(cache.read(&path), None);
+    }
+
+    #[test]
+    fn test_config_files() {
+        let dir = std::env::temp_dir();
+        let system = dir.join(format!("cli_parser_system_{}.toml", std::process::id()));
+        let user = dir.join(format!("cli_parser_user_{}.toml", std::process::id()));
+        std::fs::write(
+            &system,
+            "jobs = 2\n\n[build]\ntarget = \"x86\"\nfeatures = [\"a\", \"b\"]\n",
+        )
+        .unwrap();
+        std::fs::write(&user, "jobs = 8\n").unwrap();
+
+        let mut parser = CliParser::parse_from(std::iter::empty::<String>())
+            .command(
+                Command::new("build")
+                    .flag(Flag::new("--target").positional().config_key("target"))
+                    .flag(Flag::new("--features").positional().config_key("features")),
+            )
+            .global_flag(
+                Flag::new("--jobs")
+                    .value_parser(|v| v.parse::<u8>())
+                    .config_key("jobs"),
+            )
+            .config_file(&system)
+            .config_file(&user)
+            .config_file(dir.join("cli_parser_missing.toml"));
+
+        let cmd = parser.parse_args(["build"]).unwrap();
+        assert_eq!(cmd.flag_as::<u8>("--jobs"), Some(&8));
+        assert_eq!(cmd.parsed_flags["--target"].value(), Some("x86"));
+        assert_eq!(cmd.parsed_flags["--features"].values, ["a", "b"]);
+        let cmd = parser.parse_args(["build", "--target", "arm"]).unwrap();
+        assert_eq!(cmd.parsed_flags["--target"].source, ValueSource::Cli);
+        assert!(parser.warnings.is_empty());
+
+        std::fs::write(
+            &user,
+            "jobs = 8\n\n[build]\ntarget = \"arm\"\ntarets = \"arm\"\n",
+        )
+        .unwrap();
+        parser.invalidate_sources();
+        parser.parse_args(["build"]).unwrap();
+        assert_eq!(
+            parser.warnings,
+            [ParseWarning::UnusedConfigKey(
+                "build.tarets".into(),
+                Some(user.display().to_string())
+            )]
+        );
+
+        std::fs::write(&user, "jobs = 300\n").unwrap();
+        parser.invalidate_sources();
+        let err = parser.parse_args(["build"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "invalid value for 'jobs' in '{}': '300': number too large to fit in target type",
+                user.display()
+            )
+        );
+
+        std::fs::write(&user, "jobs = \n").unwrap();
+        parser.invalidate_sources();
+        assert!(matches!(
+            parser.parse_args(["build"]),
+            Err(ParseError::InvalidConfigFile(path, _)) if path == user.display().to_string()
+        ));
+
+        std::fs::remove_file(&system).unwrap();
+        std::fs::remove_file(&user).unwrap();
+    }
+
+    #[test]
+    fn test_config_flag() {
+        let dir = std::env::temp_dir();
+        let default = dir.join(format!("cli_parser_default_{}.toml", std::process::id()));
+        let passed = dir.join(format!("cli_parser_passed_{}.toml", std::process::id()));
+        std::fs::write(&default, "profile = \"dev\"\n").unwrap();
+        std::fs::write(&passed, "profile = \"release\"\n").unwrap();
+
+        let mut parser = CliParser::parse_from(std::iter::empty::<String>())
+            .command(crate::Command::new("run"))
+            .global_flag(Flag::new("--profile").positional().config_key("profile"))
+            .config_file(&default)
+            .config_flag("-c");
+
+        let cmd = parser
+            .parse_args(["run", "-c", passed.to_str().unwrap()])
+            .unwrap();
+        assert_eq!(cmd.parsed_flags["--profile"].value(), Some("release"));
+        let cmd = parser.parse_args(["run"]).unwrap();
+        assert_eq!(cmd.parsed_flags["--profile"].value(), Some("dev"));
+
+        let missing = dir.join("cli_parser_missing.toml");
+        assert!(matches!(
+            parser.parse_args(["run", "-c", missing.to_str().unwrap()]),
+            Err(ParseError::InvalidConfigFile(path, reason))
+                if path == missing.display().to_string() && reason == "the file can not be read"
+        ));
+
+        std::fs::write(&passed, "not toml").unwrap();
+        let cmd = CliParser::parse_from(["run", "-c", passed.to_str().unwrap()])
+            .command(crate::Command::new("run"))
+            .config_flag("-c")
+            .sandboxed()
+            .parse();
+        assert!(cmd.is_ok());
+
+        std::fs::remove_file(&default).unwrap();
+        std::fs::remove_file(&passed).unwrap();
+    }
+}
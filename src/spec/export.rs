@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use super::SPEC_VERSION;
+use crate::{provenance::json_str, CliParser, Command, Flag, FlagKind, ValueHint};
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Describe all commands and flags as JSON, for tooling like docs sites,
+    /// completion generators, or UI wrappers, see [crate::spec].
+    ///
+    /// Commands and flags are sorted by id so the output is stable, and
+    /// hidden ones are included, marked `"hidden":true`
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::parse_from(std::iter::empty::<String>())
+    ///     .command(Command::new("deploy").flag(Flag::new("--env").positional().required()));
+    ///
+    /// let json = parser.to_spec_json();
+    /// assert!(json.starts_with(r#"{"version":2,"commands":[{"id":"deploy","#));
+    /// assert!(json.contains(r#"{"id":"--env","kind":"value","required":true,"#));
+    /// ```
+    pub fn to_spec_json(&self) -> String {
+        format!(
+            "{{\"version\":{},\"commands\":{},\"global_flags\":{}}}",
+            SPEC_VERSION,
+            json_commands(&self.commands),
+            json_flags(&self.global_flags)
+        )
+    }
+}
+
+fn json_commands(commands: &HashMap<String, Command>) -> String {
+    let mut commands: Vec<&Command> = commands.values().collect();
+    commands.sort_by(|a, b| a.id.cmp(&b.id));
+    let commands: Vec<String> = commands.into_iter().map(json_command).collect();
+    format!("[{}]", commands.join(","))
+}
+
+fn json_command(command: &Command) -> String {
+    let positionals: Vec<&str> = command.positionals.iter().map(|p| p.id.as_str()).collect();
+    format!(
+        concat!(
+            "{{\"id\":{},\"positional\":{},\"positionals\":{},\"flags\":{},",
+            "\"subcommands\":{},\"hidden\":{},\"trailing_args\":{},\"capabilities\":{},",
+            "\"about\":{},\"long_about\":{},\"before_help\":{},\"after_help\":{},",
+            "\"display_order\":{}}}"
+        ),
+        json_str(&command.id),
+        command.positional,
+        json_strs(&positionals),
+        json_flags(&command.flags),
+        json_commands(&command.subcommands),
+        command.hidden,
+        command.takes_trailing,
+        json_strs(&command.capabilities),
+        json_opt(command.about.as_deref()),
+        json_opt(command.long_about.as_deref()),
+        json_opt(command.before_help.as_deref()),
+        json_opt(command.after_help.as_deref()),
+        json_num(command.display_order),
+    )
+}
+
+fn json_flags(flags: &HashMap<String, Flag>) -> String {
+    let mut flags: Vec<&Flag> = flags.values().collect();
+    flags.sort_by(|a, b| a.id.cmp(&b.id));
+    let flags: Vec<String> = flags.into_iter().map(json_flag).collect();
+    format!("[{}]", flags.join(","))
+}
+
+fn json_flag(flag: &Flag) -> String {
+    let kind = match flag.kind {
+        FlagKind::Bool => "bool",
+        FlagKind::Value => "value",
+        FlagKind::Count => "count",
+    };
+    let tuple: Vec<&str> = flag.tuple.iter().map(|(name, _)| name.as_str()).collect();
+    format!(
+        concat!(
+            "{{\"id\":{},\"kind\":\"{}\",\"required\":{},\"default\":{},\"env\":{},",
+            "\"config_key\":{},\"hidden\":{},",
+            "\"deprecated\":{},\"exclusive\":{},\"min_values\":{},\"max_values\":{},",
+            "\"value_delimiter\":{},\"capabilities\":{},\"help\":{},\"long_help\":{},",
+            "\"help_heading\":{},\"display_order\":{},\"value_hint\":{},",
+            "\"allow_hyphen_values\":{},\"multiple\":{},\"tuple\":{}}}"
+        ),
+        json_str(&flag.id),
+        kind,
+        flag.required,
+        json_opt(flag.default_val.as_deref()),
+        json_opt(flag.env.as_deref()),
+        json_opt(flag.config_key.as_deref()),
+        flag.hidden,
+        json_opt(flag.deprecated.as_deref()),
+        flag.exclusive,
+        json_num(flag.min_values),
+        json_num(flag.max_values),
+        json_opt(flag.value_delimiter.map(String::from).as_deref()),
+        json_strs(&flag.capabilities),
+        json_opt(flag.help.as_deref()),
+        json_opt(flag.long_help.as_deref()),
+        json_opt(flag.help_heading.as_deref()),
+        json_num(flag.display_order),
+        json_opt(flag.value_hint.map(ValueHint::name)),
+        flag.allow_hyphen_values,
+        flag.multiple,
+        json_strs(&tuple),
+    )
+}
+
+fn json_strs<S: AsRef<str>>(values: &[S]) -> String {
+    let values: Vec<String> = values.iter().map(|v| json_str(v.as_ref())).collect();
+    format!("[{}]", values.join(","))
+}
+
+fn json_opt(value: Option<&str>) -> String {
+    value.map(json_str).unwrap_or("null".into())
+}
+
+fn json_num(value: Option<usize>) -> String {
+    value.map(|n| n.to_string()).unwrap_or("null".into())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_to_spec_json() {
+        let parser = CliParser::parse_from(std::iter::empty::<String>())
+            .command(
+                Command::new("remote")
+                    .requires_capability("net")
+                    .subcommand(Command::new("add").positional_arg(Positional::new("NAME"))),
+            )
+            .command(Command::new("debug").hidden().trailing_args())
+            .global_flag(Flag::new("--verbose").counted())
+            .global_flag(
+                Flag::new("--color")
+                    .positional()
+                    .default_value("auto")
+                    .value_delimiter(',')
+                    .max_values(2)
+                    .deprecated("use \"--colour\""),
+            );
+
+        let json: serde_json::Value = serde_json::from_str(&parser.to_spec_json()).unwrap();
+        assert_eq!(json["version"], SPEC_VERSION);
+        assert_eq!(json["commands"][0]["id"], "debug");
+        assert_eq!(json["commands"][0]["hidden"], true);
+        assert_eq!(json["commands"][0]["trailing_args"], true);
+        assert_eq!(json["commands"][1]["capabilities"][0], "net");
+        let add = &json["commands"][1]["subcommands"][0];
+        assert_eq!(add["positionals"], serde_json::json!(["NAME"]));
+        assert_eq!(add["positional"], false);
+
+        let color = &json["global_flags"][0];
+        assert_eq!(color["kind"], "value");
+        assert_eq!(color["default"], "auto");
+        assert_eq!(color["deprecated"], "use \"--colour\"");
+        assert_eq!(color["value_delimiter"], ",");
+        assert_eq!(color["max_values"], 2);
+        assert_eq!(color["min_values"], serde_json::Value::Null);
+        assert_eq!(json["global_flags"][1]["kind"], "count");
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_spec_round_trip() {
+        use std::str::FromStr;
+
+        let parser = CliParser::parse_from(std::iter::empty::<String>())
+            .command(
+                Command::new("remote")
+                    .about("Manage remotes")
+                    .after_help("See the remote docs")
+                    .flag(
+                        Flag::new("--tags")
+                            .positional()
+                            .min_values(1)
+                            .help("Tags")
+                            .long_help("Tags, like v1")
+                            .display_order(2)
+                            .help_heading("Filters"),
+                    )
+                    .flag(Flag::new("--pattern").positional().allow_hyphen_values())
+                    .flag(Flag::new("--port").values_of_type::<u16>())
+                    .flag(Flag::new("--map").tuple([
+                        ("FROM", ValueParser::new(String::from_str)),
+                        ("TO", ValueParser::new(String::from_str)),
+                    ]))
+                    .subcommand(Command::new("add").positional()),
+            )
+            .global_flag(Flag::new("--quiet").exclusive());
+        let json = parser.to_spec_json();
+
+        // JSON is valid YAML
+        let mut loaded = CliParser::parse_from(std::iter::empty::<String>());
+        loaded.load_spec(&json).unwrap();
+        assert_eq!(loaded.to_spec_json(), json);
+
+        let cmd = loaded
+            .parse_args([
+                "remote",
+                "--pattern",
+                "-x",
+                "--map",
+                "a",
+                "b",
+                "--port",
+                "1",
+            ])
+            .unwrap();
+        assert_eq!(cmd.value_of("--pattern"), Some("-x"));
+        assert_eq!(
+            cmd.parsed_flags["--map"].get_tuple::<(String, String)>(),
+            Some(("a".into(), "b".into()))
+        );
+        let flags = &loaded.commands["remote"].flags;
+        assert!(flags["--port"].multiple);
+    }
+}
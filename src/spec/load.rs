@@ -0,0 +1,478 @@
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use super::SPEC_VERSION;
+use crate::{CliParser, Command, Flag, Positional, ValueHint, ValueParser};
+/// Why a spec could not be loaded, see [CliParser::from_spec]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecError {
+    /// The spec is not valid in any enabled format, (reason for each format)
+    Invalid(Vec<String>),
+    /// The spec has a `version` of 0 or newer than [SPEC_VERSION]
+    UnsupportedVersion(u32),
+    /// A flag has an unknown `kind`, (id, kind)
+    InvalidKind(String, String),
+    /// A flag has an unknown `value_hint`, (id, hint)
+    InvalidValueHint(String, String),
+}
+
+impl std::fmt::Display for SpecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpecError::Invalid(reasons) => write!(f, "invalid spec: {}", reasons.join(", ")),
+            SpecError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported spec version {}, expected 1 to {}",
+                version, SPEC_VERSION
+            ),
+            SpecError::InvalidKind(id, kind) => write!(
+                f,
+                "invalid kind '{}' for '{}', expected bool, value or count",
+                kind, id
+            ),
+            SpecError::InvalidValueHint(id, hint) => write!(
+                f,
+                "invalid value hint '{}' for '{}', expected file_path, dir_path, hostname or username",
+                hint, id
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    #[serde(default = "default_version")]
+    version: u32,
+    #[serde(default)]
+    commands: Vec<CommandSpec>,
+    #[serde(default)]
+    global_flags: Vec<FlagSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CommandSpec {
+    id: String,
+    #[serde(default)]
+    positional: bool,
+    #[serde(default)]
+    positionals: Vec<String>,
+    #[serde(default)]
+    flags: Vec<FlagSpec>,
+    #[serde(default)]
+    subcommands: Vec<CommandSpec>,
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    trailing_args: bool,
+    #[serde(default)]
+    about: Option<String>,
+    #[serde(default)]
+    long_about: Option<String>,
+    #[serde(default)]
+    before_help: Option<String>,
+    #[serde(default)]
+    after_help: Option<String>,
+    #[serde(default)]
+    display_order: Option<usize>,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FlagSpec {
+    id: String,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    required: bool,
+    #[serde(default)]
+    default: Option<String>,
+    #[serde(default)]
+    env: Option<String>,
+    #[serde(default)]
+    config_key: Option<String>,
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    deprecated: Option<String>,
+    #[serde(default)]
+    exclusive: bool,
+    #[serde(default)]
+    min_values: Option<usize>,
+    #[serde(default)]
+    max_values: Option<usize>,
+    #[serde(default)]
+    value_delimiter: Option<char>,
+    #[serde(default)]
+    help: Option<String>,
+    #[serde(default)]
+    long_help: Option<String>,
+    #[serde(default)]
+    help_heading: Option<String>,
+    #[serde(default)]
+    display_order: Option<usize>,
+    #[serde(default)]
+    value_hint: Option<String>,
+    #[serde(default)]
+    capabilities: Vec<String>,
+    #[serde(default)]
+    allow_hyphen_values: bool,
+    #[serde(default)]
+    multiple: bool,
+    #[serde(default)]
+    tuple: Vec<String>,
+}
+
+fn default_version() -> u32 {
+    SPEC_VERSION
+}
+
+impl CommandSpec {
+    fn build(self) -> Result<Command, SpecError> {
+        let mut command = Command::new(&self.id);
+        if self.positional {
+            command = command.positional();
+        }
+        if self.hidden {
+            command = command.hidden();
+        }
+        if self.trailing_args {
+            command = command.trailing_args();
+        }
+        if let Some(about) = &self.about {
+            command = command.about(about);
+        }
+        if let Some(long_about) = &self.long_about {
+            command = command.long_about(long_about);
+        }
+        if let Some(text) = &self.before_help {
+            command = command.before_help(text);
+        }
+        if let Some(text) = &self.after_help {
+            command = command.after_help(text);
+        }
+        if let Some(order) = self.display_order {
+            command = command.display_order(order);
+        }
+        for capability in &self.capabilities {
+            command = command.requires_capability(capability);
+        }
+        for id in self.positionals {
+            command = command.positional_arg(Positional::new(&id));
+        }
+        for flag in self.flags {
+            command.add_flag(flag.build()?);
+        }
+        for sub in self.subcommands {
+            command.add_subcommand(sub.build()?);
+        }
+        Ok(command)
+    }
+}
+
+impl FlagSpec {
+    fn build(self) -> Result<Flag, SpecError> {
+        let mut flag = Flag::new(&self.id);
+        flag = match self.kind.as_deref() {
+            None | Some("bool") => flag,
+            Some("value") => flag.positional(),
+            Some("count") => flag.counted(),
+            Some(kind) => return Err(SpecError::InvalidKind(self.id, kind.into())),
+        };
+        flag = match self.value_hint.as_deref() {
+            None => flag,
+            Some("file_path") => flag.value_hint(ValueHint::FilePath),
+            Some("dir_path") => flag.value_hint(ValueHint::DirPath),
+            Some("hostname") => flag.value_hint(ValueHint::Hostname),
+            Some("username") => flag.value_hint(ValueHint::Username),
+            Some(hint) => return Err(SpecError::InvalidValueHint(self.id, hint.into())),
+        };
+        if self.required {
+            flag = flag.required();
+        }
+        if let Some(default) = &self.default {
+            flag = flag.default_value(default);
+        }
+        if let Some(name) = &self.env {
+            flag = flag.env(name);
+        }
+        if let Some(key) = &self.config_key {
+            flag = flag.config_key(key);
+        }
+        if self.hidden {
+            flag = flag.hidden();
+        }
+        if let Some(help) = &self.help {
+            flag = flag.help(help);
+        }
+        if let Some(help) = &self.long_help {
+            flag = flag.long_help(help);
+        }
+        if let Some(heading) = &self.help_heading {
+            flag = flag.help_heading(heading);
+        }
+        if let Some(order) = self.display_order {
+            flag = flag.display_order(order);
+        }
+        if let Some(note) = &self.deprecated {
+            flag = flag.deprecated(note);
+        }
+        if self.exclusive {
+            flag = flag.exclusive();
+        }
+        if let Some(min) = self.min_values {
+            flag = flag.min_values(min);
+        }
+        if let Some(max) = self.max_values {
+            flag = flag.max_values(max);
+        }
+        if let Some(delimiter) = self.value_delimiter {
+            flag = flag.value_delimiter(delimiter);
+        }
+        for capability in &self.capabilities {
+            flag = flag.requires_capability(capability);
+        }
+        if self.allow_hyphen_values {
+            flag = flag.allow_hyphen_values();
+        }
+        if self.multiple {
+            flag.multiple = true;
+        }
+        // A spec has no types, tuple values are kept as strings
+        if !self.tuple.is_empty() {
+            let parser = ValueParser::new(String::from_str);
+            flag = flag.tuple(
+                self.tuple
+                    .iter()
+                    .map(|name| (name.as_str(), parser.clone())),
+            );
+        }
+        Ok(flag)
+    }
+}
+
+/// Read `spec` in the first enabled format it is valid in, TOML before YAML
+fn read_spec(spec: &str) -> Result<Spec, SpecError> {
+    #[allow(unused_mut)]
+    let mut reasons: Vec<String> = Vec::new();
+    #[cfg(feature = "toml")]
+    match toml::from_str::<Spec>(spec) {
+        Ok(spec) => return Ok(spec),
+        Err(e) => reasons.push(format!("toml: {}", e.message())),
+    }
+    #[cfg(feature = "yaml")]
+    match serde_yaml::from_str::<Spec>(spec) {
+        Ok(spec) => return Ok(spec),
+        Err(e) => reasons.push(format!("yaml: {}", e)),
+    }
+    Err(SpecError::Invalid(reasons))
+}
+
+/// Upgrades of a spec from each version to the next, starting at version 1
+const UPGRADES: [fn(Spec) -> Result<Spec, SpecError>; SPEC_VERSION as usize - 1] = [upgrade_v1];
+
+/// Upgrade `spec` to [SPEC_VERSION] one version at a time, see [the versions](super#versions)
+fn upgrade(mut spec: Spec) -> Result<Spec, SpecError> {
+    if spec.version == 0 || spec.version > SPEC_VERSION {
+        return Err(SpecError::UnsupportedVersion(spec.version));
+    }
+    while spec.version < SPEC_VERSION {
+        spec = UPGRADES[spec.version as usize - 1](spec)?;
+        spec.version += 1;
+    }
+    Ok(spec)
+}
+
+/// Version 2 only added flag fields, which a version 1 spec may not use
+fn upgrade_v1(spec: Spec) -> Result<Spec, SpecError> {
+    fn check(flag: &FlagSpec) -> Result<(), SpecError> {
+        let added = [
+            ("allow_hyphen_values", flag.allow_hyphen_values),
+            ("multiple", flag.multiple),
+            ("tuple", !flag.tuple.is_empty()),
+        ];
+        match added.iter().find(|(_, used)| *used) {
+            Some((field, _)) => Err(SpecError::Invalid(vec![format!(
+                "'{}' of '{}' needs version 2",
+                field, flag.id
+            )])),
+            None => Ok(()),
+        }
+    }
+    fn check_command(command: &CommandSpec) -> Result<(), SpecError> {
+        command.flags.iter().try_for_each(check)?;
+        command.subcommands.iter().try_for_each(check_command)
+    }
+
+    spec.global_flags.iter().try_for_each(check)?;
+    spec.commands.iter().try_for_each(check_command)?;
+    Ok(spec)
+}
+
+impl CliParser<std::vec::IntoIter<String>> {
+    /// Create a new [CliParser] builder without any args, with the commands
+    /// and global flags described by a TOML or YAML `spec`, see [crate::spec].
+    ///
+    /// The parser can be further customized like any other, and args are passed
+    /// with [CliParser::parse_args]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// # #[cfg(feature = "toml")] {
+    /// let spec = r#"
+    /// [[commands]]
+    /// id = "deploy"
+    ///
+    /// [[commands.flags]]
+    /// id = "--env"
+    /// kind = "value"
+    /// default = "staging"
+    /// "#;
+    /// let mut parser = CliParser::from_spec(spec).unwrap();
+    /// let cmd = parser.parse_args(["deploy"]).unwrap();
+    /// assert_eq!(cmd.value_of("--env"), Some("staging"));
+    /// # }
+    /// ```
+    pub fn from_spec(spec: &str) -> Result<Self, SpecError> {
+        let mut parser = CliParser::parse_from(std::iter::empty::<String>());
+        parser.load_spec(spec)?;
+        Ok(parser)
+    }
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Add the commands and global flags described by a TOML or YAML `spec`,
+    /// see [CliParser::from_spec]. Nothing is added if any of the spec is invalid
+    pub fn load_spec(&mut self, spec: &str) -> Result<&mut Self, SpecError> {
+        let spec = upgrade(read_spec(spec)?)?;
+        let global_flags = spec
+            .global_flags
+            .into_iter()
+            .map(FlagSpec::build)
+            .collect::<Result<Vec<Flag>, SpecError>>()?;
+        let commands = spec
+            .commands
+            .into_iter()
+            .map(CommandSpec::build)
+            .collect::<Result<Vec<Command>, SpecError>>()?;
+
+        for flag in global_flags {
+            self.add_global_flag(flag);
+        }
+        for command in commands {
+            self.add_command(command);
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_yaml_spec() {
+        let spec = "
+version: 1
+global_flags:
+  - id: --verbose
+    kind: count
+commands:
+  - id: remote
+    subcommands:
+      - id: add
+        positionals: [NAME, URL]
+        flags:
+          - id: --tags
+            kind: value
+            value_delimiter: ','
+";
+        let mut parser = CliParser::parse_from(std::iter::empty::<String>());
+        parser.load_spec(spec).unwrap();
+        let cmd = parser
+            .parse_args([
+                "--verbose",
+                "remote",
+                "add",
+                "origin",
+                "url",
+                "--tags",
+                "a,b",
+            ])
+            .unwrap();
+
+        assert_eq!(cmd.path(), ["remote", "add"]);
+        assert_eq!(cmd.occurrences_of("--verbose"), 1);
+        let add = cmd.subcommand.unwrap();
+        assert_eq!(add.values_of("--tags"), ["a", "b"]);
+        assert_eq!(add.parsed_positionals["URL"].value.as_deref(), Some("url"));
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn test_older_spec_version() {
+        let v1 = "version = 1\n[[commands]]\nid = \"grep\"\nflags = [{ id = \"--pattern\", kind = \"value\" }]";
+        let mut parser = CliParser::from_spec(v1).unwrap();
+        let cmd = parser.parse_args(["grep", "--pattern", "x"]).unwrap();
+        assert_eq!(cmd.value_of("--pattern"), Some("x"));
+        assert!(parser.to_spec_json().starts_with(r#"{"version":2,"#));
+
+        let v1_with_v2_field = "version = 1\nglobal_flags = [{ id = \"--tag\", multiple = true }]";
+        assert_eq!(
+            CliParser::from_spec(v1_with_v2_field).unwrap_err(),
+            SpecError::Invalid(vec!["'multiple' of '--tag' needs version 2".into()])
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn test_invalid_specs() {
+        let mut parser = CliParser::parse_from(std::iter::empty::<String>());
+        assert_eq!(
+            parser.load_spec("version = 3").unwrap_err(),
+            SpecError::UnsupportedVersion(3)
+        );
+        assert_eq!(
+            parser.load_spec("version = 0").unwrap_err(),
+            SpecError::UnsupportedVersion(0)
+        );
+        assert_eq!(
+            parser
+                .load_spec("[[commands]]\nid = \"x\"\nflags = [{ id = \"--f\", kind = \"int\" }]")
+                .unwrap_err(),
+            SpecError::InvalidKind("--f".into(), "int".into())
+        );
+        assert_eq!(
+            parser
+                .load_spec("global_flags = [{ id = \"--f\", value_hint = \"url\" }]")
+                .unwrap_err(),
+            SpecError::InvalidValueHint("--f".into(), "url".into())
+        );
+        assert!(matches!(
+            parser.load_spec("commands = 5"),
+            Err(SpecError::Invalid(_))
+        ));
+
+        // Nothing of a spec failing late is added
+        let before = parser.to_spec_json();
+        assert!(parser
+            .load_spec(concat!(
+                "global_flags = [{ id = \"--quiet\" }]\n",
+                "[[commands]]\nid = \"ok\"\n",
+                "[[commands]]\nid = \"bad\"\nflags = [{ id = \"--f\", kind = \"int\" }]",
+            ))
+            .is_err());
+        assert_eq!(parser.to_spec_json(), before);
+    }
+}
@@ -0,0 +1,49 @@
+//! Declarative parser definitions, loaded from TOML or YAML behind the `toml` and `yaml`
+//! features, and exported as JSON by [crate::CliParser::to_spec_json].
+//!
+//! A spec lists the commands and global flags of a parser, every field but `id` is optional.
+//! The JSON export has the same shape, with every field present:
+//!
+//! ```toml
+//! version = 2
+//!
+//! [[global_flags]]
+//! id = "--verbose"
+//! kind = "count"
+//!
+//! [[commands]]
+//! id = "deploy"
+//! positionals = ["TARGET"]
+//!
+//! [[commands.flags]]
+//! id = "--env"
+//! kind = "value"
+//! default = "staging"
+//!
+//! [[commands.subcommands]]
+//! id = "rollback"
+//! ```
+//!
+//! ## Versions
+//!
+//! The `version` of a spec is the format it is written in. It is bumped for every change
+//! to the format, new fields included, since unknown fields are rejected: a spec too new
+//! for the reader fails with `SpecError::UnsupportedVersion` instead of an unknown field.
+//!
+//! Specs of every older version keep loading, each version is upgraded to the next until
+//! it is [SPEC_VERSION]. Fields newer than the version of a spec are rejected.
+//! Dropping support for a version is a breaking change of the crate.
+//!
+//! - 1: the initial format
+//! - 2: adds `allow_hyphen_values`, `multiple`, and `tuple` to flags
+mod export;
+#[cfg(any(feature = "toml", feature = "yaml"))]
+mod load;
+
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub use load::SpecError;
+
+/// Version of the spec format read by [crate::CliParser::from_spec] and written by
+/// [crate::CliParser::to_spec_json], specs without a `version` are read as this version.
+/// See [the versions](self#versions) for what each version changed
+pub const SPEC_VERSION: u32 = 2;
@@ -0,0 +1,130 @@
+use crate::{CliParser, Flag};
+
+/// Id of the exclusive global flag asking for help, see [CliParser::with_standard_flags]
+pub const HELP: &str = "--help";
+/// Id of the exclusive global flag asking for a summary of the help, see [CliParser::help_flag]
+pub const SHORT_HELP: &str = "-h";
+/// Id of the command printing the help of the command named by its args,
+/// see [CliParser::help_command]
+pub const HELP_COMMAND: &str = "help";
+/// Id of the exclusive global flag asking for the version, see [CliParser::with_standard_flags]
+pub const VERSION: &str = "--version";
+
+/// Which of the standard global flags [CliParser::standard_flags] registers, all by default
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let parser = CliParser::new().standard_flags(StandardFlags {
+///     version: false,
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StandardFlags {
+    /// Register [HELP] and [SHORT_HELP] as exclusive flags, see [CliParser::help_flag]
+    pub help: bool,
+    /// Register the [HELP_COMMAND] command, see [CliParser::help_command]
+    pub help_command: bool,
+    /// Register [VERSION] as an exclusive flag
+    pub version: bool,
+    /// Register the counted [crate::VERBOSE] and [crate::QUIET] flags, see [CliParser::verbosity]
+    pub verbosity: bool,
+}
+
+impl Default for StandardFlags {
+    fn default() -> Self {
+        Self {
+            help: true,
+            help_command: true,
+            version: true,
+            verbosity: true,
+        }
+    }
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Register the standard `--help`, `-h`, `--version`, `--verbose`, and `--quiet` global flags,
+    /// and the `help` command, see [CliParser::standard_flags]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let cmd = CliParser::parse_from(["--version"])
+    ///     .with_standard_flags()
+    ///     .command(Command::new("build").flag(Flag::new("--out").positional().required()))
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(cmd.exclusive_flag.as_deref(), Some(VERSION));
+    /// ```
+    pub fn with_standard_flags(self) -> Self {
+        self.standard_flags(StandardFlags::default())
+    }
+
+    /// Set the version [CliParser::parse_or_exit] prints with the program name,
+    /// like `app 1.2.0`, when [VERSION] is passed. Often `env!("CARGO_PKG_VERSION")`
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Register the chosen standard global flags. `--help`, `-h`, and `--version` are
+    /// [Flag::exclusive], stopping parsing and skipping required checks when passed,
+    /// `--verbose` and `--quiet` are counted into a [crate::Verbosity]. A command of your own
+    /// named `help` registered afterwards replaces the built-in one
+    pub fn standard_flags(self, flags: StandardFlags) -> Self {
+        let mut parser = self;
+        if flags.help {
+            parser = parser.help_flag();
+        }
+        if flags.help_command {
+            parser = parser.help_command();
+        }
+        if flags.version {
+            parser = parser.global_flag(Flag::new(VERSION).exclusive());
+        }
+        if flags.verbosity {
+            parser = parser.verbosity();
+        }
+        parser
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_standard_flags() {
+        let parse = |args: &str, flags: StandardFlags| {
+            CliParser::parse_from(args.split_whitespace())
+                .standard_flags(flags)
+                .command(Command::new("build").flag(Flag::new("--out").positional().required()))
+                .parse()
+        };
+
+        let cmd = parse("build --help", StandardFlags::default()).unwrap();
+        assert_eq!(cmd.exclusive_flag.as_deref(), Some(HELP));
+        let cmd = parse("build -h", StandardFlags::default()).unwrap();
+        assert_eq!(cmd.exclusive_flag.as_deref(), Some(SHORT_HELP));
+        let cmd = parse(
+            "build --out x --verbose --quiet --quiet",
+            StandardFlags::default(),
+        );
+        assert_eq!(cmd.unwrap().verbosity().level(), -1);
+
+        let no_version = StandardFlags {
+            version: false,
+            verbosity: false,
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse("build --version", no_version),
+            Err(ParseError::InvalidFlag(id)) if id == "--version"
+        ));
+        assert!(parse("build --out x --verbose", no_version).is_err());
+    }
+}
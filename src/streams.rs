@@ -0,0 +1,139 @@
+use std::{
+    fmt::Debug,
+    io::{stderr, stdin, stdout, BufRead, IsTerminal, Write},
+    sync::{Arc, Mutex},
+};
+
+/// Destination of generated output, see [Streams]
+#[derive(Clone)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+    /// Any writer, like a buffer in a GUI wrapper or a test
+    Writer(Arc<Mutex<dyn Write + Send>>),
+}
+
+impl Stream {
+    /// Write to `writer`
+    pub fn writer<W: Write + Send + 'static>(writer: W) -> Self {
+        Stream::Writer(Arc::new(Mutex::new(writer)))
+    }
+
+    /// If the stream is a terminal, never for a [Stream::Writer]
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Stream::Stdout => stdout().is_terminal(),
+            Stream::Stderr => stderr().is_terminal(),
+            Stream::Writer(_) => false,
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Stdout => stdout().write(buf),
+            Stream::Stderr => stderr().write(buf),
+            Stream::Writer(writer) => writer.lock().unwrap_or_else(|e| e.into_inner()).write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Stdout => stdout().flush(),
+            Stream::Stderr => stderr().flush(),
+            Stream::Writer(writer) => writer.lock().unwrap_or_else(|e| e.into_inner()).flush(),
+        }
+    }
+}
+
+impl Debug for Stream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stream::Stdout => f.write_str("Stdout"),
+            Stream::Stderr => f.write_str("Stderr"),
+            Stream::Writer(_) => f.write_str("Writer"),
+        }
+    }
+}
+
+/// Source of answers to interactive prompts, see [Streams]
+#[derive(Clone)]
+pub enum Input {
+    Stdin,
+    /// Any reader, like scripted answers in a GUI wrapper or a test
+    Reader(Arc<Mutex<dyn BufRead + Send>>),
+}
+
+impl Input {
+    /// Read from `reader`
+    pub fn reader<R: BufRead + Send + 'static>(reader: R) -> Self {
+        Input::Reader(Arc::new(Mutex::new(reader)))
+    }
+
+    /// If the input is a terminal, a [Input::Reader] is always taken as one
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Input::Stdin => stdin().is_terminal(),
+            Input::Reader(_) => true,
+        }
+    }
+
+    /// Read a line into `line`, see [BufRead::read_line]
+    pub(crate) fn read_line(&self, line: &mut String) -> std::io::Result<usize> {
+        match self {
+            Input::Stdin => stdin().lock().read_line(line),
+            Input::Reader(reader) => reader
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .read_line(line),
+        }
+    }
+}
+
+impl Debug for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Stdin => f.write_str("Stdin"),
+            Input::Reader(_) => f.write_str("Reader"),
+        }
+    }
+}
+
+/// Where the parser writes its own output and reads answers to prompts,
+/// set with [crate::CliParser::streams].
+///
+/// Keeps stdout machine-parseable, or captures the output in GUI wrappers
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let parser = CliParser::from_args(std::iter::empty())
+///     .command(Command::new("export"))
+///     .streams(Streams {
+///         output: Stream::Stderr,
+///         ..Default::default()
+///     });
+/// ```
+#[derive(Debug, Clone)]
+pub struct Streams {
+    /// Requested output, like `--show-config`, stdout by default
+    pub output: Stream,
+    /// Errors and warnings, stderr by default
+    pub errors: Stream,
+    /// Interactive prompts, stderr by default
+    pub prompts: Stream,
+    /// Answers to interactive prompts, stdin by default
+    pub input: Input,
+}
+
+impl Default for Streams {
+    fn default() -> Self {
+        Self {
+            output: Stream::Stdout,
+            errors: Stream::Stderr,
+            prompts: Stream::Stderr,
+            input: Input::Stdin,
+        }
+    }
+}
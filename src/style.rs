@@ -0,0 +1,105 @@
+use crate::{Environment, Stream};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD_YELLOW: &str = "\x1b[1;33m";
+
+/// ANSI styling of generated errors, warnings, and usage, behind the `color` feature.
+///
+/// Enabled when [Environment::effective_color] for the stream written to, which respects
+/// `NO_COLOR` and only colors terminals, otherwise every method returns the text as is
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Style {
+    enabled: bool,
+}
+
+/// Kind of a generated message, picking the color of its prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageKind {
+    Error,
+    Warning,
+}
+
+impl Style {
+    /// Style for output written to `stream`
+    pub(crate) fn new(environment: &Environment, stream: &Stream) -> Self {
+        Self {
+            enabled: cfg!(feature = "color") && environment.effective_color(stream),
+        }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        match self.enabled {
+            true => format!("{}{}{}", code, text, RESET),
+            false => text.into(),
+        }
+    }
+
+    /// Bold, for flag and command names
+    pub(crate) fn bold(&self, text: &str) -> String {
+        self.paint(BOLD, text)
+    }
+
+    /// Bold red, for the `error` prefix
+    pub(crate) fn error(&self, text: &str) -> String {
+        self.paint(BOLD_RED, text)
+    }
+
+    /// Bold yellow, for the `warning` prefix
+    pub(crate) fn warning(&self, text: &str) -> String {
+        self.paint(BOLD_YELLOW, text)
+    }
+
+    /// Style a message of `kind` like `error in 'x': ...`, with the localized `prefix`
+    /// colored by kind and names quoted in `rest` bold
+    pub(crate) fn message(&self, kind: MessageKind, prefix: &str, rest: &str) -> String {
+        if !self.enabled {
+            return format!("{}{}", prefix, rest);
+        }
+        let prefix = match kind {
+            MessageKind::Error => self.error(prefix),
+            MessageKind::Warning => self.warning(prefix),
+        };
+
+        let mut out = prefix;
+        let mut parts = rest.split('\'');
+        out.push_str(parts.next().unwrap_or_default());
+        let parts: Vec<&str> = parts.collect();
+        for (i, part) in parts.iter().enumerate() {
+            match i % 2 {
+                // Quoted, unless the closing quote is missing
+                0 if i + 1 < parts.len() => out.push_str(&format!("'{}'", self.bold(part))),
+                0 => out.push_str(&format!("'{}", part)),
+                _ => out.push_str(part),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_style() {
+        let plain = Style { enabled: false };
+        let rest = " in 'deploy': unknown flag '--froce' at position 1";
+        assert_eq!(
+            plain.message(MessageKind::Error, "error", rest),
+            format!("error{}", rest)
+        );
+
+        let color = Style { enabled: true };
+        assert_eq!(
+            color.message(MessageKind::Error, "feil", rest),
+            "\x1b[1;31mfeil\x1b[0m in '\x1b[1mdeploy\x1b[0m': \
+             unknown flag '\x1b[1m--froce\x1b[0m' at position 1"
+        );
+        assert_eq!(
+            color.message(MessageKind::Warning, "warning", ": it's deprecated"),
+            "\x1b[1;33mwarning\x1b[0m: it's deprecated"
+        );
+    }
+}
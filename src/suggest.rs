@@ -0,0 +1,406 @@
+use std::{cmp::Reverse, collections::HashMap, fmt::Debug, sync::Arc};
+
+use crate::{help::flag_usage, looks_like_flag, CliParser, Command, Flag, FlagKind};
+
+/// First arg asking for completions, see [CliParser::dynamic_completion]
+pub const COMPLETE_COMMAND: &str = "__complete";
+
+type CompleterFn = dyn Fn(&str) -> Vec<String> + Send + Sync;
+
+/// Computes the values of a flag offered by completions, see [Flag::complete_with]
+#[derive(Clone)]
+pub(crate) struct Completer(pub(crate) Arc<CompleterFn>);
+
+impl Debug for Completer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Completer")
+    }
+}
+
+/// What kind of token a [Suggestion] completes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SuggestionKind {
+    Command,
+    Flag,
+    Value,
+}
+
+/// A ranked candidate for the next token, see [CliParser::suggest_next]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The full token to insert
+    pub value: String,
+    /// Label to display, like `--out <VALUE>`
+    pub label: String,
+    /// Description to display next to the label
+    pub description: Option<String>,
+    pub kind: SuggestionKind,
+}
+
+impl Suggestion {
+    fn command(command: &Command) -> Self {
+        Self {
+            value: command.id.clone(),
+            label: command.id.clone(),
+            description: command.about.clone(),
+            kind: SuggestionKind::Command,
+        }
+    }
+
+    fn flag(flag: &Flag) -> Self {
+        let label = flag_usage(flag);
+        Self {
+            value: flag.id.clone(),
+            label,
+            description: flag.help.clone(),
+            kind: SuggestionKind::Flag,
+        }
+    }
+
+    fn extra(word: &str) -> Self {
+        Self {
+            value: word.into(),
+            label: word.into(),
+            description: None,
+            kind: SuggestionKind::Value,
+        }
+    }
+
+    fn value(value: &str, description: &str) -> Self {
+        Self {
+            value: value.into(),
+            label: value.into(),
+            description: Some(description.into()),
+            kind: SuggestionKind::Value,
+        }
+    }
+}
+
+/// Where the walk over the complete tokens ended up
+struct State<'a> {
+    // Matched commands, outermost first
+    path: Vec<&'a Command>,
+    // Flag waiting for its value
+    expect_value: Option<&'a Flag>,
+    // Number of values still to be passed to `expect_value`, more than one for tuples
+    pending_values: usize,
+    // Number of positionals still to be passed to `command`
+    pending_positionals: usize,
+    // Flags already passed
+    used: Vec<&'a str>,
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Suggest ranked candidates for the last, partial, token of `args`.
+    ///
+    /// All tokens but the last are treated as complete, the last one is the
+    /// prefix being typed, pass `""` to get all candidates for a new token.
+    /// Returns no suggestions if the complete tokens do not match the definition.
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .command(Command::new("status"))
+    ///     .command(Command::new("stash"));
+    ///
+    /// let suggestions = parser.suggest_next(&["st"]);
+    /// assert_eq!(suggestions[0].value, "stash");
+    /// ```
+    pub fn suggest_next<S: AsRef<str>>(&self, args: &[S]) -> Vec<Suggestion> {
+        let (prefix, done) = match args.split_last() {
+            Some((prefix, done)) => (prefix.as_ref(), done),
+            None => ("", &[] as &[S]),
+        };
+
+        let state = match self.walk(done) {
+            Some(state) => state,
+            None => return Vec::new(),
+        };
+
+        let mut suggestions = Vec::new();
+        let (excluded, extra) = match state.path.last() {
+            Some(command) => (&command.complete_exclude[..], &command.complete_extra[..]),
+            None => (&[][..], &[][..]),
+        };
+        if let Some(flag) = state.expect_value {
+            let locale = self.environment.effective_locale();
+            let description = self.messages.template(&locale, "default");
+            suggestions.extend(
+                flag.default_val
+                    .as_deref()
+                    .map(|v| Suggestion::value(v, description)),
+            );
+            let choices = flag.validators.iter().find_map(|v| v.choices());
+            suggestions.extend(
+                choices
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|v| Suggestion::extra(v)),
+            );
+            if let Some(completer) = &flag.completer {
+                let values = (completer.0)(prefix);
+                suggestions.extend(values.iter().map(|v| Suggestion::extra(v)));
+            }
+        } else {
+            suggestions.extend(extra.iter().map(|word| Suggestion::extra(word)));
+        }
+        if state.expect_value.is_none() && state.pending_positionals == 0 {
+            let commands = match state.path.last() {
+                Some(command) => &command.subcommands,
+                None => &self.commands,
+            };
+            suggestions.extend(
+                commands
+                    .values()
+                    .filter(|c| !c.hidden && self.has_capabilities(&c.capabilities))
+                    .map(Suggestion::command),
+            );
+
+            let local = state.path.iter().flat_map(|c| c.flags.values());
+            suggestions.extend(
+                self.global_flags
+                    .values()
+                    .chain(local)
+                    .filter(|f| f.kind == FlagKind::Count || !state.used.contains(&f.id.as_str()))
+                    .filter(|f| !f.hidden && self.has_capabilities(&f.capabilities))
+                    .filter(|f| !excluded.contains(&f.id))
+                    .map(Suggestion::flag),
+            );
+        }
+
+        suggestions.retain(|s| s.value.starts_with(prefix));
+        // Most used commands first, see [CliParser::usage_frequency]
+        let parent: Vec<&str> = state.path.iter().map(|c| c.id.as_str()).collect();
+        let usage = |s: &Suggestion| match s.kind {
+            SuggestionKind::Command => self.usage_of(&parent, &s.value),
+            _ => 0,
+        };
+        suggestions.sort_by_cached_key(|s| (s.kind, Reverse(usage(s)), s.value.clone()));
+        // A default may also be a choice
+        suggestions.dedup_by(|a, b| a.value == b.value);
+        suggestions
+    }
+
+    /// Answer `app __complete <ARGS>...` in [CliParser::parse_or_exit] and
+    /// [CliParser::parse_or_print], printing the [CliParser::suggest_next] candidates
+    /// for the last of the args, one per line, as the value and its description
+    /// separated by a tab. Generated completion scripts call it to complete the values of
+    /// flags with a [Flag::complete_with] completer, where the shell supports it
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let output = Arc::new(Mutex::new(Vec::new()));
+    /// CliParser::parse_from(["__complete", "--host", ""])
+    ///     .global_flag(Flag::new("--host").positional().complete_with(|_| vec!["db1".into()]))
+    ///     .dynamic_completion()
+    ///     .streams(Streams {
+    ///         output: Stream::Writer(output.clone()),
+    ///         ..Default::default()
+    ///     })
+    ///     .parse_or_print()
+    ///     .unwrap_err();
+    /// assert_eq!(output.lock().unwrap().as_slice(), b"db1\n");
+    /// ```
+    pub fn dynamic_completion(mut self) -> Self {
+        self.dynamic_completion = true;
+        self
+    }
+
+    /// The completions of the args following [COMPLETE_COMMAND], see [CliParser::dynamic_completion]
+    pub(crate) fn complete_args(&mut self) -> String {
+        let mut args: Vec<String> = self.args.by_ref().skip(1).collect();
+        if args.is_empty() {
+            args.push(String::new());
+        }
+        self.suggest_next(&args)
+            .into_iter()
+            .map(|s| match s.description {
+                Some(description) => format!(
+                    "{}\t{}\n",
+                    s.value,
+                    description.lines().next().unwrap_or_default()
+                ),
+                None => format!("{}\n", s.value),
+            })
+            .collect()
+    }
+
+    /// Walk the complete tokens through the definition
+    fn walk<'a, S: AsRef<str>>(&'a self, done: &[S]) -> Option<State<'a>> {
+        let mut state = State {
+            path: Vec::new(),
+            expect_value: None,
+            pending_values: 0,
+            pending_positionals: 0,
+            used: Vec::new(),
+        };
+
+        for token in done.iter().map(|t| t.as_ref()) {
+            if state.pending_values > 0 {
+                state.pending_values -= 1;
+                if state.pending_values == 0 {
+                    state.expect_value = None;
+                }
+                continue;
+            }
+            if state.pending_positionals > 0 {
+                state.pending_positionals -= 1;
+                continue;
+            }
+
+            if looks_like_flag(token) {
+                let flag = find_flag(&self.global_flags, &state.path, token)?;
+                if flag.kind == FlagKind::Value {
+                    state.expect_value = Some(flag);
+                    state.pending_values = flag.tuple.len().max(1);
+                }
+                state.used.push(&flag.id);
+            } else {
+                let commands = match state.path.last() {
+                    Some(command) => &command.subcommands,
+                    None => &self.commands,
+                };
+                let command = commands.get(token)?;
+                state.pending_positionals = command.positional as usize + command.positionals.len();
+                state.path.push(command);
+            }
+        }
+        Some(state)
+    }
+}
+
+/// Look up a flag like the parser, globals first, then from the innermost command and out
+fn find_flag<'a>(
+    global_flags: &'a HashMap<String, Flag>,
+    path: &[&'a Command],
+    token: &str,
+) -> Option<&'a Flag> {
+    global_flags
+        .get(token)
+        .or_else(|| path.iter().rev().find_map(|c| c.flags.get(token)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parser() -> CliParser<std::iter::Empty<String>> {
+        CliParser::from_args(std::iter::empty())
+            .command(
+                Command::new("remote")
+                    .flag(Flag::new("--verbose"))
+                    .subcommand(Command::new("add").positional())
+                    .subcommand(Command::new("remove")),
+            )
+            .command(Command::new("rebase").flag(Flag::new("--onto").default_value("main")))
+            .command(Command::new("reflog-debug").hidden())
+            .global_flag(Flag::new("--quiet"))
+            .global_flag(Flag::new("--trace").hidden())
+    }
+
+    fn values(suggestions: Vec<Suggestion>) -> Vec<String> {
+        suggestions.into_iter().map(|s| s.value).collect()
+    }
+
+    #[test]
+    fn test_suggest_commands_then_flags() {
+        assert_eq!(
+            values(parser().suggest_next(&["re"])),
+            vec!["rebase", "remote"]
+        );
+        assert_eq!(
+            values(parser().suggest_next(&["remote", ""])),
+            vec!["add", "remove", "--quiet", "--verbose"]
+        );
+        assert_eq!(
+            values(parser().suggest_next(&["remote", "--verbose", "-"])),
+            vec!["--quiet"]
+        );
+        assert_eq!(
+            values(parser().suggest_next(&["remote", "remove", "--v"])),
+            vec!["--verbose"]
+        );
+    }
+
+    #[test]
+    fn test_suggest_most_used_first() {
+        let parser = parser().usage_frequency([
+            ("remote remove".to_string(), 5),
+            ("remote add".to_string(), 1),
+            ("remote".to_string(), 2),
+        ]);
+        assert_eq!(
+            values(parser.suggest_next(&["remote", ""])),
+            vec!["remove", "add", "--quiet", "--verbose"]
+        );
+        assert_eq!(
+            values(parser.suggest_next(&["re"])),
+            vec!["remote", "rebase"]
+        );
+    }
+
+    #[test]
+    fn test_complete_exclude_and_extra() {
+        let parser = parser().command(
+            Command::new("log")
+                .positional()
+                .flag(Flag::new("--debug-dump"))
+                .complete_exclude("--debug-dump")
+                .complete_exclude("--quiet")
+                .complete_extra("HEAD"),
+        );
+        assert_eq!(values(parser.suggest_next(&["log", ""])), vec!["HEAD"]);
+        assert_eq!(values(parser.suggest_next(&["log", "x", ""])), vec!["HEAD"]);
+        assert_eq!(
+            values(parser.suggest_next(&["remote", ""])),
+            vec!["add", "remove", "--quiet", "--verbose"]
+        );
+    }
+
+    #[test]
+    fn test_suggest_values() {
+        let suggestions = parser().suggest_next(&["rebase", "--onto", ""]);
+        assert_eq!(suggestions[0].kind, SuggestionKind::Value);
+        assert_eq!(values(suggestions), vec!["main"]);
+
+        assert!(parser().suggest_next(&["remote", "add", ""]).is_empty());
+        assert!(parser().suggest_next(&["unknown", ""]).is_empty());
+
+        let point = || crate::ValueParser::new(|v: &str| v.parse::<i32>());
+        let parser = parser().command(
+            Command::new("move")
+                .flag(Flag::new("--to").tuple([("X", point()), ("Y", point())]))
+                .flag(Flag::new("--fast")),
+        );
+        assert!(parser.suggest_next(&["move", "--to", "1", ""]).is_empty());
+        assert_eq!(
+            values(parser.suggest_next(&["move", "--to", "1", "2", "--f"])),
+            vec!["--fast"]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_completion() {
+        let args = ["__complete", "deploy", "--host", "w"].map(String::from);
+        let mut parser = CliParser::from_args(args.into_iter())
+            .command(
+                Command::new("deploy").flag(
+                    Flag::new("--host")
+                        .positional()
+                        .complete_with(|prefix| vec![format!("{}eb1", prefix), "db1".into()]),
+                ),
+            )
+            .dynamic_completion();
+        assert_eq!(parser.complete_args(), "web1\n");
+
+        let script = crate::generate_completions(crate::Shell::Bash, &parser);
+        assert!(script.contains("__complete"));
+    }
+}
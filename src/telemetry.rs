@@ -0,0 +1,48 @@
+use std::{fmt::Debug, sync::Arc};
+
+use crate::{Command, ParseError};
+
+/// Anonymized summary of a single parse, passed to [crate::CliParser::on_outcome].
+///
+/// Contains only ids from the definition, never any values passed by the user
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOutcome {
+    /// Ids of the matched commands, outermost first
+    pub command_path: Vec<String>,
+    /// Ids of the matched flags, in the order they were passed
+    pub flags: Vec<String>,
+    /// Kind of the error if parsing failed, see [crate::ParseError::kind]
+    pub error: Option<&'static str>,
+}
+
+type OutcomeFn = dyn Fn(&ParseOutcome) + Send + Sync;
+
+/// Callback registered with [crate::CliParser::on_outcome]
+#[derive(Clone)]
+pub(crate) struct OutcomeHook(pub(crate) Arc<OutcomeFn>);
+
+impl Debug for OutcomeHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OutcomeHook")
+    }
+}
+
+type BeforeFn = dyn Fn() + Send + Sync;
+type AfterFn = dyn Fn(&mut Result<Command, ParseError>) + Send + Sync;
+
+/// Callbacks registered with [crate::CliParser::before_parse] and [crate::CliParser::after_parse],
+/// called in the order they were registered
+#[derive(Clone, Default)]
+pub(crate) struct ParseHooks {
+    pub(crate) before: Vec<Arc<BeforeFn>>,
+    pub(crate) after: Vec<Arc<AfterFn>>,
+}
+
+impl Debug for ParseHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParseHooks")
+            .field("before", &self.before.len())
+            .field("after", &self.after.len())
+            .finish()
+    }
+}
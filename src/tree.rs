@@ -0,0 +1,240 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    help::{display_key, flag_usage},
+    CliParser, Command, Flag, FlagKind,
+};
+
+/// Characters drawing the branches of the tree
+struct Branches {
+    // Prefix of a child with later siblings, and of its descendants
+    tee: &'static str,
+    pipe: &'static str,
+    // Prefix of the last child, and of its descendants
+    corner: &'static str,
+    blank: &'static str,
+}
+
+const UNICODE: Branches = Branches {
+    tee: "├── ",
+    pipe: "│   ",
+    corner: "└── ",
+    blank: "    ",
+};
+
+const ASCII: Branches = Branches {
+    tee: "|-- ",
+    pipe: "|   ",
+    corner: "`-- ",
+    blank: "    ",
+};
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Render the full definition as a tree, for debugging parser construction.
+    ///
+    /// Global flags come first, then every command with its flags and subcommands,
+    /// each in display order, drawn with ASCII if [crate::Environment::effective_ascii]
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::*;
+    /// let parser = CliParser::from_args(std::iter::empty())
+    ///     .environment(Environment::dumb())
+    ///     .command(
+    ///         Command::new("remote")
+    ///             .flag(Flag::new("--tags").positional().default_value("all"))
+    ///             .subcommand(Command::new("add").positional()),
+    ///     )
+    ///     .global_flag(Flag::new("--verbose").counted());
+    ///
+    /// assert_eq!(
+    ///     parser.tree(),
+    ///     "--verbose (count)\n\
+    ///      remote\n\
+    ///      |-- --tags <VALUE> (default: all)\n\
+    ///      `-- add <POSITIONAL>\n"
+    /// );
+    /// ```
+    pub fn tree(&self) -> String {
+        let branches = match self.environment.effective_ascii() {
+            true => &ASCII,
+            false => &UNICODE,
+        };
+        let mut out = String::new();
+        for flag in sorted_flags(&self.global_flags) {
+            out.push_str(&flag_label(flag));
+            out.push('\n');
+        }
+        for command in sorted_commands(&self.commands) {
+            render(&mut out, command, "", branches);
+        }
+        out
+    }
+}
+
+/// Same as [CliParser::tree]
+impl<It> Display for CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.tree())
+    }
+}
+
+/// The command with its flags and subcommands as a tree, see [CliParser::tree]
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut out = String::new();
+        render(&mut out, self, "", &UNICODE);
+        f.write_str(&out)
+    }
+}
+
+/// Render `command` and its children, each child line prefixed with `prefix`
+fn render(out: &mut String, command: &Command, prefix: &str, branches: &Branches) {
+    out.push_str(&command_label(command));
+    out.push('\n');
+
+    let flags = sorted_flags(&command.flags);
+    let subcommands = sorted_commands(&command.subcommands);
+    let count = flags.len() + subcommands.len();
+    let mut children = 0;
+    let mut branch = |out: &mut String| {
+        children += 1;
+        let (head, tail) = match children == count {
+            true => (branches.corner, branches.blank),
+            false => (branches.tee, branches.pipe),
+        };
+        out.push_str(prefix);
+        out.push_str(head);
+        format!("{}{}", prefix, tail)
+    };
+
+    for flag in flags {
+        branch(out);
+        out.push_str(&flag_label(flag));
+        out.push('\n');
+    }
+    for sub in subcommands {
+        let prefix = branch(out);
+        render(out, sub, &prefix, branches);
+    }
+}
+
+fn command_label(command: &Command) -> String {
+    let mut label = command.id.clone();
+    if command.positional {
+        label.push_str(" <POSITIONAL>");
+    }
+    for positional in &command.positionals {
+        label.push_str(&format!(" <{}>", positional.id));
+    }
+    if command.takes_trailing {
+        label.push_str(" [-- ARGS...]");
+    }
+
+    let mut notes = Vec::new();
+    if command.hidden {
+        notes.push("hidden".to_string());
+    }
+    if !command.capabilities.is_empty() {
+        notes.push(format!("requires: {}", command.capabilities.join(", ")));
+    }
+    with_notes(label, notes)
+}
+
+fn flag_label(flag: &Flag) -> String {
+    let label = flag_usage(flag);
+
+    let mut notes = Vec::new();
+    if flag.kind == FlagKind::Count {
+        notes.push("count".to_string());
+    }
+    if flag.required {
+        notes.push("required".into());
+    }
+    if let Some(default) = &flag.default_val {
+        notes.push(format!("default: {}", default));
+    }
+    if flag.exclusive {
+        notes.push("exclusive".into());
+    }
+    if flag.hidden {
+        notes.push("hidden".into());
+    }
+    if flag.deprecated.is_some() {
+        notes.push("deprecated".into());
+    }
+    if !flag.capabilities.is_empty() {
+        notes.push(format!("requires: {}", flag.capabilities.join(", ")));
+    }
+    with_notes(label, notes)
+}
+
+fn with_notes(label: String, notes: Vec<String>) -> String {
+    match notes.is_empty() {
+        true => label,
+        false => format!("{} ({})", label, notes.join(", ")),
+    }
+}
+
+fn sorted_commands(commands: &HashMap<String, Command>) -> Vec<&Command> {
+    let mut commands: Vec<&Command> = commands.values().collect();
+    commands.sort_by(|a, b| {
+        display_key(a.display_order, &a.id).cmp(&display_key(b.display_order, &b.id))
+    });
+    commands
+}
+
+fn sorted_flags(flags: &HashMap<String, Flag>) -> Vec<&Flag> {
+    let mut flags: Vec<&Flag> = flags.values().collect();
+    flags.sort_by(|a, b| {
+        display_key(a.display_order, &a.id).cmp(&display_key(b.display_order, &b.id))
+    });
+    flags
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_tree() {
+        let parser = CliParser::from_args(std::iter::empty())
+            .environment(Environment {
+                ascii: Some(false),
+                ..Default::default()
+            })
+            .command(
+                Command::new("remote")
+                    .requires_capability("net")
+                    .flag(Flag::new("--verbose"))
+                    .subcommand(
+                        Command::new("add")
+                            .positional_arg(Positional::new("NAME"))
+                            .positional_arg(Positional::new("URL"))
+                            .flag(Flag::new("--fetch").required()),
+                    )
+                    .subcommand(Command::new("prune").hidden()),
+            )
+            .command(Command::new("exec").trailing_args())
+            .global_flag(Flag::new("--help").exclusive());
+
+        assert_eq!(
+            parser.to_string(),
+            concat!(
+                "--help (exclusive)\n",
+                "exec [-- ARGS...]\n",
+                "remote (requires: net)\n",
+                "├── --verbose\n",
+                "├── add <NAME> <URL>\n",
+                "│   └── --fetch (required)\n",
+                "└── prune (hidden)\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! Composable validators for flag and positional values.
+//!
+//! ## Example
+//! ```
+//! # use cli_parser::*;
+//! use cli_parser::validate::{all, len, not, one_of};
+//!
+//! let name = all([len(1..=16), not(one_of(["root", "admin"]))]);
+//! let flag = Flag::new("--user").positional().validate_with(name.clone());
+//!
+//! assert!(name.check("alice").is_ok());
+//! assert_eq!(
+//!     name.check("root").unwrap_err(),
+//!     "'root' must not be one of: root, admin"
+//! );
+//! ```
+use std::{
+    fmt::Debug,
+    ops::{Bound, RangeBounds},
+    path::Path,
+    sync::Arc,
+};
+
+type CheckFn = dyn Fn(&str) -> Result<(), String> + Send + Sync;
+
+/// A named rule values must satisfy, see [crate::Flag::validate_with] and [crate::Positional::validate_with].
+///
+/// Failures are reported as [crate::ParseError::Validation], with a message naming the rule
+#[derive(Clone)]
+pub struct Validator {
+    // Description of the rule, like `one of: a, b`
+    rule: String,
+    check: Arc<CheckFn>,
+    // Reads the filesystem, skipped by [crate::CliParser::sandboxed]
+    fs: bool,
+    // The only valid values, offered by generated completions
+    choices: Option<Vec<String>>,
+}
+
+impl Validator {
+    /// Create a validator from a predicate, `rule` describes what valid values are
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::validate::Validator;
+    /// let lowercase = Validator::new("lowercase", |v| v.chars().all(|c| !c.is_uppercase()));
+    /// assert_eq!(lowercase.check("Hi").unwrap_err(), "'Hi' must be lowercase");
+    /// ```
+    pub fn new<F>(rule: &str, check: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        let message = match rule.strip_prefix("not ") {
+            Some(rule) => format!("must not be {}", rule),
+            None => format!("must be {}", rule),
+        };
+        Self {
+            rule: rule.into(),
+            check: Arc::new(move |v| match check(v) {
+                true => Ok(()),
+                false => Err(format!("'{}' {}", v, message)),
+            }),
+            fs: false,
+            choices: None,
+        }
+    }
+
+    /// Create a validator from a closure returning the full error message,
+    /// e.g. for domain checks like "must be an existing git ref"
+    ///
+    /// ## Example
+    /// ```
+    /// # use cli_parser::validate::Validator;
+    /// let even = Validator::from_fn(|v| match v.parse::<u32>() {
+    ///     Ok(n) if n % 2 == 0 => Ok(()),
+    ///     _ => Err(format!("'{}' is not an even number", v)),
+    /// });
+    /// assert_eq!(even.check("3").unwrap_err(), "'3' is not an even number");
+    /// ```
+    pub fn from_fn<F>(check: F) -> Self
+    where
+        F: Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        Self {
+            rule: "custom".into(),
+            check: Arc::new(check),
+            fs: false,
+            choices: None,
+        }
+    }
+
+    /// Description of the rule
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// The only valid values, for rules like [one_of]
+    pub fn choices(&self) -> Option<&[String]> {
+        self.choices.as_deref()
+    }
+
+    /// If `value` satisfies the rule
+    pub fn is_valid(&self, value: &str) -> bool {
+        self.check(value).is_ok()
+    }
+
+    /// Check `value`, returning a message naming the failing rule
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        (self.check)(value)
+    }
+
+    /// If the rule reads the filesystem, like [existing_file]
+    pub(crate) fn reads_fs(&self) -> bool {
+        self.fs
+    }
+}
+
+impl Debug for Validator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Validator").field(&self.rule).finish()
+    }
+}
+
+/// Valid if all `validators` are valid, reports the first failing rule
+pub fn all(validators: impl IntoIterator<Item = Validator>) -> Validator {
+    let validators: Vec<Validator> = validators.into_iter().collect();
+    Validator {
+        rule: join_rules(&validators, " and "),
+        fs: validators.iter().any(|r| r.fs),
+        choices: None,
+        check: Arc::new(move |v| validators.iter().try_for_each(|r| r.check(v))),
+    }
+}
+
+/// Valid if any of `validators` is valid
+pub fn any(validators: impl IntoIterator<Item = Validator>) -> Validator {
+    let validators: Vec<Validator> = validators.into_iter().collect();
+    let rule = join_rules(&validators, " or ");
+    let fs = validators.iter().any(|r| r.fs);
+    Validator {
+        fs,
+        ..Validator::new(&rule, move |v| validators.iter().any(|r| r.is_valid(v)))
+    }
+}
+
+/// Valid if `validator` is not valid
+pub fn not(validator: Validator) -> Validator {
+    let rule = match validator.rule.strip_prefix("not ") {
+        Some(rule) => rule.to_string(),
+        None => format!("not {}", validator.rule),
+    };
+    Validator {
+        fs: validator.fs,
+        ..Validator::new(&rule, move |v| !validator.is_valid(v))
+    }
+}
+
+/// Valid if the number of characters is in `range`
+pub fn len(range: impl RangeBounds<usize> + Send + Sync + 'static) -> Validator {
+    let min = match range.start_bound() {
+        Bound::Included(&min) => Some(min),
+        Bound::Excluded(&min) => Some(min + 1),
+        Bound::Unbounded => None,
+    };
+    let max = match range.end_bound() {
+        Bound::Included(&max) => Some(max),
+        Bound::Excluded(&max) => Some(max.saturating_sub(1)),
+        Bound::Unbounded => None,
+    };
+    let rule = match (min, max) {
+        (Some(min), Some(max)) => format!("{} to {} characters long", min, max),
+        (Some(min), None) => format!("at least {} characters long", min),
+        (None, Some(max)) => format!("at most {} characters long", max),
+        (None, None) => "any length".into(),
+    };
+    Validator::new(&rule, move |v| range.contains(&v.chars().count()))
+}
+
+/// Valid if equal to one of `values`
+pub fn one_of<S: AsRef<str>>(values: impl IntoIterator<Item = S>) -> Validator {
+    let values: Vec<String> = values.into_iter().map(|v| v.as_ref().to_string()).collect();
+    let rule = format!("one of: {}", values.join(", "));
+    let choices = Some(values.clone());
+    Validator {
+        choices,
+        ..Validator::new(&rule, move |v| values.iter().any(|allowed| allowed == v))
+    }
+}
+
+/// Valid if `pattern` matches, behind the `regex` feature
+///
+/// ## Panics
+/// If `pattern` is not a valid regex
+#[cfg(feature = "regex")]
+pub fn matches(pattern: &str) -> Validator {
+    let regex = regex::Regex::new(pattern).expect("invalid regex pattern");
+    let rule = format!("matching the pattern `{}`", pattern);
+    Validator::new(&rule, move |v| regex.is_match(v))
+}
+
+/// Valid if the path is an existing file
+pub fn existing_file() -> Validator {
+    Validator {
+        rule: "an existing file".into(),
+        check: Arc::new(|v| match Path::new(v).metadata() {
+            Ok(meta) if meta.is_file() => Ok(()),
+            Ok(_) => Err(format!("'{}' is not a file", v)),
+            Err(e) => Err(format!("'{}' can not be read: {}", v, e)),
+        }),
+        fs: true,
+        choices: None,
+    }
+}
+
+/// Valid if the path is an existing directory
+pub fn existing_dir() -> Validator {
+    Validator {
+        rule: "an existing directory".into(),
+        check: Arc::new(|v| match Path::new(v).metadata() {
+            Ok(meta) if meta.is_dir() => Ok(()),
+            Ok(_) => Err(format!("'{}' is not a directory", v)),
+            Err(e) => Err(format!("'{}' can not be read: {}", v, e)),
+        }),
+        fs: true,
+        choices: None,
+    }
+}
+
+/// Valid if a file can be created at the path, or already exists there,
+/// meaning the parent directory exists
+pub fn creatable_path() -> Validator {
+    Validator {
+        rule: "a creatable path".into(),
+        check: Arc::new(|v| {
+            let path = Path::new(v);
+            if path.is_dir() {
+                return Err(format!("'{}' is a directory", v));
+            }
+            let parent = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            match parent.is_dir() {
+                true => Ok(()),
+                false => Err(format!(
+                    "'{}' can not be created, '{}' is not a directory",
+                    v,
+                    parent.display()
+                )),
+            }
+        }),
+        fs: true,
+        choices: None,
+    }
+}
+
+fn join_rules(validators: &[Validator], separator: &str) -> String {
+    validators
+        .iter()
+        .map(|v| v.rule.as_str())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_combinators() {
+        let slug = all([len(..=8), not(one_of(["new"]))]);
+        assert!(slug.check("deploy").is_ok());
+        assert_eq!(
+            slug.check("new").unwrap_err(),
+            "'new' must not be one of: new"
+        );
+        assert_eq!(
+            slug.check("too-long-slug").unwrap_err(),
+            "'too-long-slug' must be at most 8 characters long"
+        );
+
+        let level = any([one_of(["debug", "info"]), len(0..1)]);
+        assert!(level.check("").is_ok());
+        assert!(level.check("info").is_ok());
+        assert_eq!(
+            level.check("warn").unwrap_err(),
+            "'warn' must be one of: debug, info or 0 to 0 characters long"
+        );
+
+        assert!(not(not(len(3..))).check("abc").is_ok());
+    }
+
+    #[test]
+    fn test_paths() {
+        let dir = std::env::temp_dir();
+        let dir = dir.to_str().unwrap();
+        let missing = format!("{}/cli_parser_missing/file", dir);
+        let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+
+        assert!(existing_file().check(manifest).is_ok());
+        assert_eq!(
+            existing_file().check(dir).unwrap_err(),
+            format!("'{}' is not a file", dir)
+        );
+        assert!(existing_dir().check(dir).is_ok());
+        assert!(existing_dir().check(&missing).is_err());
+
+        assert!(creatable_path().check("out.txt").is_ok());
+        assert!(creatable_path().check(manifest).is_ok());
+        assert_eq!(
+            creatable_path().check(&missing).unwrap_err(),
+            format!(
+                "'{}' can not be created, '{}/cli_parser_missing' is not a directory",
+                missing, dir
+            )
+        );
+        assert!(creatable_path().check(dir).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_matches() {
+        let slug = matches(r"^[a-z0-9-]+$");
+        assert!(slug.check("my-repo-2").is_ok());
+        assert_eq!(
+            slug.check("My Repo").unwrap_err(),
+            "'My Repo' must be matching the pattern `^[a-z0-9-]+$`"
+        );
+    }
+}
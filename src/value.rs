@@ -0,0 +1,180 @@
+use std::{any::Any, fmt::Debug, fmt::Display, marker::PhantomData, str::FromStr, sync::Arc};
+
+use crate::{Command, Flag, Matches};
+
+/// A converted value, produced by a [ValueParser]
+pub(crate) type AnyValue = Arc<dyn Any + Send + Sync>;
+
+type ParseFn = dyn Fn(&str) -> Result<AnyValue, String> + Send + Sync;
+
+/// Converts a raw string argument into a typed value at parse time.
+///
+/// Usually constructed implicitly through the `value_parser` builders,
+/// e.g. [crate::Positional::value_parser].
+#[derive(Clone)]
+pub struct ValueParser(Arc<ParseFn>);
+
+impl ValueParser {
+    /// Wrap any fallible conversion function, e.g. `usize::from_str`
+    pub fn new<T, E, F>(f: F) -> Self
+    where
+        T: Any + Send + Sync,
+        E: Display,
+        F: Fn(&str) -> Result<T, E> + Send + Sync + 'static,
+    {
+        Self(Arc::new(move |s| {
+            f(s).map(|v| Arc::new(v) as AnyValue)
+                .map_err(|e| e.to_string())
+        }))
+    }
+
+    pub(crate) fn parse(&self, raw: &str) -> Result<AnyValue, String> {
+        (self.0)(raw)
+    }
+}
+
+impl Debug for ValueParser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ValueParser")
+    }
+}
+
+/// A single failed conversion of a value by a [ValueParser]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueError {
+    /// Index of the value among all values of the flag
+    pub index: usize,
+    /// Index of the value in the input args, `None` for default values
+    pub position: Option<usize>,
+    /// The value that failed to convert
+    pub value: String,
+    /// Why the conversion failed
+    pub reason: String,
+}
+
+impl Display for ValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}': {}", self.value, self.reason)
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+/// A flag whose value type `T` is declared with its definition, so reading
+/// the parsed value needs no type annotation and can not ask for the wrong type.
+///
+/// Values that do not convert to `T` fail parsing with [crate::ParseError::InvalidValues]
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let port = TypedFlag::<u16>::new("--port");
+/// let cmd = CliParser::parse_from(["serve", "--port", "8080"])
+///     .command(Command::new("serve").flag(port.flag().default_value("80")))
+///     .parse()
+///     .unwrap();
+///
+/// let value: Option<&u16> = port.get(&cmd);
+/// assert_eq!(value, Some(&8080));
+/// ```
+pub struct TypedFlag<T> {
+    id: String,
+    value_type: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedFlag<T>
+where
+    T: FromStr + Any + Send + Sync,
+    T::Err: Display,
+{
+    /// Create a handle for the flag `id` taking a `T`
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: Flag::new(id).id,
+            value_type: PhantomData,
+        }
+    }
+
+    /// The [Flag] definition converting its value to a `T`, to add to a
+    /// [Command] or to the global flags. It can be further customized like any flag
+    pub fn flag(&self) -> Flag {
+        Flag::new(&self.id).value_parser(T::from_str)
+    }
+
+    /// Id of the flag
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The parsed value of the flag in `command`
+    pub fn get<'a>(&self, command: &'a Command) -> Option<&'a T> {
+        command
+            .parsed_flags
+            .get(&self.id)
+            .and_then(|f| f.get::<T>())
+    }
+
+    /// The parsed value of the flag in `matches`
+    pub fn get_from<'a>(&self, matches: &'a Matches) -> Option<&'a T> {
+        matches.flag_as::<T>(&self.id)
+    }
+}
+
+impl<T> Clone for TypedFlag<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            value_type: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for TypedFlag<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TypedFlag")
+            .field(&self.id)
+            .field(&std::any::type_name::<T>())
+            .finish()
+    }
+}
+
+/// A tuple of typed values taken by a [crate::Flag::tuple] flag, see [crate::Flag::get_tuple]
+pub trait TupleValue: Sized {
+    /// Downcast and clone each of `values`, `None` if the count or any type does not match
+    fn from_values(values: &[&dyn Any]) -> Option<Self>;
+}
+
+/// The last `arity` of `typed_values` as a tuple, see [crate::Flag::get_tuple]
+pub(crate) fn last_tuple<T: TupleValue>(typed_values: &[AnyValue], arity: usize) -> Option<T> {
+    let start = typed_values.len().checked_sub(arity)?;
+    let values: Vec<&dyn Any> = typed_values[start..]
+        .iter()
+        .map(|v| v.as_ref() as &dyn Any)
+        .collect();
+    T::from_values(&values)
+}
+
+impl<A: Any + Clone, B: Any + Clone> TupleValue for (A, B) {
+    fn from_values(values: &[&dyn Any]) -> Option<Self> {
+        match values {
+            [a, b] => Some((
+                a.downcast_ref::<A>()?.clone(),
+                b.downcast_ref::<B>()?.clone(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl<A: Any + Clone, B: Any + Clone, C: Any + Clone> TupleValue for (A, B, C) {
+    fn from_values(values: &[&dyn Any]) -> Option<Self> {
+        match values {
+            [a, b, c] => Some((
+                a.downcast_ref::<A>()?.clone(),
+                b.downcast_ref::<B>()?.clone(),
+                c.downcast_ref::<C>()?.clone(),
+            )),
+            _ => None,
+        }
+    }
+}
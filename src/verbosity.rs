@@ -0,0 +1,134 @@
+use crate::{CliParser, Command, Flag, Matches};
+
+/// Id of the counted global flag raising the [Verbosity]
+pub const VERBOSE: &str = "--verbose";
+/// Id of the counted global flag lowering the [Verbosity]
+pub const QUIET: &str = "--quiet";
+
+/// How chatty the program should be, from the [VERBOSE] and [QUIET] flags
+/// registered by [CliParser::verbosity]
+///
+/// ## Example
+/// ```
+/// # use cli_parser::*;
+/// let cmd = CliParser::parse_from(["build", "--verbose", "--verbose"])
+///     .verbosity()
+///     .command(Command::new("build"))
+///     .parse()
+///     .unwrap();
+/// assert_eq!(cmd.verbosity().level(), 2);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Verbosity {
+    verbose: usize,
+    quiet: usize,
+}
+
+impl Verbosity {
+    /// Create a verbosity from the number of times each flag was passed
+    pub fn new(verbose: usize, quiet: usize) -> Self {
+        Self { verbose, quiet }
+    }
+
+    /// The number of [VERBOSE] flags minus the number of [QUIET] flags,
+    /// `0` when neither was passed
+    pub fn level(&self) -> isize {
+        self.verbose as isize - self.quiet as isize
+    }
+
+    /// If the level is below the default, output beyond errors should be left out
+    pub fn is_quiet(&self) -> bool {
+        self.level() < 0
+    }
+
+    /// The [log::LevelFilter] for the level, behind the `log` feature,
+    /// `Warn` by default, one step per flag, from `Off` to `Trace`
+    #[cfg(feature = "log")]
+    pub fn log_level_filter(&self) -> log::LevelFilter {
+        match self.level() {
+            ..=-2 => log::LevelFilter::Off,
+            -1 => log::LevelFilter::Error,
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    }
+
+    fn from_flags(flag: impl Fn(&str) -> usize) -> Self {
+        Self::new(flag(VERBOSE), flag(QUIET))
+    }
+}
+
+impl<It> CliParser<It>
+where
+    It: Iterator<Item = String>,
+{
+    /// Register the counted [VERBOSE] and [QUIET] global flags, read the resolved
+    /// level with [Command::verbosity] or [Matches::verbosity]
+    pub fn verbosity(self) -> Self {
+        self.global_flag(Flag::new(VERBOSE).counted())
+            .global_flag(Flag::new(QUIET).counted())
+    }
+}
+
+impl Command {
+    /// The [Verbosity] from the global flags registered by [CliParser::verbosity],
+    /// on the outermost command
+    pub fn verbosity(&self) -> Verbosity {
+        Verbosity::from_flags(|id| self.occurrences_of(id))
+    }
+}
+
+impl Matches {
+    /// The [Verbosity] from the global flags registered by [CliParser::verbosity],
+    /// on the outermost matches
+    pub fn verbosity(&self) -> Verbosity {
+        Verbosity::from_flags(|id| self.occurrences_of(id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verbosity() {
+        let level = |args: &str| {
+            CliParser::parse_from(args.split_whitespace())
+                .verbosity()
+                .command(Command::new("run").subcommand(Command::new("all")))
+                .parse_matches()
+                .unwrap()
+                .verbosity()
+        };
+        assert_eq!(level("run").level(), 0);
+        assert_eq!(level("--verbose run all --verbose --verbose").level(), 3);
+        assert_eq!(
+            level("run --verbose --quiet all --quiet"),
+            Verbosity::new(1, 2)
+        );
+        assert!(level("run --quiet").is_quiet());
+    }
+
+    #[test]
+    #[cfg(feature = "log")]
+    fn test_log_level_filter() {
+        assert_eq!(
+            Verbosity::new(0, 0).log_level_filter(),
+            log::LevelFilter::Warn
+        );
+        assert_eq!(
+            Verbosity::new(2, 0).log_level_filter(),
+            log::LevelFilter::Debug
+        );
+        assert_eq!(
+            Verbosity::new(9, 0).log_level_filter(),
+            log::LevelFilter::Trace
+        );
+        assert_eq!(
+            Verbosity::new(0, 5).log_level_filter(),
+            log::LevelFilter::Off
+        );
+    }
+}